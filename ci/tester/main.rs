@@ -7,6 +7,10 @@
 //! pico-php-tester parse -b <path> <check> : Parse&Print the files given in the <path> and try to locate the origin of the first error
 //!                                           using binary search techniques
 //! pico-php-tester parse-file <file>       : Parse&Print a given file
+//! pico-php-tester token-dump <file>       : Print the tokens of a given file like PHP's token_get_all() as JSON
+//! pico-php-tester min-version <file>      : Print the oldest PHP version able to parse a given file
+//!
+//! `--php-version <X.Y>` rejects syntax which isn't supported by the given PHP version
 //!
 extern crate pico_php_parser;
 extern crate glob;
@@ -23,7 +27,7 @@ use std::process::Command;
 use glob::glob;
 use wait_timeout::ChildExt;
 use clap::{Arg, App, SubCommand};
-use pico_php_parser::{Parser, PrettyPrinter};
+use pico_php_parser::{Parser, ParserOptions, PhpVersion, PrettyPrinter, min_version, token_get_all, tokens_to_json};
 
 fn main() {
     let matches = App::new("PicoPhpParser-Tester")
@@ -31,6 +35,11 @@ fn main() {
         .arg(Arg::with_name("binary-search")
             .short("b")
         )
+        .arg(Arg::with_name("php-version")
+            .long("php-version")
+            .takes_value(true)
+            .validator(|version| version.parse::<PhpVersion>().map(|_| ()).map_err(|err| err.to_owned()))
+        )
         .subcommand(SubCommand::with_name("parse")
             .about("parses files in a directory")
             .arg(Arg::with_name("DIR").index(1).required(true))
@@ -41,10 +50,24 @@ fn main() {
               .arg(Arg::with_name("FILE").index(1).required(true))
               .arg(Arg::with_name("CHECK").required(false))
         )
+        .subcommand(SubCommand::with_name("token-dump")
+              .about("prints the tokens of a file in the format of token_get_all()")
+              .arg(Arg::with_name("FILE").index(1).required(true))
+        )
+        .subcommand(SubCommand::with_name("min-version")
+              .about("prints the oldest PHP version able to parse a file and the syntax requiring it")
+              .arg(Arg::with_name("FILE").index(1).required(true))
+        )
         .get_matches();
 
     // perform the checks with binary search / divide and conquer
     let do_binary_search = matches.occurrences_of("binary-search") > 0;
+    let php_version = matches.value_of("php-version");
+    let mut options = ParserOptions::default();
+    if let Some(version) = php_version {
+        // already checked by the validator of the argument
+        options.version = version.parse::<PhpVersion>().unwrap();
+    }
 
     if let Some(matches) = matches.subcommand_matches("parse") {
         let dir = matches.value_of("DIR").unwrap();
@@ -71,6 +94,9 @@ fn main() {
 
         let run_parse_file = |entry: &str, no_check:bool| {
             let mut cmd = Command::new(env::current_exe().unwrap());
+            if let Some(version) = php_version {
+                cmd.arg("--php-version").arg(version);
+            }
             cmd
                 .arg("parse-file")
                 .arg(entry);
@@ -157,20 +183,48 @@ fn main() {
 
     if let Some(matches) = matches.subcommand_matches("parse-file") {
         let file = matches.value_of("FILE").unwrap();
-        parse_file(file, matches.value_of("CHECK"));
+        parse_file(file, matches.value_of("CHECK"), &options);
+        return;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("token-dump") {
+        let file = matches.value_of("FILE").unwrap();
+        let mut src = vec![];
+        File::open(file).unwrap().read_to_end(&mut src).unwrap();
+        match token_get_all(&src) {
+            Ok(tokens) => print!("{}", tokens_to_json(&tokens)),
+            Err(err) => panic!("ERROR: {:?}", err),
+        }
+        return;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("min-version") {
+        let file = matches.value_of("FILE").unwrap();
+        let mut src = vec![];
+        File::open(file).unwrap().read_to_end(&mut src).unwrap();
+        let ast = match Parser::parse_bytes(&src) {
+            Ok(ast) => ast,
+            Err(err) => panic!("ERROR: {}", err.error_message(None)),
+        };
+        let result = min_version(&ast);
+        println!("PHP {}", result.version);
+        for feature_use in result.required_by {
+            let line = src[..feature_use.span.start as usize].iter().filter(|&&c| c == b'\n').count() + 1;
+            println!("{}:{}: {}", file, line, feature_use.feature);
+        }
         return;
     }
 
     panic!("invalid arguments!")
 }
 
-fn parse_file(arg: &str, check_cmd: Option<&str>) {
+fn parse_file(arg: &str, check_cmd: Option<&str>, options: &ParserOptions) {
     println!("parsing {}", arg);
     let mut s = String::new();
     let ast = {
         let mut f = File::open(arg).unwrap();
         f.read_to_string(&mut s).unwrap();
-        match Parser::parse_str(&s) {
+        match Parser::parse_str_with_options(&s, options) {
             Ok(ast) => ast,
             Err(err) => {
                 panic!("ERROR: {}", err.error_message(Some(&s)));
@@ -1,6 +1,9 @@
+use std::borrow::Borrow;
+use std::mem;
 use std::rc::Rc;
 use tokenizer::Span;
-use interner::RcStr;
+use interner::{Interner, RcStr};
+use visitor::{self, Visitor};
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum ParsedItem {
@@ -72,6 +75,8 @@ pub enum Op {
     BitwiseExclOr,
     /// spaceship operator, <=>
     Spaceship,
+    /// null coalescing operator, ??
+    Coalesce,
     Sl,
     Sr,
 }
@@ -103,6 +108,7 @@ pub enum Visibility {
 pub enum ClassModifier {
     Abstract = 1<<0,
     Final = 1<<1,
+    Readonly = 1<<2,
 }
 
 #[derive(Copy, Clone, Debug, PartialEq)]
@@ -137,6 +143,7 @@ pub enum MemberModifier {
     Static = 1<<3,
     Abstract = 1<<4,
     Final = 1<<5,
+    Readonly = 1<<6,
 }
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub struct MemberModifiers(u8);
@@ -162,9 +169,66 @@ impl MemberModifiers {
 #[derive(Clone, Debug, PartialEq)]
 pub struct Expr(pub Expr_, pub Span);
 
+impl Expr {
+    /// the base of the nullsafe access (`?->`) which short-circuits this expression, if any
+    pub fn nullsafe_base(&self) -> Option<&Expr> {
+        match self.0 {
+            Expr_::ObjMember(ref base, _, true) => Some(base),
+            Expr_::ObjMember(ref base, _, false) | Expr_::StaticMember(ref base, _) |
+            Expr_::ArrayIdx(ref base, _) | Expr_::Call(ref base, _) => base.nullsafe_base(),
+            _ => None,
+        }
+    }
+
+    /// whether an object is created (`new`) anywhere in this expression, which is only allowed in
+    /// some initializers (e.g. parameter defaults) since PHP 8.1
+    pub fn contains_new(&self) -> bool {
+        let mut finder = NewFinder { found: false };
+        finder.visit_expr(self);
+        finder.found
+    }
+}
+
+/// finds a `new` in an expression
+struct NewFinder {
+    found: bool,
+}
+
+impl Visitor for NewFinder {
+    fn visit_expr(&mut self, expr: &Expr) {
+        if let Expr_::New(_, _) = expr.0 {
+            self.found = true;
+        }
+        visitor::walk_expr(self, expr);
+    }
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct Stmt(pub Stmt_, pub Span);
 
+/// an argument of a function call or object creation
+#[derive(Clone, Debug, PartialEq)]
+pub struct Argument {
+    /// the parameter a named argument (e.g. `limit: 10`) is passed to
+    pub name: Option<RcStr>,
+    /// whether the value is unpacked into several arguments (`...$rest`)
+    pub unpack: bool,
+    /// call-time pass-by-reference (`&$a`), which was removed in PHP 5.4
+    pub by_ref: bool,
+    pub value: Expr,
+}
+
+impl From<Expr> for Argument {
+    fn from(value: Expr) -> Argument {
+        Argument {
+            name: None,
+            unpack: false,
+            by_ref: false,
+            value: value,
+        }
+    }
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct Block(pub Vec<Stmt>);
 
@@ -198,8 +262,10 @@ pub enum Expr_ {
     Path(Path),
     String(RcStr),
     BinaryString(Rc<Vec<u8>>),
-    Int(i64),
-    Double(f64),
+    /// an integer literal, .1 is set if it contains separators (e.g. `1_000`)
+    Int(i64, bool),
+    /// a floating point literal, .1 is set if it contains separators (e.g. `1_000.5`)
+    Double(f64, bool),
     Array(Vec<(Option<Expr>, Expr)>),
     Variable(Variable),
     Reference(Box<Expr>),
@@ -210,19 +276,35 @@ pub enum Expr_ {
 
     Include(IncludeTy, Box<Expr>),
     ArrayIdx(Box<Expr>, Vec<Option<Expr>>),
-    ObjMember(Box<Expr>, Vec<Expr>),
+    /// a chain of property fetches (a method call wraps it in a `Call`),
+    /// if nullsafe (.2, `?->`) and the base is null, the whole chain including the calls and
+    /// array/member accesses around this node evaluates to null without being evaluated
+    ObjMember(Box<Expr>, Vec<Expr>, bool),
     StaticMember(Box<Expr>, Vec<Expr>),
-    Call(Box<Expr>, Vec<Expr>),
-    New(Box<Expr>, Vec<Expr>),
+    Call(Box<Expr>, Vec<Argument>),
+    /// `strlen(...)`, creates a closure of the callee
+    FirstClassCallable(Box<Expr>),
+    New(Box<Expr>, Vec<Argument>),
     /// variadic unpack ...
     Unpack(Box<Expr>),
     UnaryOp(UnaryOp, Box<Expr>),
     BinaryOp(Op, Box<Expr>, Box<Expr>),
     InstanceOf(Box<Expr>, Box<Expr>),
     Cast(Ty, Box<Expr>),
-    Yield(Option<Box<Expr>>),
-    /// an anonymous function
-    Function(FunctionDecl),
+    /// a string containing interpolated expressions (e.g. "Hello $name!"), a shell command (`ls $dir`), or a heredoc or nowdoc
+    Interpolated(StringKind, Vec<InterpolatedPart>),
+    /// `yield key => value`, a key (.0) is only allowed together with a value (.1)
+    Yield(Option<Box<Expr>>, Option<Box<Expr>>),
+    /// `yield from`, delegates to another generator or traversable
+    YieldFrom(Box<Expr>),
+    /// an anonymous function, .1 is set if it's `static` (so `$this` isn't bound)
+    Function(FunctionDecl, bool),
+    /// an arrow function (e.g. fn($x) => $x * 2) returning the expression in .1,
+    /// the variables it uses from the parent scope are captured by-value (in `usev`), `static` if .2 is set
+    ArrowFunction(FunctionDecl, Box<Expr>, bool),
+    /// compares the subject (.0) strictly against the conditions of each arm
+    /// and evaluates to the expression of the first matching arm
+    Match(Box<Expr>, Vec<MatchArm>),
 
     // statements
     Assign(Box<Expr>, Box<Expr>),
@@ -282,13 +364,48 @@ pub enum Ty {
     Double,
     String,
     Object(Option<Path>),
+    Void,
+    Iterable,
+    /// a member of union types, also used for the `(unset)` cast
+    Null,
+    /// only valid as a member of union types
+    False,
+    /// only valid as a return type
+    Never,
 }
 
-/// A type and flag describing whether it's nullable
+/// The type of a parameter, return value or property
 #[derive(Clone, Debug, PartialEq)]
-pub enum NullableTy {
-    NonNullable(Ty),
+pub enum TypeExpr {
+    Simple(Ty),
+    /// `?Ty`
     Nullable(Ty),
+    /// `A|B`, the members are simple types or (parenthesized) intersections
+    Union(Vec<TypeExpr>),
+    /// `A&B`, only class types may be intersected
+    Intersection(Vec<Ty>),
+}
+
+/// The quoting style of an interpolated string
+#[derive(Clone, Debug, PartialEq)]
+pub enum StringKind {
+    /// "..."
+    DoubleQuote,
+    /// <<<LABEL ... LABEL, the label (.0), the indentation of the end-tag (.1) and whether code follows the end-tag
+    /// on its line (.2) are stored, the latter two require PHP >= 7.3
+    HereDoc(RcStr, RcStr, bool),
+    /// <<<'LABEL' ... LABEL, like a heredoc without escape sequences and interpolation
+    NowDoc(RcStr, RcStr, bool),
+    /// `...`, executed as a shell command
+    Backquote,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum InterpolatedPart {
+    Literal(RcStr),
+    /// a literal containing invalid UTF-8 sequences
+    BinaryLiteral(Rc<Vec<u8>>),
+    Expr(Expr),
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -305,19 +422,31 @@ pub enum TraitUse {
     As(Option<Path>, RcStr, MemberModifiers, Option<RcStr>),
 }
 
+/// metadata attached to a declaration (e.g. `#[Route('/x')]`),
+/// the attributes of a group (`#[A, B]`) are stored one after another
+#[derive(Clone, Debug, PartialEq)]
+pub struct Attribute {
+    pub name: Path,
+    pub args: Vec<Argument>,
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct ParamDefinition {
+    pub attributes: Vec<Attribute>,
+    /// The visibility of a promoted constructor parameter, empty otherwise
+    pub modifiers: MemberModifiers,
     pub name: RcStr,
     pub as_ref: bool,
     pub variadic: bool,
     /// The type of the parameter
-    pub ty: Option<NullableTy>,
+    pub ty: Option<TypeExpr>,
     /// The default value for the parameter
     pub default: Option<Expr>,
 }
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct FunctionDecl {
+    pub attributes: Vec<Attribute>,
     pub params: Vec<ParamDefinition>,
     pub body: Option<Block>,
     /// A list of variables to pass from the parent scope to the scope of this function
@@ -325,11 +454,41 @@ pub struct FunctionDecl {
     /// the boolean indicates whether to bind by-reference (true)
     pub usev: Vec<(bool, RcStr)>,
     pub ret_ref: bool,
-    pub ret_ty: Option<NullableTy>,
+    pub ret_ty: Option<TypeExpr>,
+}
+
+/// finds a yield in a function body, ignoring nested functions
+struct YieldFinder {
+    found: bool,
+}
+
+impl Visitor for YieldFinder {
+    fn visit_expr(&mut self, expr: &Expr) {
+        match expr.0 {
+            Expr_::Yield(_, _) | Expr_::YieldFrom(_) => self.found = true,
+            Expr_::ArrowFunction(_, _, _) => (),
+            _ => visitor::walk_expr(self, expr),
+        }
+    }
+
+    fn visit_function_decl(&mut self, _decl: &FunctionDecl) {}
+}
+
+impl FunctionDecl {
+    /// whether the body contains a yield, which makes the function return a Generator.
+    /// the body of an arrow function isn't part of its declaration and therefore not considered
+    pub fn is_generator(&self) -> bool {
+        let mut finder = YieldFinder { found: false };
+        if let Some(ref body) = self.body {
+            visitor::walk_block(&mut finder, body);
+        }
+        finder.found
+    }
 }
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct ClassDecl {
+    pub attributes: Vec<Attribute>,
     pub cmod: ClassModifiers,
     pub name: RcStr,
     pub base_class: Option<Path>,
@@ -338,12 +497,71 @@ pub struct ClassDecl {
     pub members: Vec<Member>,
 }
 
+#[derive(Clone, Debug, PartialEq)]
+pub struct EnumDecl {
+    pub attributes: Vec<Attribute>,
+    pub name: RcStr,
+    /// `int` or `string` for backed enums
+    pub backing_ty: Option<Ty>,
+    pub implements: Vec<Path>,
+    pub members: Vec<Member>,
+}
+
+impl ClassDecl {
+    /// turns the promoted parameters of the constructor (e.g. `__construct(private $a)`) into properties
+    /// declared in front of it and assignments at the start of its body, the generated nodes have empty spans
+    pub fn desugar_promoted_params(&mut self) {
+        desugar_promoted_params(&mut self.members)
+    }
+}
+
+/// like `ClassDecl::desugar_promoted_params`, but for any list of members (e.g. the ones of a `Decl::Trait`)
+pub fn desugar_promoted_params(members: &mut Vec<Member>) {
+    let this_var = Interner::new().intern("this");
+    let mut desugared = Vec::with_capacity(members.len());
+    for mut member in mem::replace(members, vec![]) {
+        if let Member::Method(_, ref name, ref mut decl) = member {
+            if (name.borrow() as &str).to_lowercase() == "__construct" {
+                let mut assignments = vec![];
+                for param in &mut decl.params {
+                    if param.modifiers == MemberModifiers::none() {
+                        continue;
+                    }
+                    desugared.push(Member::Property(param.attributes.clone(), param.modifiers, param.ty.clone(), param.name.clone(), None));
+                    param.modifiers = MemberModifiers::none();
+                    let this = Expr(Expr_::Variable(this_var.clone().into()), Span::new());
+                    let property = Expr(Expr_::Path(Path::identifier(false, param.name.clone())), Span::new());
+                    let target = Expr(Expr_::ObjMember(Box::new(this), vec![property], false), Span::new());
+                    let value = Expr(Expr_::Variable(param.name.clone().into()), Span::new());
+                    let assignment = if param.as_ref {
+                        Expr_::AssignRef(Box::new(target), Box::new(value))
+                    } else {
+                        Expr_::Assign(Box::new(target), Box::new(value))
+                    };
+                    let assignment = Expr(assignment, Span::new());
+                    assignments.push(Stmt(Stmt_::Expr(assignment), Span::new()));
+                }
+                if let Some(ref mut body) = decl.body {
+                    let stmts = mem::replace(&mut body.0, assignments);
+                    body.0.extend(stmts);
+                }
+            }
+        }
+        desugared.push(member);
+    }
+    *members = desugared;
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum Member {
-    Constant(MemberModifiers, RcStr, Expr),
-    Property(MemberModifiers, RcStr, Option<Expr>),
+    /// Constant(Attributes, Modifiers, Name, Value)
+    Constant(Vec<Attribute>, MemberModifiers, RcStr, Expr),
+    /// Property(Attributes, Modifiers, Type, Name, Default)
+    Property(Vec<Attribute>, MemberModifiers, Option<TypeExpr>, RcStr, Option<Expr>),
     Method(MemberModifiers, RcStr, FunctionDecl),
     TraitUse(Vec<Path>, Vec<TraitUse>),
+    /// EnumCase(Attributes, Name, Value), the value is only given in backed enums
+    EnumCase(Vec<Attribute>, RcStr, Option<Expr>),
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -351,8 +569,11 @@ pub enum Decl {
     Namespace(Path),
     GlobalFunction(RcStr, FunctionDecl),
     Class(ClassDecl),
-    Interface(RcStr, Vec<Path>, Vec<Member>),
-    Trait(RcStr, Vec<Member>),
+    /// Interface(Attributes, Name, Extended interfaces, Members)
+    Interface(Vec<Attribute>, RcStr, Vec<Path>, Vec<Member>),
+    /// Trait(Attributes, Name, Members)
+    Trait(Vec<Attribute>, RcStr, Vec<Member>),
+    Enum(EnumDecl),
     StaticVars(Vec<(RcStr, Option<Expr>)>),
     GlobalVars(Vec<Variable>),
     // a goto jump target
@@ -361,7 +582,8 @@ pub enum Decl {
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct CatchClause {
-    pub ty: Path,
+    /// the caught exception types, several of them are separated by `|`
+    pub types: Vec<Path>,
     pub var: RcStr,
     pub block: Block,
 }
@@ -372,3 +594,12 @@ pub struct SwitchCase {
     pub default: bool,
     pub block: Block,
 }
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MatchArm {
+    /// empty for the default arm
+    pub conds: Vec<Expr>,
+    pub default: bool,
+    pub expr: Expr,
+    pub span: Span,
+}
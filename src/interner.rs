@@ -4,6 +4,7 @@ use std::collections::HashSet;
 use std::rc::Rc;
 use std::hash::BuildHasherDefault;
 use std::mem;
+use std::str;
 use fnv::FnvHasher;
 
 #[cfg(test)]
@@ -27,6 +28,64 @@ impl<T: Into<String>> From<T> for RcStr {
     }
 }
 
+/// the code point a single escaped byte is added to
+const BYTE_ESCAPE_BASE: u32 = 0x10FF00;
+
+fn is_byte_escape(c: char) -> bool {
+    c as u32 >= BYTE_ESCAPE_BASE + 0x80
+}
+
+/// decode a byte-string as UTF-8, every byte of an invalid sequence becomes the code point `U+10FF00 + byte`.
+/// the code points used for this are escaped as well when they occur in the input, which makes the mapping reversible
+fn escape_bytes(mut s: &[u8]) -> String {
+    fn push_valid(ret: &mut String, valid: &str) {
+        for c in valid.chars() {
+            if is_byte_escape(c) {
+                let mut buf = [0; 4];
+                push_escaped(ret, c.encode_utf8(&mut buf).as_bytes());
+            } else {
+                ret.push(c);
+            }
+        }
+    }
+    fn push_escaped(ret: &mut String, bytes: &[u8]) {
+        ret.extend(bytes.iter().map(|&b| ::std::char::from_u32(BYTE_ESCAPE_BASE + b as u32).unwrap()));
+    }
+
+    let mut ret = String::with_capacity(s.len());
+    loop {
+        match str::from_utf8(s) {
+            Ok(valid) => {
+                push_valid(&mut ret, valid);
+                return ret;
+            }
+            Err(err) => {
+                let (valid, rest) = s.split_at(err.valid_up_to());
+                push_valid(&mut ret, str::from_utf8(valid).unwrap());
+                let invalid_len = err.error_len().unwrap_or(rest.len());
+                push_escaped(&mut ret, &rest[..invalid_len]);
+                s = &rest[invalid_len..];
+            }
+        }
+    }
+}
+
+impl RcStr {
+    /// the bytes this string was interned from, reverses the escaping of `Interner::intern_bytes`
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut ret = Vec::with_capacity(self.0.len());
+        for c in self.0.chars() {
+            if is_byte_escape(c) {
+                ret.push((c as u32 - BYTE_ESCAPE_BASE) as u8);
+            } else {
+                let mut buf = [0; 4];
+                ret.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            }
+        }
+        ret
+    }
+}
+
 pub struct Interner {
     strs: HashSet<RcStr, BuildHasherDefault<FnvHasher>>,
 }
@@ -48,6 +107,15 @@ impl Interner {
         str_
     }
 
+    /// intern a byte-string, bytes which aren't part of a valid UTF-8 sequence are mapped one-to-one
+    /// onto the code points U+10FF80 to U+10FFFF (see `escape_bytes`), so distinct byte-strings never share a name
+    pub fn intern_bytes(&mut self, s: &[u8]) -> RcStr {
+        match str::from_utf8(s) {
+            Ok(str_) if !str_.chars().any(is_byte_escape) => self.intern(str_),
+            _ => self.intern(&escape_bytes(s)),
+        }
+    }
+
     /// get a new interner instance only containing interned strings
     /// which are also referenced externally
     pub fn compact(self) -> Interner {
@@ -79,4 +147,16 @@ mod tests {
         };
         assert_eq!(Rc::get_mut(&mut str2.0), Some(&mut "test".to_owned()));
     }
+
+    #[test]
+    fn test_intern_bytes() {
+        let mut interner = Interner::new();
+        let inputs: &[&[u8]] = &[b"caf\xe9", b"caf\xe8", "café".as_bytes(), b"\xf4\x8f\xbf\xa9", b"\xe9\xff\xc3"];
+        let names: Vec<_> = inputs.iter().map(|x| interner.intern_bytes(x)).collect();
+        for (i, name) in names.iter().enumerate() {
+            assert_eq!(&name.to_bytes()[..], inputs[i]);
+            assert!(names[i + 1..].iter().all(|x| x != name));
+        }
+        assert_eq!(names[2], interner.intern("café"));
+    }
 }
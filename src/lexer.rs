@@ -0,0 +1,120 @@
+//! public access to the token stream of a PHP file, e.g. for highlighters or formatters
+use tokenizer::Tokenizer;
+use tokens::{Token, TokenSpan, SyntaxError};
+
+/// An iterator over all tokens of a source file including trivia (whitespace, comments, open and close tags),
+/// which the parser would otherwise strip.
+/// Iteration stops at the end of the input or at the first error the lexer can't skip.
+pub struct Lexer<'a> {
+    tokenizer: Tokenizer<'a>,
+    done: bool,
+}
+
+impl<'a> Lexer<'a> {
+    pub fn new(src: &'a str) -> Lexer<'a> {
+        Lexer::from_bytes(src.as_bytes())
+    }
+
+    /// lex a source file in any ASCII compatible encoding (e.g. ISO-8859-1)
+    pub fn from_bytes(src: &'a [u8]) -> Lexer<'a> {
+        let mut tokenizer = Tokenizer::from_bytes(src);
+        tokenizer.set_trivia(true);
+        Lexer {
+            tokenizer: tokenizer,
+            done: false,
+        }
+    }
+}
+
+impl<'a> Iterator for Lexer<'a> {
+    type Item = Result<TokenSpan, SyntaxError>;
+
+    fn next(&mut self) -> Option<Result<TokenSpan, SyntaxError>> {
+        if self.done {
+            return None;
+        }
+        let old_pos = self.tokenizer.input_pos();
+        match self.tokenizer.next_token() {
+            Ok(TokenSpan(Token::End, _)) => {
+                self.done = true;
+                None
+            }
+            Ok(token) => Some(Ok(token)),
+            Err(err) => {
+                // only continue if the invalid input was skipped, else the same error would be reported forever
+                self.done = self.tokenizer.input_pos() == old_pos;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokenizer::mk_span;
+    use tokens::Span;
+
+    fn lex(src: &str) -> Vec<Result<(Token, Span), SyntaxError>> {
+        Lexer::new(src).map(|x| x.map(|TokenSpan(tok, span)| (tok, span))).collect()
+    }
+
+    #[test]
+    fn trivia() {
+        assert_eq!(lex("a<?php  $b->c; # d\n?>"), vec![
+            Ok((Token::InlineHtml("a".into()), mk_span(0usize, 1usize))),
+            Ok((Token::OpenTag, mk_span(1usize, 6usize))),
+            Ok((Token::Whitespace("  ".into()), mk_span(6usize, 8usize))),
+            Ok((Token::Variable("b".into()), mk_span(8usize, 10usize))),
+            Ok((Token::ObjectOp, mk_span(10usize, 12usize))),
+            Ok((Token::String("c".into()), mk_span(12usize, 13usize))),
+            Ok((Token::SemiColon, mk_span(13usize, 14usize))),
+            Ok((Token::Whitespace(" ".into()), mk_span(14usize, 15usize))),
+            Ok((Token::Comment(" d".into()), mk_span(15usize, 18usize))),
+            Ok((Token::Whitespace("\n".into()), mk_span(18usize, 19usize))),
+            Ok((Token::CloseTag, mk_span(19usize, 21usize))),
+        ]);
+    }
+
+    #[test]
+    fn spans_cover_source() {
+        let src = "<?php\nfunction a($b) {\n    yield  $b->c  ;\n    return \"x$b\";\n}\n";
+        let mut pos = 0;
+        for token in Lexer::new(src) {
+            let TokenSpan(_, span) = token.unwrap();
+            assert_eq!(span.start as usize, pos);
+            pos = span.end as usize;
+        }
+        assert_eq!(pos, src.len());
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(lex("\u{FEFF}<?php \\"), vec![
+            Err(SyntaxError::ByteOrderMark(mk_span(0usize, 3usize))),
+            Ok((Token::OpenTag, mk_span(3usize, 8usize))),
+            Ok((Token::Whitespace(" ".into()), mk_span(8usize, 9usize))),
+            Ok((Token::NsSeparator, mk_span(9usize, 10usize))),
+        ]);
+        // unknown characters are skipped
+        assert_eq!(lex("<?php #\n\x01 a"), vec![
+            Ok((Token::OpenTag, mk_span(0usize, 5usize))),
+            Ok((Token::Whitespace(" ".into()), mk_span(5usize, 6usize))),
+            Ok((Token::Comment("".into()), mk_span(6usize, 7usize))),
+            Ok((Token::Whitespace("\n".into()), mk_span(7usize, 8usize))),
+            Err(SyntaxError::UnknownCharacter(mk_span(8usize, 9usize))),
+            Ok((Token::Whitespace(" ".into()), mk_span(9usize, 10usize))),
+            Ok((Token::String("a".into()), mk_span(10usize, 11usize))),
+        ]);
+    }
+
+    #[test]
+    fn description() {
+        let tokens: Vec<_> = Lexer::new("<?php $a = (int) 1.5 ?? 'b' . C\\D::E;")
+            .map(|x| x.unwrap().0.description()).collect();
+        assert_eq!(tokens.concat(), "<?php $a = (int) 1.5 ?? b . C\\D::E;");
+        assert_eq!(Token::End.description(), "end of file");
+        assert_eq!(Token::YieldFrom.description(), "yield from");
+        assert_eq!(Token::DollarCurlyBracesOpen.description(), "${");
+    }
+}
@@ -4,13 +4,22 @@ extern crate fnv;
 mod interner;
 
 mod tokens;
+pub use tokens::{Token, TokenSpan};
 
 #[allow(dead_code)] //TODO: remove some day
 pub mod ast;
 
 mod tokenizer;
+mod lexer;
+pub use lexer::Lexer;
+mod token_dump;
+pub use token_dump::{PhpToken, token_get_all, tokens_to_json};
+mod version;
 mod parser;
 pub use parser::*;
+pub mod visitor;
+mod min_version;
+pub use min_version::{FeatureUse, MinVersion, min_version};
 
 mod printer;
 pub use printer::PrettyPrinter;
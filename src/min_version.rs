@@ -0,0 +1,187 @@
+//! Determine the oldest PHP version which is able to parse a file
+use std::borrow::Borrow;
+use std::cmp;
+use ast::{Attribute, ClassModifier, Decl, Expr, Expr_, FunctionDecl, Member, MemberModifier, MemberModifiers, Op, Stmt, Stmt_, StringKind, Ty, TypeExpr};
+use tokenizer::{Span, mk_span};
+use version::{Feature, PhpVersion};
+use visitor::{self, Visitor};
+
+/// A use of syntax which isn't available in every PHP version
+#[derive(Clone, Debug, PartialEq)]
+pub struct FeatureUse {
+    pub feature: Feature,
+    /// the innermost statement or expression containing the syntax
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MinVersion {
+    pub version: PhpVersion,
+    /// the syntax which requires `version`, empty if nothing needs more than `PhpVersion::OLDEST`
+    pub required_by: Vec<FeatureUse>,
+}
+
+/// `$a::class` as opposed to `A::class`
+fn is_class_name_on_object(base: &Expr, idxs: &[Expr]) -> bool {
+    if let Expr_::Path(_) = base.0 {
+        return false;
+    }
+    match idxs {
+        [Expr(Expr_::Path(ref path), _)] => (path.identifier.borrow() as &str).to_lowercase() == "class",
+        _ => false,
+    }
+}
+
+struct FeatureCollector {
+    spans: Vec<Span>,
+    uses: Vec<FeatureUse>,
+}
+
+impl FeatureCollector {
+    fn add(&mut self, feature: Feature) {
+        let span = self.spans.last().cloned().unwrap_or_else(Span::new);
+        let feature_use = FeatureUse {
+            feature: feature,
+            span: span,
+        };
+        if !self.uses.contains(&feature_use) {
+            self.uses.push(feature_use);
+        }
+    }
+
+    fn add_ty(&mut self, ty: &Ty) {
+        if let Some(feature) = Feature::of_ty(ty) {
+            self.add(feature);
+        }
+    }
+}
+
+impl Visitor for FeatureCollector {
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        self.spans.push(mk_span(stmt.1.start as usize, stmt.1.end as usize));
+        match stmt.0 {
+            Stmt_::Decl(Decl::Class(ref decl)) if decl.cmod.has(ClassModifier::Readonly) => self.add(Feature::ReadonlyClasses),
+            Stmt_::Decl(Decl::Enum(_)) => self.add(Feature::Enums),
+            Stmt_::Decl(Decl::StaticVars(ref vars)) if vars.iter().any(|&(_, ref value)| value.as_ref().map_or(false, Expr::contains_new)) => {
+                self.add(Feature::NewInInitializers)
+            }
+            _ => (),
+        }
+        if let Stmt_::Try(_, ref catch_clauses, _) = stmt.0 {
+            if catch_clauses.iter().any(|clause| clause.types.len() > 1) {
+                self.add(Feature::MultiCatch);
+            }
+        }
+        visitor::walk_stmt(self, stmt);
+        self.spans.pop();
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        self.spans.push(mk_span(expr.1.start as usize, expr.1.end as usize));
+        match expr.0 {
+            Expr_::Int(_, true) | Expr_::Double(_, true) => self.add(Feature::NumericSeparators),
+            Expr_::BinaryOp(Op::Coalesce, _, _) => self.add(Feature::NullCoalesce),
+            Expr_::BinaryOp(Op::Spaceship, _, _) => self.add(Feature::Spaceship),
+            Expr_::YieldFrom(_) => self.add(Feature::YieldFrom),
+            Expr_::ArrowFunction(_, _, _) => self.add(Feature::ArrowFunctions),
+            Expr_::Match(_, _) => self.add(Feature::Match),
+            Expr_::ObjMember(_, _, true) => self.add(Feature::NullsafeOperator),
+            Expr_::FirstClassCallable(_) => self.add(Feature::FirstClassCallables),
+            Expr_::Interpolated(StringKind::HereDoc(_, ref indentation, code_follows), _) |
+            Expr_::Interpolated(StringKind::NowDoc(_, ref indentation, code_follows), _)
+                if code_follows || !(indentation.borrow() as &str).is_empty() => self.add(Feature::FlexibleHeredoc),
+            Expr_::Call(_, ref args) | Expr_::New(_, ref args) if args.iter().any(|arg| arg.name.is_some()) => {
+                self.add(Feature::NamedArguments)
+            }
+            Expr_::Array(ref items) if items.iter().any(|&(_, ref value)| if let Expr_::Unpack(_) = value.0 { true } else { false }) => {
+                self.add(Feature::ArraySpread)
+            }
+            Expr_::StaticMember(ref base, ref idxs) if is_class_name_on_object(base, idxs) => self.add(Feature::ClassNameOnObjects),
+            _ => (),
+        }
+        visitor::walk_expr(self, expr);
+        self.spans.pop();
+    }
+
+    fn visit_member(&mut self, member: &Member) {
+        match *member {
+            Member::Property(_, ref modifiers, ref ty, _, _) => {
+                if ty.is_some() {
+                    self.add(Feature::TypedProperties);
+                }
+                if modifiers.has(MemberModifier::Readonly) {
+                    self.add(Feature::ReadonlyProperties);
+                }
+            }
+            _ => (),
+        }
+        visitor::walk_member(self, member);
+    }
+
+    fn visit_function_decl(&mut self, decl: &FunctionDecl) {
+        if decl.ret_ty.is_some() {
+            self.add(Feature::ReturnTypes);
+        }
+        for param in &decl.params {
+            if param.modifiers != MemberModifiers::none() {
+                self.add(Feature::ConstructorPromotion);
+            }
+            if param.modifiers.has(MemberModifier::Readonly) {
+                self.add(Feature::ReadonlyProperties);
+            }
+            if param.default.as_ref().map_or(false, Expr::contains_new) {
+                self.add(Feature::NewInInitializers);
+            }
+        }
+        visitor::walk_function_decl(self, decl);
+    }
+
+    fn visit_attribute(&mut self, attribute: &Attribute) {
+        self.add(Feature::Attributes);
+        if attribute.args.iter().any(|arg| arg.value.contains_new()) {
+            self.add(Feature::NewInInitializers);
+        }
+        visitor::walk_attribute(self, attribute);
+    }
+
+    fn visit_ty(&mut self, ty: &TypeExpr) {
+        match *ty {
+            TypeExpr::Simple(ref ty) => self.add_ty(ty),
+            TypeExpr::Nullable(ref ty) => {
+                self.add(Feature::NullableTypes);
+                self.add_ty(ty);
+            }
+            TypeExpr::Union(ref types) => {
+                self.add(Feature::UnionTypes);
+                for ty in types {
+                    if let TypeExpr::Intersection(_) = *ty {
+                        self.add(Feature::DnfTypes);
+                    }
+                    self.visit_ty(ty);
+                }
+            }
+            TypeExpr::Intersection(ref types) => {
+                self.add(Feature::IntersectionTypes);
+                for ty in types {
+                    self.add_ty(ty);
+                }
+            }
+        }
+    }
+}
+
+/// the oldest PHP version able to parse the given statements and the syntax requiring it
+pub fn min_version(stmts: &[Stmt]) -> MinVersion {
+    let mut collector = FeatureCollector {
+        spans: vec![],
+        uses: vec![],
+    };
+    visitor::walk_stmts(&mut collector, stmts);
+    let version = collector.uses.iter()
+        .filter_map(|x| x.feature.introduced())
+        .fold(PhpVersion::OLDEST, cmp::max);
+    MinVersion {
+        version: version,
+        required_by: collector.uses.into_iter().filter(|x| x.feature.introduced() == Some(version)).collect(),
+    }
+}
@@ -8,13 +8,19 @@
 /// ! [3] https://github.com/php/php-src/blob/ab304579ff046426f281e9a95abea8d611e38e1c/Zend/zend_language_parser.y
 
 use std::borrow::{Borrow, Cow};
+use std::fmt;
 use std::iter;
-use tokenizer::{Tokenizer, Token, TokenSpan};
+use std::mem;
+use std::rc::Rc;
+use std::str;
+use tokenizer::{Tokenizer, Token, TokenSpan, LineMap};
+pub use version::{Feature, PhpVersion};
+use visitor::{self, Visitor};
 use interner::{Interner, RcStr};
 pub use tokenizer::{Span, SyntaxError, TokenizerExternalState, mk_span};
-pub use ast::{Block, CatchClause, Expr, Expr_, IncludeTy, UnaryOp, Op, Path, SwitchCase, Stmt,
-              Stmt_, NullableTy, Ty, TraitUse, UseClause};
-pub use ast::{Decl, FunctionDecl, ClassDecl, ParamDefinition, Member, MemberModifier,
+pub use ast::{Argument, Attribute, Block, CatchClause, Expr, Expr_, IncludeTy, MatchArm, UnaryOp, Op, Path, SwitchCase, Stmt,
+              Stmt_, TypeExpr, Ty, TraitUse, UseClause, StringKind, InterpolatedPart};
+pub use ast::{Decl, FunctionDecl, ClassDecl, EnumDecl, ParamDefinition, Member, MemberModifier,
               MemberModifiers, ClassModifier, ClassModifiers};
 pub use ast::Variable;
 
@@ -27,6 +33,8 @@ pub struct ParserError {
     /// an optional message to replace a generic error message with
     message: Option<&'static str>,
     syntax: Option<SyntaxError>,
+    /// syntax which isn't available in the targeted version
+    feature: Option<(Feature, PhpVersion)>,
 }
 
 impl ParserError {
@@ -36,6 +44,7 @@ impl ParserError {
             pos: position,
             message: None,
             syntax: None,
+            feature: None,
         }
     }
 
@@ -45,6 +54,33 @@ impl ParserError {
             pos: position,
             message: None,
             syntax: Some(e),
+            feature: None,
+        }
+    }
+
+    fn message(message: &'static str, position: usize) -> ParserError {
+        ParserError {
+            tokens: vec![],
+            pos: position,
+            message: Some(message),
+            syntax: None,
+            feature: None,
+        }
+    }
+
+    /// whether the erroneous construct was recognized (e.g. it's unsupported by the targeted version),
+    /// such an error is preferred over the errors of other alternatives
+    fn is_recognized(&self) -> bool {
+        self.feature.is_some() || self.message.is_some()
+    }
+
+    fn feature(feature: Feature, target: PhpVersion, position: usize) -> ParserError {
+        ParserError {
+            tokens: vec![],
+            pos: position,
+            message: None,
+            syntax: None,
+            feature: Some((feature, target)),
         }
     }
 }
@@ -60,14 +96,31 @@ pub struct SpannedParserError {
 }
 
 impl SpannedParserError {
-    pub fn error_message(&self, code: Option<&str>) -> Cow<'static, str> {
-        if let Some(message) = self.error.message {
-            return message.into();
+    fn new(error: ParserError, span: Span, line_map: &LineMap) -> SpannedParserError {
+        let line = line_map.line_from_position(span.end as usize);
+        let (line_start, line_end) = line_map.line(line);
+        SpannedParserError {
+            start: span.start,
+            end: span.end,
+            line_start: line_start,
+            line_end: line_end,
+            line: line,
+            error: error,
         }
+    }
 
-        let mut str_ = format!("expected one of {:?} at line {:?}\n", self.error.tokens,
-            self.line,
-        );
+    /// the language feature which caused this error, if it isn't supported by the targeted version
+    pub fn feature(&self) -> Option<Feature> {
+        self.error.feature.map(|(feature, _)| feature)
+    }
+
+    pub fn error_message(&self, code: Option<&str>) -> Cow<'static, str> {
+        let mut str_ = match (self.error.message, &self.error.syntax, self.error.feature) {
+            (Some(message), _, _) => format!("{} at line {:?}\n", message, self.line),
+            (None, &Some(ref syntax), _) => format!("{} at line {:?}\n", syntax.message(), self.line),
+            (None, &None, Some((feature, target))) => format!("{} at line {:?}\n", feature.unsupported_message(target), self.line),
+            (None, &None, None) => format!("expected one of {:?} at line {:?}\n", self.error.tokens, self.line),
+        };
         if let Some(code) = code {
             str_.push_str(&code[self.line_start as usize..self.line_end as usize]);
             str_.push_str("\n");
@@ -83,20 +136,80 @@ impl SpannedParserError {
     }
 }
 
+/// Settings which change how a source file is parsed, equal to the respective php.ini settings
+#[derive(Clone, Debug)]
+pub struct ParserOptions {
+    /// whether `<?` opens a PHP block (short_open_tag), enabled by default
+    pub short_tags: bool,
+    /// whether `<%`, `<%=` and `%>` are PHP tags (asp_tags), disabled by default
+    pub asp_tags: bool,
+    /// the language version to accept, syntax which was added later or was removed is an error
+    pub version: PhpVersion,
+}
+
+impl Default for ParserOptions {
+    fn default() -> ParserOptions {
+        ParserOptions {
+            short_tags: true,
+            asp_tags: false,
+            version: PhpVersion::default(),
+        }
+    }
+}
+
 pub struct Parser {
     interner: Interner,
     external: TokenizerExternalState,
     tokens: Vec<TokenSpan>,
+    /// the source of tokens whose spelling isn't implied by the token by their start position:
+    /// keywords which aren't spelled in lowercase (an identifier like `Foo::LIST` keeps its spelling)
+    /// and numbers containing separators (e.g. `1_000`)
+    spellings: Vec<(u32, String)>,
     pos: usize,
+    version: PhpVersion,
 }
 
 impl Parser {
-    fn new(tokens: Vec<TokenSpan>, ext: TokenizerExternalState, interner: Interner) -> Parser {
+    fn new(tokens: Vec<TokenSpan>, ext: TokenizerExternalState, interner: Interner, spellings: Vec<(u32, String)>,
+           version: PhpVersion) -> Parser {
         Parser {
             tokens: tokens,
             interner: interner,
             external: ext,
+            spellings: spellings,
             pos: 0,
+            version: version,
+        }
+    }
+
+    /// ensure the targeted version supports a feature used at the token-position `pos`
+    fn check_feature(&self, feature: Feature, pos: usize) -> Result<(), ParserError> {
+        if feature.is_supported_by(self.version) {
+            Ok(())
+        } else {
+            Err(ParserError::feature(feature, self.version, pos))
+        }
+    }
+
+    /// ensure an expression which is written to (e.g. assigned) isn't part of a nullsafe chain
+    fn check_writable(&self, target: &Expr) -> Result<(), ParserError> {
+        match target.0 {
+            // destructuring writes to every item (e.g. `[$a, list($b)] = $c`)
+            Expr_::Array(ref items) | Expr_::List(ref items) => {
+                for &(_, ref value) in items {
+                    try!(self.check_writable(value));
+                }
+                return Ok(());
+            }
+            _ => (),
+        }
+        match target.nullsafe_base() {
+            Some(base) => {
+                // point at the ?-> token
+                let pos = self.tokens.iter().position(|x| x.1.start >= base.1.end).unwrap_or(self.pos);
+                Err(ParserError::message("cannot use the nullsafe operator in write context", pos))
+            }
+            None => Ok(()),
         }
     }
 
@@ -109,6 +222,61 @@ impl Parser {
     fn next_token(&self) -> Option<&TokenSpan> {
         self.tokens.get(self.pos)
     }
+
+    /// the source of the token at `span`, if it's not implied by the token
+    fn spelling(&self, span: &Span) -> Option<&str> {
+        self.spellings.binary_search_by_key(&span.start, |x| x.0).ok().map(|i| &self.spellings[i].1[..])
+    }
+}
+
+/// compares types like PHP compares class names, case-insensitively
+fn is_same_type<T: fmt::Display>(a: &T, b: &T) -> bool {
+    a.to_string().to_lowercase() == b.to_string().to_lowercase()
+}
+
+/// the empty statement of a close tag (which counts as ';'), it's not kept in statement lists.
+/// unlike ';' itself `?>` is always longer than one byte
+fn is_close_tag_stmt(stmt: &Stmt) -> bool {
+    match *stmt {
+        Stmt(Stmt_::None, ref span) => span.end - span.start > 1,
+        _ => false,
+    }
+}
+
+/// the kind of declaration whose members are parsed
+#[derive(Copy, Clone)]
+enum OoType {
+    Class,
+    Trait,
+    Interface,
+    /// whether the enum has a backing type
+    Enum(bool),
+}
+
+/// collects the variables an arrow function uses from its parent scope
+struct CaptureCollector {
+    vars: Vec<RcStr>,
+}
+
+impl CaptureCollector {
+    fn add(&mut self, var: &RcStr) {
+        if (var.borrow() as &str) != "this" && !self.vars.contains(var) {
+            self.vars.push(var.clone());
+        }
+    }
+}
+
+impl Visitor for CaptureCollector {
+    fn visit_expr(&mut self, expr: &Expr) {
+        match expr.0 {
+            Expr_::Variable(Variable::Name(ref name)) => self.add(name),
+            // nested functions only use the variables they bind
+            Expr_::Function(ref decl, _) | Expr_::ArrowFunction(ref decl, _, _) => for &(_, ref var) in &decl.usev {
+                self.add(var);
+            },
+            _ => visitor::walk_expr(self, expr),
+        }
+    }
 }
 
 enum Associativity {
@@ -124,6 +292,7 @@ enum Precedence {
     LogicalAnd2,
     /// e.g. ternary
     Conditional,
+    Coalesce,
     LogicalIncOr1,
     LogicalAnd1,
     BitwiseIncOr,
@@ -160,6 +329,7 @@ macro_rules! from_usize {
 }
 from_usize!(None,
             Conditional,
+            Coalesce,
             LogicalIncOr2,
             LogicalExcOr2,
             LogicalAnd2,
@@ -198,6 +368,7 @@ impl Token {
             Token::Mul | Token::Div | Token::Mod => Precedence::Mul,
             Token::Pow => Precedence::Pow,
             Token::QuestionMark => Precedence::Conditional,
+            Token::Coalesce => Precedence::Coalesce,
             Token::InstanceOf => Precedence::InstanceOf,
             _ => return None,
         })
@@ -211,7 +382,7 @@ impl Token {
             Token::SpaceShip | Token::Lt | Token::Gt | Token::IsSmallerOrEqual |
             Token::IsGreaterOrEqual | Token::Sl | Token::Sr | Token::Plus | Token::Minus |
             Token::Dot | Token::Mul | Token::Div | Token::Mod => Associativity::Left,
-            Token::Pow => Associativity::Right,
+            Token::Pow | Token::Coalesce => Associativity::Right,
             _ => unimplemented!(),
         }
     }
@@ -230,11 +401,12 @@ macro_rules! deepest {
         match $expr {
             Ok(e) => return Ok(e),
             Err(x) => {
-                match $store {
-                    Some((spos, _)) => if x.pos > spos {
-                        $store = Some((x.pos, x));
-                    },
-                    None => $store = Some((x.pos, x)),
+                let replace = match $store {
+                    Some((spos, ref serr)) => (x.is_recognized(), x.pos) > (serr.is_recognized(), spos),
+                    None => true,
+                };
+                if replace {
+                    $store = Some((x.pos, x));
                 }
             }
         }
@@ -302,6 +474,9 @@ impl Parser {
                     _ => unreachable!(),
                 };
                 let expr = try!(self.parse_expression(Precedence::Unary));
+                if let UnaryOp::PreInc | UnaryOp::PreDec = op {
+                    try!(self.check_writable(&expr));
+                }
                 let span = mk_span(left.1.start, expr.1.end);
                 Expr(Expr_::UnaryOp(op, Box::new(expr)), span)
             }
@@ -348,6 +523,7 @@ impl Parser {
                         Token::Div => Some(Op::Div),
                         Token::Mod => Some(Op::Mod),
                         Token::Pow => Some(Op::Pow),
+                        Token::Coalesce => Some(Op::Coalesce),
                         _ => None,
                     }),
                     None => (None, None),
@@ -366,6 +542,11 @@ impl Parser {
             // consume the operator token
             let op_token = self.next_token().unwrap().clone();
             self.advance(1);
+            match op_token.0 {
+                Token::SpaceShip => try!(self.check_feature(Feature::Spaceship, self.pos - 1)),
+                Token::Coalesce => try!(self.check_feature(Feature::NullCoalesce, self.pos - 1)),
+                _ => (),
+            }
 
             // also try to match the ternary here.. since it's PHP and it's left associative therefor
             if let Token::QuestionMark = op_token.0 {
@@ -407,9 +588,18 @@ impl Parser {
     }
 
     fn parse_simple_variable(&mut self) -> Result<(Variable, Span), ParserError> {
-        // TODO '$' '{' expr '}'
-        // TODO '$' simple_variable
-        if_lookahead!(self, Token::Dollar, _token, unimplemented!());
+        if_lookahead!(self, Token::Dollar, token, {
+            // '$' '{' expr '}'
+            if_lookahead!(self, Token::CurlyBracesOpen, _tok, {
+                let expr = try!(self.parse_expression(Precedence::None));
+                let end_pos = if_lookahead_expect!(self, Token::CurlyBracesClose, Token::CurlyBracesClose, tok, tok.1.end);
+                return Ok((Variable::Fetch(Box::new(expr)), mk_span(token.1.start, end_pos)));
+            });
+            // '$' simple_variable
+            let (var, span) = try!(self.parse_simple_variable());
+            let end_pos = span.end;
+            return Ok((Variable::Fetch(Box::new(Expr(Expr_::Variable(var), span))), mk_span(token.1.start, end_pos)));
+        });
         // T_VARIABLE
         if_lookahead!(self, Token::Variable(_), _token, Ok(match _token {
             TokenSpan(Token::Variable(varname), span) => (Variable::Name(varname.into()), span),
@@ -434,22 +624,62 @@ impl Parser {
         Ok(args)
     }
 
-    fn parse_argument_list(&mut self) -> Result<Vec<Expr>, ParserError> {
+    fn parse_argument(&mut self) -> Result<Argument, ParserError> {
+        // identifier ':' expr
+        let is_named = match (self.next_token(), self.tokens.get(self.pos + 1)) {
+            (Some(&TokenSpan(Token::String(_), _)), Some(&TokenSpan(Token::Colon, _))) => true,
+            (Some(&TokenSpan(ref token, _)), Some(&TokenSpan(Token::Colon, _))) => token.is_semi_reserved(),
+            _ => false,
+        };
+        let name = if is_named {
+            try!(self.check_feature(Feature::NamedArguments, self.pos));
+            let (name, _) = try!(self.parse_identifier());
+            self.advance(1);
+            Some(name)
+        } else {
+            None
+        };
+        let unpack = self.parse_is_variadic();
+        let by_ref = !unpack && if_lookahead!(self, Token::Ampersand, _tok, {
+            try!(self.check_feature(Feature::CallTimePassByRef, self.pos - 1));
+            true
+        }, false);
+        Ok(Argument {
+            name: name,
+            unpack: unpack,
+            by_ref: by_ref,
+            value: try!(self.parse_expression(Precedence::None)),
+        })
+    }
+
+    /// consumes the `(...)` of the first-class callable syntax (e.g. `strlen(...)`), if present
+    fn parse_callable_placeholder(&mut self) -> bool {
+        match (self.tokens.get(self.pos), self.tokens.get(self.pos + 1), self.tokens.get(self.pos + 2)) {
+            (Some(&TokenSpan(Token::ParenthesesOpen, _)), Some(&TokenSpan(Token::Ellipsis, _)), Some(&TokenSpan(Token::ParenthesesClose, _))) => {
+                self.advance(3);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn parse_argument_list(&mut self) -> Result<Vec<Argument>, ParserError> {
         if_lookahead_expect!(self, Token::ParenthesesOpen, Token::ParenthesesOpen, _token, {
             if_lookahead!(self, Token::ParenthesesClose, _token, {
                 return Ok(vec![]);
             });
             // parse arguments (non_empty_argument_list)
-            let mut args = vec![];
+            let mut args: Vec<Argument> = vec![];
             loop {
-                let start_pos = if_lookahead!(self, Token::Ellipsis, token, Some(token.1.start), None);
-                let arg = try!(self.parse_expression(Precedence::None));
-                if let Some(start_pos) = start_pos {
-                    let span = Span { start: start_pos, ..arg.1.clone() };
-                    args.push(Expr(Expr_::Unpack(Box::new(arg)), span));
-                } else {
-                    args.push(arg);
+                let start_pos = self.pos;
+                let arg = try!(self.parse_argument());
+                if arg.name.is_none() && !arg.unpack && args.iter().any(|x| x.name.is_some()) {
+                    return Err(ParserError::message("cannot use a positional argument after a named argument", start_pos));
                 }
+                if arg.name.is_none() && !arg.unpack && args.iter().any(|x| x.unpack) {
+                    return Err(ParserError::message("cannot use a positional argument after argument unpacking", start_pos));
+                }
+                args.push(arg);
                 if_lookahead!(self, Token::Comma, _tok, {}, break);
             }
 
@@ -457,6 +687,36 @@ impl Parser {
         });
     }
 
+    /// parses the attribute groups in front of a declaration (e.g. `#[A, B(1)] #[C]`), if any
+    fn parse_attributes(&mut self) -> Result<Vec<Attribute>, ParserError> {
+        let mut attributes = vec![];
+        while let Some(&TokenSpan(Token::AttributeStart, _)) = self.next_token() {
+            self.advance(1);
+            loop {
+                let name = try!(self.parse_name()).0;
+                let args_pos = self.pos;
+                let args = if let Some(&TokenSpan(Token::ParenthesesOpen, _)) = self.next_token() {
+                    try!(self.parse_argument_list())
+                } else {
+                    vec![]
+                };
+                if args.iter().any(|arg| arg.value.contains_new()) {
+                    try!(self.check_feature(Feature::NewInInitializers, args_pos));
+                }
+                attributes.push(Attribute {
+                    name: name,
+                    args: args,
+                });
+                if_lookahead!(self, Token::Comma, _tok, {}, break);
+                if let Some(&TokenSpan(Token::SquareBracketClose, _)) = self.next_token() {
+                    break;
+                }
+            }
+            if_lookahead_expect!(self, Token::SquareBracketClose, Token::SquareBracketClose);
+        }
+        Ok(attributes)
+    }
+
     fn parse_property_name(&mut self) -> Result<Expr, ParserError> {
         let old_pos = self.pos;
         alt!(self.parse_simple_variable_expr());
@@ -473,7 +733,7 @@ impl Parser {
                 Ok(expr) => if_lookahead_expect!(self, Token::CurlyBracesClose, Token::CurlyBracesClose, _tok, return Ok(expr)),
             }
         });
-        Err(ParserError::new(vec![], self.pos))
+        Err(ParserError::new(vec![Token::String(self.interner.intern("")), Token::Variable(self.interner.intern("")), Token::CurlyBracesOpen], self.pos))
     }
 
     /// simple_only is used to indicate that only rules of `new_variable` are valid
@@ -583,24 +843,48 @@ impl Parser {
                     continue;
                 }),
             });
-            // object property indexing
+            // object property indexing, a nullsafe access starts a new chain
+            // which isn't continued if it's the (parenthesized) base item
             if_lookahead!(self, Token::ObjectOp, _tok, match (self.parse_property_name(), var_expr) {
                 (Err(_), var_expr_new) => var_expr = var_expr_new,
-                (Ok(p), Expr(Expr_::ObjMember(var, mut idxs), mut span)) => {
+                (Ok(p), Expr(Expr_::ObjMember(var, mut idxs, nullsafe), mut span)) if !nullsafe || i > 1 => {
                     idxs.push(p);
                     span.end = self.tokens[self.pos-1].1.end;
-                    var_expr = Expr(Expr_::ObjMember(var, idxs), span);
+                    var_expr = Expr(Expr_::ObjMember(var, idxs, nullsafe), span);
                     continue;
                 },
                 (Ok(p), Expr(expr, old_span)) => {
                     let span = mk_span(old_span.start, self.tokens[self.pos-1].1.end);
-                    var_expr = Expr(Expr_::ObjMember(Box::new(Expr(expr, old_span)), vec![p]), span);
+                    var_expr = Expr(Expr_::ObjMember(Box::new(Expr(expr, old_span)), vec![p], false), span);
                     continue;
                 }
             });
+            if_lookahead!(self, Token::NullsafeObjectOp, _tok, {
+                try!(self.check_feature(Feature::NullsafeOperator, self.pos - 1));
+                let p = try!(self.parse_property_name());
+                let span = mk_span(var_expr.1.start, self.tokens[self.pos-1].1.end);
+                var_expr = Expr(Expr_::ObjMember(Box::new(var_expr), vec![p], true), span);
+                continue;
+            });
             // static member indexing
             if_lookahead!(self, Token::ScopeOp, _tok, match (self.parse_simple_variable_expr(), var_expr) {
-                (Err(_), var_expr_new) => var_expr = var_expr_new,
+                // a constant or method of the class of an expression (e.g. `$a::B` or `$a::class`)
+                (Err(_), var_expr_new) => {
+                    var_expr = var_expr_new;
+                    if !simple_only {
+                        let name_pos = self.pos;
+                        if let Ok(identifier) = self.parse_identifier_as_expr() {
+                            if let Expr_::Path(ref path) = identifier.0 {
+                                if (path.identifier.borrow() as &str).to_lowercase() == "class" {
+                                    try!(self.check_feature(Feature::ClassNameOnObjects, name_pos));
+                                }
+                            }
+                            let span = mk_span(var_expr.1.start, identifier.1.end);
+                            var_expr = Expr(Expr_::StaticMember(Box::new(var_expr), vec![identifier]), span);
+                            continue;
+                        }
+                    }
+                }
                 (Ok(p), Expr(Expr_::StaticMember(var, mut idxs), mut span)) => {
                     idxs.push(p);
                     span.end = self.tokens[self.pos-1].1.end;
@@ -616,6 +900,16 @@ impl Parser {
             // call syntax
             if !simple_only {
                 if let Some(&TokenSpan(Token::ParenthesesOpen, _)) = self.next_token() {
+                    let placeholder_pos = self.pos + 1;
+                    if self.parse_callable_placeholder() {
+                        try!(self.check_feature(Feature::FirstClassCallables, placeholder_pos));
+                        if var_expr.nullsafe_base().is_some() {
+                            return Err(ParserError::message("cannot combine the nullsafe operator with the first-class callable syntax", placeholder_pos));
+                        }
+                        let span = mk_span(var_expr.1.start, self.tokens[self.pos - 1].1.end);
+                        var_expr = Expr(Expr_::FirstClassCallable(Box::new(var_expr)), span);
+                        continue;
+                    }
                     let args = try!(self.parse_argument_list());
                     let span = mk_span(var_expr.1.start, self.tokens[self.pos - 1].1.end);
                     var_expr = Expr(Expr_::Call(Box::new(var_expr), args), span);
@@ -652,9 +946,11 @@ impl Parser {
         let expr = try!(self.parse_other_expression());
         let start_pos = expr.1.start;
         if_lookahead!(self, Token::Increment, token, {
+            try!(self.check_writable(&expr));
             return Ok(Expr(Expr_::UnaryOp(UnaryOp::PostInc, Box::new(expr)), mk_span(start_pos, token.1.end)));
         });
         if_lookahead!(self, Token::Decrement, token, {
+            try!(self.check_writable(&expr));
             return Ok(Expr(Expr_::UnaryOp(UnaryOp::PostDec, Box::new(expr)),  mk_span(start_pos, token.1.end)));
         });
         Ok(expr)
@@ -670,17 +966,11 @@ impl Parser {
         if_lookahead!(self, Token::Ellipsis, _tok, true, false)
     }
 
-    fn parse_type_expr(&mut self) -> Result<NullableTy, Option<ParserError>> {
-        let nullable = if_lookahead!(self, Token::QuestionMark, _tok, true, false);
+    /// parses a type without any modifiers or operators (e.g. `int` or `\\Foo\\Bar`)
+    fn parse_single_type(&mut self) -> Result<Ty, ParserError> {
+        let ty_pos = self.pos;
         let ty = if_lookahead!(self, Token::Array, _tok, Ty::Array, if_lookahead!(self, Token::Callable, _tok, Ty::Callable, {
-            let (path, _) = match self.parse_name() {
-                Err(err) => if nullable {
-                    return Err(Some(err))
-                } else {
-                    return Err(None)
-                },
-                Ok(x) => x,
-            };
+            let (path, _) = try!(self.parse_name());
             let translated_ty = if path.namespace.is_none() && !path.is_absolute {
                 let lower_path = (path.identifier.borrow() as &str).to_lowercase();
                 match lower_path.as_str() {
@@ -690,6 +980,11 @@ impl Parser {
                     "float" => Some(Ty::Float),
                     "int" | "integer" => Some(Ty::Int),
                     "object" => Some(Ty::Object(None)),
+                    "void" => Some(Ty::Void),
+                    "iterable" => Some(Ty::Iterable),
+                    "null" => Some(Ty::Null),
+                    "false" => Some(Ty::False),
+                    "never" => Some(Ty::Never),
                     _ => None,
                 }
             } else {
@@ -697,24 +992,168 @@ impl Parser {
             };
             translated_ty.unwrap_or(Ty::Object(Some(path)))
         }));
-        if nullable {
-            Ok(NullableTy::Nullable(ty))
+        if let Some(feature) = Feature::of_ty(&ty) {
+            try!(self.check_feature(feature, ty_pos));
+        }
+        Ok(ty)
+    }
+
+    /// whether the next `&` intersects types, instead of marking a by-reference parameter
+    fn next_is_type_ampersand(&self) -> bool {
+        match (self.tokens.get(self.pos), self.tokens.get(self.pos + 1)) {
+            (Some(&TokenSpan(Token::Ampersand, _)), Some(&TokenSpan(Token::Variable(_), _))) |
+            (Some(&TokenSpan(Token::Ampersand, _)), Some(&TokenSpan(Token::Ellipsis, _))) => false,
+            (Some(&TokenSpan(Token::Ampersand, _)), _) => true,
+            _ => false,
+        }
+    }
+
+    /// parses a single type or an intersection of class types, which is parenthesized in DNF types (e.g. `(A&B)|null`)
+    fn parse_intersection_type(&mut self) -> Result<(TypeExpr, bool), Option<ParserError>> {
+        let parenthesized = if_lookahead!(self, Token::ParenthesesOpen, _tok, true, false);
+        let first_pos = self.pos;
+        let first = match self.parse_single_type() {
+            Ok(ty) => ty,
+            // e.g. a scalar type which isn't supported by the targeted version
+            Err(err) => return Err(if parenthesized || err.is_recognized() { Some(err) } else { None }),
+        };
+        if !self.next_is_type_ampersand() {
+            if parenthesized {
+                return Err(Some(ParserError::new(vec![Token::Ampersand], self.pos)));
+            }
+            return Ok((TypeExpr::Simple(first), false));
+        }
+        try!(self.check_feature(Feature::IntersectionTypes, self.pos));
+        let mut types = vec![(first, first_pos)];
+        while self.next_is_type_ampersand() {
+            self.advance(1);
+            let ty_pos = self.pos;
+            types.push((try!(self.parse_single_type()), ty_pos));
+        }
+        for (i, &(ref ty, ty_pos)) in types.iter().enumerate() {
+            match *ty {
+                Ty::Object(Some(_)) => (),
+                _ => return Err(Some(ParserError::message("intersection types may only contain class types", ty_pos))),
+            }
+            if types[..i].iter().any(|x| is_same_type(&x.0, ty)) {
+                return Err(Some(ParserError::message("a type may only occur once in a union or intersection type", ty_pos)));
+            }
+        }
+        if parenthesized {
+            if_lookahead!(self, Token::ParenthesesClose, _tok, {}, return Err(Some(ParserError::new(vec![Token::ParenthesesClose], self.pos))));
+        }
+        Ok((TypeExpr::Intersection(types.into_iter().map(|x| x.0).collect()), parenthesized))
+    }
+
+    fn parse_type_expr(&mut self) -> Result<TypeExpr, Option<ParserError>> {
+        if_lookahead!(self, Token::QuestionMark, _tok, {
+            try!(self.check_feature(Feature::NullableTypes, self.pos - 1));
+            let ty_pos = self.pos;
+            let ty = try!(self.parse_single_type());
+            match ty {
+                Ty::Void => return Err(Some(ParserError::message("void can only be used as a standalone type", ty_pos))),
+                Ty::Never => return Err(Some(ParserError::message("never can only be used as a standalone type", ty_pos))),
+                _ => (),
+            }
+            if let Some(&TokenSpan(Token::BwOr, _)) = self.next_token() {
+                return Err(Some(ParserError::message("a nullable type cannot be part of a union or intersection type", self.pos)));
+            }
+            if self.next_is_type_ampersand() {
+                return Err(Some(ParserError::message("a nullable type cannot be part of a union or intersection type", self.pos)));
+            }
+            return Ok(TypeExpr::Nullable(ty));
+        });
+        let first_pos = self.pos;
+        let (first, parenthesized) = try!(self.parse_intersection_type());
+        if let Some(&TokenSpan(Token::BwOr, _)) = self.next_token() {
+            try!(self.check_feature(Feature::UnionTypes, self.pos));
+        } else if parenthesized {
+            return Err(Some(ParserError::message("only intersection types inside a union type may be parenthesized", first_pos)));
         } else {
-            Ok(NullableTy::NonNullable(ty))
+            return Ok(first);
+        }
+        let mut types = vec![(first, parenthesized, first_pos)];
+        while let Some(&TokenSpan(Token::BwOr, _)) = self.next_token() {
+            self.advance(1);
+            let ty_pos = self.pos;
+            let (ty, parenthesized) = match self.parse_intersection_type() {
+                Ok(x) => x,
+                Err(Some(err)) => return Err(Some(err)),
+                Err(None) => return Err(Some(ParserError::new(vec![Token::String(self.interner.intern(""))], ty_pos))),
+            };
+            types.push((ty, parenthesized, ty_pos));
+        }
+        for (i, &(ref ty, parenthesized, ty_pos)) in types.iter().enumerate() {
+            match *ty {
+                TypeExpr::Intersection(_) if !parenthesized => {
+                    return Err(Some(ParserError::message("an intersection type inside a union type must be parenthesized", ty_pos)));
+                }
+                TypeExpr::Intersection(_) => try!(self.check_feature(Feature::DnfTypes, ty_pos)),
+                TypeExpr::Simple(Ty::Void) => return Err(Some(ParserError::message("void can only be used as a standalone type", ty_pos))),
+                TypeExpr::Simple(Ty::Never) => return Err(Some(ParserError::message("never can only be used as a standalone type", ty_pos))),
+                _ => (),
+            }
+            if types[..i].iter().any(|x| is_same_type(&x.0, ty)) {
+                return Err(Some(ParserError::message("a type may only occur once in a union or intersection type", ty_pos)));
+            }
         }
+        Ok(TypeExpr::Union(types.into_iter().map(|x| x.0).collect()))
     }
 
-    fn parse_parameter_list(&mut self) -> (Vec<ParamDefinition>, Option<ParserError>) {
+    /// ensure a parameter or property type isn't one which is only allowed for return values
+    fn check_value_type(&self, ty: &Option<TypeExpr>, ty_pos: usize) -> Result<(), ParserError> {
+        match *ty {
+            Some(TypeExpr::Simple(Ty::Void)) => Err(ParserError::message("void can only be used as a return type", ty_pos)),
+            Some(TypeExpr::Simple(Ty::Never)) => Err(ParserError::message("never can only be used as a return type", ty_pos)),
+            _ => Ok(()),
+        }
+    }
+
+    /// `promotion` is whether parameters may declare properties, which is only the case for constructors
+    fn parse_parameter_list(&mut self, promotion: bool) -> (Vec<ParamDefinition>, Option<ParserError>) {
         let mut params = vec![];
         loop {
+            let attributes = match self.parse_attributes() {
+                Ok(x) => x,
+                Err(err) => return (params, Some(err)),
+            };
+            // constructor property promotion
+            let modifiers_pos = self.pos;
+            let modifiers = MemberModifiers::new(&self.parse_member_modifiers());
+            if modifiers != MemberModifiers::none() {
+                if !promotion {
+                    return (params, Some(ParserError::message("properties may only be promoted in a constructor", modifiers_pos)));
+                }
+                if modifiers.has(MemberModifier::Static) || modifiers.has(MemberModifier::Abstract) || modifiers.has(MemberModifier::Final) {
+                    return (params, Some(ParserError::message("a promoted property may only have a visibility modifier", modifiers_pos)));
+                }
+                if let Err(err) = self.check_feature(Feature::ConstructorPromotion, modifiers_pos) {
+                    return (params, Some(err));
+                }
+                if modifiers.has(MemberModifier::Readonly) {
+                    if let Err(err) = self.check_feature(Feature::ReadonlyProperties, modifiers_pos) {
+                        return (params, Some(err));
+                    }
+                }
+            }
             // type hint:
+            let ty_pos = self.pos;
             let ty = match self.parse_type_expr() {
                 Ok(x) => Some(x),
                 Err(Some(err)) => return (params, Some(err)),
                 Err(None) => None,
             };
+            if let Err(err) = self.check_value_type(&ty, ty_pos) {
+                return (params, Some(err));
+            }
+            if ty.is_none() && modifiers.has(MemberModifier::Readonly) {
+                return (params, Some(ParserError::message("readonly properties must have a type", modifiers_pos)));
+            }
             let is_ref = self.parse_is_ref();
             let is_variadic = self.parse_is_variadic();
+            if is_variadic && modifiers != MemberModifiers::none() {
+                return (params, Some(ParserError::message("a promoted property cannot be variadic", self.pos - 1)));
+            }
             // parameter name
             let param_name = if_lookahead!(self, Token::Variable(_), token, {
                 match token.0 {
@@ -725,11 +1164,20 @@ impl Parser {
                 return (params, Some(ParserError::new(vec![Token::Variable(self.interner.intern(""))], self.pos)))
             });
             // optional default value
+            let default_pos = self.pos + 1;
             let default = if_lookahead!(self, Token::Equal, _tok, Some(match self.parse_expression(Precedence::None) {
                 Ok(x) => x,
                 Err(err) => return (params, Some(err)),
             }), None);
+            if default.as_ref().map_or(false, Expr::contains_new) {
+                if let Err(err) = self.check_feature(Feature::NewInInitializers, default_pos) {
+                    self.pos = default_pos;
+                    return (params, Some(err));
+                }
+            }
             params.push(ParamDefinition {
+                attributes: attributes,
+                modifiers: modifiers,
                 name: param_name,
                 as_ref: is_ref,
                 variadic: is_variadic,
@@ -741,6 +1189,114 @@ impl Parser {
         (params, None)
     }
 
+    fn parse_opt_return_type(&mut self) -> Result<Option<TypeExpr>, ParserError> {
+        Ok(if_lookahead!(self, Token::Colon, _tok, {
+            try!(self.check_feature(Feature::ReturnTypes, self.pos - 1));
+            match self.parse_type_expr() {
+                Ok(ty) => Some(ty),
+                Err(Some(err)) => return Err(err),
+                Err(None) => None,
+            }
+        }, None))
+    }
+
+    /// parse an arrow function after the `fn` token (e.g. `fn($x) => $x * 2`)
+    fn parse_arrow_function(&mut self, span: Span) -> Result<Expr, ParserError> {
+        let returns_ref = self.parse_is_ref();
+        if_lookahead_expect!(self, Token::ParenthesesOpen, Token::ParenthesesOpen);
+        let (params, params_err) = self.parse_parameter_list(false);
+        if_lookahead!(self, Token::ParenthesesClose, _tok, {}, return Err(params_err.unwrap()));
+        let ret_ty = try!(self.parse_opt_return_type());
+        if_lookahead_expect!(self, Token::DoubleArrow, Token::DoubleArrow);
+        let body = try!(self.parse_expression(Precedence::None));
+        // variables of the parent scope are bound by-value implicitly
+        let mut captures = CaptureCollector { vars: vec![] };
+        captures.visit_expr(&body);
+        let usev = captures.vars.into_iter()
+            .filter(|var| params.iter().all(|param| param.name != *var))
+            .map(|var| (false, var))
+            .collect();
+        let decl = FunctionDecl {
+            attributes: vec![],
+            params: params,
+            body: None,
+            usev: usev,
+            ret_ref: returns_ref,
+            ret_ty: ret_ty,
+        };
+        let span = mk_span(span.start, body.1.end);
+        Ok(Expr(Expr_::ArrowFunction(decl, Box::new(body), false), span))
+    }
+
+    fn parse_match(&mut self, span: Span) -> Result<Expr, ParserError> {
+        if_lookahead_expect!(self, Token::ParenthesesOpen, Token::ParenthesesOpen);
+        let subject = try!(self.parse_expression(Precedence::None));
+        if_lookahead_expect!(self, Token::ParenthesesClose, Token::ParenthesesClose);
+        if_lookahead_expect!(self, Token::CurlyBracesOpen, Token::CurlyBracesOpen);
+        let mut arms: Vec<MatchArm> = vec![];
+        loop {
+            if let Some(&TokenSpan(Token::CurlyBracesClose, _)) = self.next_token() {
+                break;
+            }
+            let start = match self.next_token() {
+                Some(token) => token.1.start,
+                None => return Err(ParserError::new(vec![Token::CurlyBracesClose], self.pos)),
+            };
+            // default [,] => expr | cond {, cond} [,] => expr
+            let conds = if_lookahead!(self, Token::Default, _tok, {
+                if arms.iter().any(|arm| arm.default) {
+                    return Err(ParserError::message("match expressions may only contain one default arm", self.pos - 1));
+                }
+                if_lookahead!(self, Token::Comma, _tok, {});
+                None
+            }, {
+                let mut conds = vec![];
+                loop {
+                    conds.push(try!(self.parse_expression(Precedence::None)));
+                    if_lookahead!(self, Token::Comma, _tok, {}, break);
+                    if let Some(&TokenSpan(Token::DoubleArrow, _)) = self.next_token() {
+                        break;
+                    }
+                }
+                Some(conds)
+            });
+            if_lookahead_expect!(self, Token::DoubleArrow, Token::DoubleArrow);
+            let expr = try!(self.parse_expression(Precedence::None));
+            let arm_span = mk_span(start, expr.1.end);
+            arms.push(MatchArm {
+                default: conds.is_none(),
+                conds: conds.unwrap_or_else(Vec::new),
+                expr: expr,
+                span: arm_span,
+            });
+            if_lookahead!(self, Token::Comma, _tok, {}, break);
+        }
+        let end_pos = if_lookahead_expect!(self, Token::CurlyBracesClose, Token::CurlyBracesClose, token, token.1.end);
+        Ok(Expr(Expr_::Match(Box::new(subject), arms), mk_span(span.start, end_pos)))
+    }
+
+    /// parses an anonymous or arrow function, optionally `static` (e.g. `static fn($x) => $x * 2`)
+    fn parse_closure(&mut self) -> Result<Expr, ParserError> {
+        let start = match self.next_token() {
+            Some(token) => token.1.start,
+            None => return Err(ParserError::new(vec![Token::Function, Token::Fn], self.pos)),
+        };
+        let is_static = if_lookahead!(self, Token::Static, _tok, true, false);
+        let mut expr = if_lookahead!(self, Token::Function, token, match try!(self.parse_function_declaration(token.1, true, false)).0 {
+            Stmt_::Expr(e) => e,
+            _ => unreachable!(),
+        }, if_lookahead_expect!(self, Token::Fn, Token::Fn, token, {
+            try!(self.check_feature(Feature::ArrowFunctions, self.pos - 1));
+            try!(self.parse_arrow_function(token.1))
+        }));
+        match expr.0 {
+            Expr_::Function(_, ref mut static_) | Expr_::ArrowFunction(_, _, ref mut static_) => *static_ = is_static,
+            _ => unreachable!(),
+        }
+        expr.1.start = start;
+        Ok(expr)
+    }
+
     fn parse_function_declaration(&mut self,
                                   span: Span,
                                   parse_closure: bool,
@@ -750,6 +1306,9 @@ impl Parser {
         let returns_ref = self.parse_is_ref();
         let name = if parse_closure {
             None
+        } else if allow_abstract {
+            // methods may be named like keywords (e.g. `function list()`)
+            Some(try!(self.parse_identifier()).0)
         } else {
             Some(if_lookahead_expect!(self, Token::String(_), Token::String(self.interner.intern("")), token, {
                 match token.0 {
@@ -759,7 +1318,9 @@ impl Parser {
             }))
         };
         if_lookahead_expect!(self, Token::ParenthesesOpen, Token::ParenthesesOpen);
-        let (params, params_err) = self.parse_parameter_list();
+        // only methods (which may be abstract) can be constructors
+        let is_constructor = allow_abstract && name.as_ref().map_or(false, |name| (name.borrow() as &str).to_lowercase() == "__construct");
+        let (params, params_err) = self.parse_parameter_list(is_constructor);
         if_lookahead!(self, Token::ParenthesesClose, _tok, {}, return Err(params_err.unwrap()));
         // lexical_vars (use clause)
         let mut use_variables = vec![];
@@ -778,13 +1339,7 @@ impl Parser {
                 if_lookahead_expect!(self, Token::ParenthesesClose, Token::ParenthesesClose);
             });
         }
-        let ret_ty = if_lookahead!(self, Token::Colon, _tok, {
-            match self.parse_type_expr() {
-                Ok(ty) => Some(ty),
-                Err(Some(err)) => return Err(err),
-                Err(None) => None,
-            }
-        }, None);
+        let ret_ty = try!(self.parse_opt_return_type());
         let no_body = if allow_abstract {
             if_lookahead!(self, Token::SemiColon, _tok, true, false)
         } else {
@@ -803,6 +1358,7 @@ impl Parser {
             (None, None)
         };
         let decl = FunctionDecl {
+            attributes: vec![],
             params: params,
             body: body,
             usev: use_variables,
@@ -811,35 +1367,64 @@ impl Parser {
         };
         let span = mk_span(span.start, self.tokens[self.pos - 1].1.end);
         Ok(Stmt(match name {
-            None => Stmt_::Expr(Expr(Expr_::Function(decl), span.clone())),
+            None => Stmt_::Expr(Expr(Expr_::Function(decl, false), span.clone())),
             Some(name) => Stmt_::Decl(Decl::GlobalFunction(name, decl)),
         }, span))
     }
 
-    /// parses a class or trait declaration
+    /// parses a class, interface, trait or enum declaration
     fn parse_oo_declaration(&mut self) -> Result<Stmt, ParserError> {
-        enum OoType {
-            Class,
-            Trait,
-            Interface,
-        }
-        let oo_type = if_lookahead!(self, Token::Trait, _tok, OoType::Trait, if_lookahead!(self, Token::Interface, _tok, OoType::Interface, OoType::Class));
+        // `enum` is only a keyword in front of the name of an enum
+        let is_enum = self.next_is_keyword("enum") && match self.tokens.get(self.pos + 1) {
+            Some(&TokenSpan(Token::String(_), _)) => true,
+            _ => false,
+        };
+        let mut oo_type = if is_enum {
+            self.advance(1);
+            try!(self.check_feature(Feature::Enums, self.pos - 1));
+            OoType::Enum(false)
+        } else {
+            if_lookahead!(self, Token::Trait, _tok, OoType::Trait, if_lookahead!(self, Token::Interface, _tok, OoType::Interface, OoType::Class))
+        };
 
         let mut class_modifiers = vec![];
+        let mut readonly_pos = None;
         // only a class has modifiers (and a class token ofcourse)
         if let OoType::Class = oo_type {
             loop {
                 if_lookahead!(self, Token::Abstract, _tok, { class_modifiers.push(ClassModifier::Abstract); continue; });
                 if_lookahead!(self, Token::Final, _tok, { class_modifiers.push(ClassModifier::Final); continue; });
+                if self.next_is_readonly() {
+                    readonly_pos = Some(self.pos);
+                    self.advance(1);
+                    class_modifiers.push(ClassModifier::Readonly);
+                    continue;
+                }
                 break;
             }
             if_lookahead_expect!(self, Token::Class, Token::Class);
+            if let Some(readonly_pos) = readonly_pos {
+                try!(self.check_feature(Feature::ReadonlyClasses, readonly_pos));
+            }
         }
         let start_pos = self.tokens[self.pos - 1 - class_modifiers.len()].1.start;
         let name = if_lookahead_expect!(self, Token::String(_), Token::String(self.interner.intern("")), token, match token.0 {
             Token::String(str_) => str_,
             _ => unreachable!(),
         });
+        let backing_ty = match oo_type {
+            OoType::Enum(_) => if_lookahead!(self, Token::Colon, _tok, {
+                let ty_pos = self.pos;
+                match try!(self.parse_single_type()) {
+                    ty @ Ty::Int | ty @ Ty::String => Some(ty),
+                    _ => return Err(ParserError::message("an enum may only be backed by int or string", ty_pos)),
+                }
+            }, None),
+            _ => None,
+        };
+        if let OoType::Enum(_) = oo_type {
+            oo_type = OoType::Enum(backing_ty.is_some());
+        }
         // extends are only valid for interfaces and classes
         let extends = match oo_type {
             OoType::Class => if_lookahead!(self, Token::Extends, _tok, Some(try!(self.parse_name()).0), None),
@@ -847,7 +1432,7 @@ impl Parser {
         };
         // implements = extended interfaces (equals to implements clause for classes and extends for interfaces)
         let implements_token = match oo_type {
-            OoType::Class => Some(Token::Implements),
+            OoType::Class | OoType::Enum(_) => Some(Token::Implements),
             OoType::Interface => Some(Token::Extends),
             _ => None,
         };
@@ -861,25 +1446,53 @@ impl Parser {
             _ => vec![],
         };
         if_lookahead_expect!(self, Token::CurlyBracesOpen, Token::CurlyBracesOpen);
-        let (members, err) = self.parse_class_statement_list();
+        let (members, err) = self.parse_class_statement_list(oo_type);
         let end_pos = if_lookahead_expect!(self, Token::CurlyBracesClose, Token::CurlyBracesClose, token, token.1.end, if let Some(err) = err {
             return Err(err);
         });
         let span = mk_span(start_pos, end_pos);
         let ret_expr = match oo_type {
             OoType::Class => Stmt_::Decl(Decl::Class(ClassDecl {
+                attributes: vec![],
                 cmod: ClassModifiers::new(&class_modifiers),
                 name: name,
                 base_class: extends,
                 implements: implements,
                 members: members,
             })),
-            OoType::Interface => Stmt_::Decl(Decl::Interface(name, implements, members)),
-            OoType::Trait => Stmt_::Decl(Decl::Trait(name, members)),
+            OoType::Interface => Stmt_::Decl(Decl::Interface(vec![], name, implements, members)),
+            OoType::Trait => Stmt_::Decl(Decl::Trait(vec![], name, members)),
+            OoType::Enum(_) => Stmt_::Decl(Decl::Enum(EnumDecl {
+                attributes: vec![],
+                name: name,
+                backing_ty: backing_ty,
+                implements: implements,
+                members: members,
+            })),
         };
         Ok(Stmt(ret_expr, span))
     }
 
+    /// parses a function or class declaration which is preceded by attributes
+    fn parse_attributed_declaration(&mut self) -> Result<Stmt, ParserError> {
+        let start = match self.next_token() {
+            Some(token) => token.1.start,
+            None => return Err(ParserError::new(vec![Token::AttributeStart], self.pos)),
+        };
+        let attributes = try!(self.parse_attributes());
+        let mut stmt = if_lookahead!(self, Token::Function, token, try!(self.parse_function_declaration(token.1, false, false)),
+            try!(self.parse_oo_declaration()));
+        match stmt.0 {
+            Stmt_::Decl(Decl::GlobalFunction(_, ref mut decl)) => decl.attributes = attributes,
+            Stmt_::Decl(Decl::Class(ref mut decl)) => decl.attributes = attributes,
+            Stmt_::Decl(Decl::Enum(ref mut decl)) => decl.attributes = attributes,
+            Stmt_::Decl(Decl::Interface(ref mut attrs, _, _, _)) | Stmt_::Decl(Decl::Trait(ref mut attrs, _, _)) => *attrs = attributes,
+            _ => unreachable!(),
+        }
+        stmt.1.start = start;
+        Ok(stmt)
+    }
+
     /// parsing all expressions after the precedence applying (stage 2 "callback")
     fn parse_other_expression(&mut self) -> Result<Expr, ParserError> {
         let mut deepest_err: Option<(usize, ParserError)> = None;
@@ -888,6 +1501,9 @@ impl Parser {
         if_lookahead!(self, Token::New, token, {
             match self.parse_class_name_reference() {
                 Ok(x) => {
+                    if self.parse_callable_placeholder() {
+                        return Err(ParserError::message("cannot use the first-class callable syntax with new", self.pos - 2));
+                    }
                     let args = if let Some(&TokenSpan(Token::ParenthesesOpen, _)) = self.next_token() {
                         try!(self.parse_argument_list())
                     } else {
@@ -911,13 +1527,45 @@ impl Parser {
             }, None);
             return Ok(Expr(Expr_::Exit(expr.map(Box::new)), span));
         });
+        // the operands of yield only exclude `and`, `xor` and `or`
         if_lookahead!(self, Token::Yield, token, {
-            let expr = try!(self.parse_opt_expression(Precedence::None)).map(Box::new);
-            return Ok(Expr(Expr_::Yield(expr), mk_span(token.1.start, self.tokens[self.pos-1].1.end)));
+            let mut key = None;
+            let mut value = try!(self.parse_opt_expression(Precedence::LogicalAnd2)).map(Box::new);
+            if value.is_some() {
+                if_lookahead!(self, Token::DoubleArrow, _tok, {
+                    key = value;
+                    value = Some(Box::new(try!(self.parse_expression(Precedence::LogicalAnd2))));
+                });
+            }
+            return Ok(Expr(Expr_::Yield(key, value), mk_span(token.1.start, self.tokens[self.pos-1].1.end)));
+        });
+        if_lookahead!(self, Token::YieldFrom, token, {
+            try!(self.check_feature(Feature::YieldFrom, self.pos - 1));
+            let expr = try!(self.parse_expression(Precedence::LogicalAnd2));
+            let span = mk_span(token.1.start, expr.1.end);
+            return Ok(Expr(Expr_::YieldFrom(Box::new(expr)), span));
         });
-        // function declaration (anonymous function)
-        if_lookahead!(self, Token::Function, token, if let Stmt_::Expr(e) = try!(self.parse_function_declaration(token.1, true, false)).0 {
-            return Ok(e)
+        // closures with attributes (e.g. `#[Pure] fn($x) => $x`)
+        if let Some(TokenSpan(Token::AttributeStart, span)) = self.next_token().cloned() {
+            let attributes = try!(self.parse_attributes());
+            let mut expr = try!(self.parse_closure());
+            match expr.0 {
+                Expr_::Function(ref mut decl, _) | Expr_::ArrowFunction(ref mut decl, _, _) => decl.attributes = attributes,
+                _ => unreachable!(),
+            }
+            expr.1.start = span.start;
+            return Ok(expr);
+        }
+        // anonymous and arrow functions
+        match (self.next_token(), self.tokens.get(self.pos + 1)) {
+            (Some(&TokenSpan(Token::Function, _)), _) | (Some(&TokenSpan(Token::Fn, _)), _) |
+            (Some(&TokenSpan(Token::Static, _)), Some(&TokenSpan(Token::Function, _))) |
+            (Some(&TokenSpan(Token::Static, _)), Some(&TokenSpan(Token::Fn, _))) => return self.parse_closure(),
+            _ => (),
+        }
+        if_lookahead!(self, Token::Match, token, {
+            try!(self.check_feature(Feature::Match, self.pos - 1));
+            return self.parse_match(token.1);
         });
         // internal_functions_in_yacc / casts
         let ret = match self.next_token() {
@@ -939,7 +1587,10 @@ impl Parser {
                 Token::CastArray => Some(Ty::Array),
                 Token::CastObject => Some(Ty::Object(None)),
                 Token::CastBool => Some(Ty::Bool),
-                Token::CastUnset => unimplemented!(),
+                Token::CastUnset => {
+                    try!(self.check_feature(Feature::UnsetCast, self.pos - 1));
+                    Some(Ty::Null)
+                }
                 _ => None,
             };
             if let Some(cast_ty) = cast_ty {
@@ -988,6 +1639,8 @@ impl Parser {
         // variable handling
         let assign_target = match self.parse_variable(false, None) {
             Ok(x) => Some(x),
+            // e.g. a nullsafe access which isn't supported by the targeted version
+            Err(x) if x.is_recognized() => return Err(x),
             Err(x) => {
                 deepest!(deepest_err, Err(x));
                 None
@@ -1035,6 +1688,7 @@ impl Parser {
                 None => None,
             };
             if let Some(assign_type) = assign_type {
+                try!(self.check_writable(&var));
                 self.advance(1);
                 let by_ref = match (&assign_type, self.next_token()) {
                     (&Op::Eq, Some(&TokenSpan(Token::Ampersand, _))) => {
@@ -1046,6 +1700,10 @@ impl Parser {
 
                 return match self.parse_expression(Precedence::None) {
                     Ok(expr) => {
+                        // the source of a reference assignment is written to as well
+                        if by_ref {
+                            try!(self.check_writable(&expr));
+                        }
                         let span = mk_span(var.1.start, self.tokens[self.pos - 1].1.end);
                         let expr = match (assign_type, by_ref) {
                             (Op::Eq, false) => Expr_::Assign(Box::new(var), Box::new(expr)),
@@ -1159,9 +1817,10 @@ impl Parser {
             if let Token::String(str_) = token {
                 self.advance(1);
                 return Ok((str_, span));
-            } else if token.is_reserved_non_modifier() {
+            } else if token.is_semi_reserved() {
                 self.advance(1);
-                return Ok((self.interner.intern(token.repr()), span));
+                let name = self.spelling(&span).unwrap_or(token.repr()).to_owned();
+                return Ok((self.interner.intern(&name), span));
             }
         }
         Err(ParserError::new(vec![Token::String(self.interner.intern(""))], self.pos))
@@ -1190,98 +1849,145 @@ impl Parser {
         Ok(name)
     }
 
-    fn parse_encaps_list(&mut self) -> Result<Expr, ParserError> {
-        let mut str_ = String::new();
+    /// encaps_var_offset: T_STRING | T_NUM_STRING | '-' T_NUM_STRING | T_VARIABLE
+    fn parse_encaps_var_offset(&mut self) -> Result<Expr, ParserError> {
+        let minus_pos = if_lookahead!(self, Token::Minus, token, Some(token.1.start), None);
+        if_lookahead!(self, Token::NumString(_), token, {
+            let num = match token.0 {
+                Token::NumString(num) => num,
+                _ => unreachable!(),
+            };
+            let start_pos = minus_pos.unwrap_or(token.1.start);
+            let mut num_str = if minus_pos.is_some() { "-".to_owned() } else { String::new() };
+            num_str.push_str(num.borrow());
+            // only canonical decimal integers are treated as integer offsets, everything else is a string key
+            let digits = if minus_pos.is_some() { &num_str[1..] } else { &num_str[..] };
+            let is_canonical = digits == "0" && minus_pos.is_none() ||
+                               !digits.starts_with('0') && digits.chars().all(|x| x.is_digit(10));
+            let expr = match num_str.parse::<i64>() {
+                Ok(val) if is_canonical => Expr_::Int(val, false),
+                _ => Expr_::String(self.interner.intern(&num_str)),
+            };
+            return Ok(Expr(expr, mk_span(start_pos, token.1.end)));
+        });
+        if minus_pos.is_some() {
+            return Err(ParserError::new(vec![Token::NumString(self.interner.intern(""))], self.pos));
+        }
+        if_lookahead!(self, Token::String(_), token, {
+            match token.0 {
+                Token::String(key) => return Ok(Expr(Expr_::String(key), token.1)),
+                _ => unreachable!(),
+            }
+        });
+        self.parse_simple_variable_expr()
+    }
+
+    /// encaps_var: T_VARIABLE | T_VARIABLE '[' encaps_var_offset ']' | T_VARIABLE T_OBJECT_OPERATOR T_STRING
+    fn parse_encaps_var(&mut self) -> Result<Expr, ParserError> {
+        let var = try!(self.parse_simple_variable_expr());
+        if_lookahead!(self, Token::SquareBracketOpen, _tok, {
+            let offset = try!(self.parse_encaps_var_offset());
+            let end_pos = if_lookahead_expect!(self, Token::SquareBracketClose, Token::SquareBracketClose, token, token.1.end);
+            let span = mk_span(var.1.start, end_pos);
+            return Ok(Expr(Expr_::ArrayIdx(Box::new(var), vec![Some(offset)]), span));
+        });
+        let nullsafe = match self.next_token() {
+            Some(&TokenSpan(Token::ObjectOp, _)) => Some(false),
+            Some(&TokenSpan(Token::NullsafeObjectOp, _)) => {
+                try!(self.check_feature(Feature::NullsafeOperator, self.pos));
+                Some(true)
+            }
+            _ => None,
+        };
+        if let Some(nullsafe) = nullsafe {
+            self.advance(1);
+            let (name, name_span) = try!(self.parse_identifier());
+            let span = mk_span(var.1.start, name_span.end);
+            let prop = Expr(Expr_::Path(Path::identifier(false, name)), name_span);
+            return Ok(Expr(Expr_::ObjMember(Box::new(var), vec![prop], nullsafe), span));
+        }
+        Ok(var)
+    }
+
+    fn parse_encaps_list(&mut self) -> Result<Vec<InterpolatedPart>, ParserError> {
+        let mut bytes = vec![];
         let mut parts = vec![];
-        let mut start_pos = None;
-        let mut end_pos = 0;
-        // find string literals
         loop {
+            // adjacent string literals are merged into a single part
             if_lookahead!(self, Token::ConstantEncapsedString(_), token, {
                 match token.0 {
                     Token::ConstantEncapsedString(str_part) => {
-                        if start_pos.is_none() {
-                            start_pos = Some(token.1.start);
-                        }
-                        if end_pos < token.1.end {
-                            end_pos = token.1.end;
-                        }
-                        str_.push_str(str_part.borrow());
+                        bytes.extend((str_part.borrow() as &str).as_bytes());
                         continue;
                     },
                     _ => unreachable!(),
                 }
             });
-            if_lookahead!(self, Token::DollarCurlyBracesOpen, token, {
-                if !str_.is_empty() {
-                    parts.push(Expr(Expr_::String(self.interner.intern(&str_)), mk_span(start_pos.unwrap(), end_pos)));
+            if_lookahead!(self, Token::BinaryCharSequence(_), token, {
+                match token.0 {
+                    Token::BinaryCharSequence(str_part) => {
+                        bytes.extend(str_part.iter());
+                        continue;
+                    },
+                    _ => unreachable!(),
                 }
-                str_.clear();
+            });
+            let expr = if_lookahead!(self, Token::DollarCurlyBracesOpen, token, {
                 match self.parse_identifier() {
                     Ok((name, span)) => {
-                        let mut  expr = try!(self.parse_variable(false, Some((Expr(Expr_::Variable(Variable::Name(name)), span), false))));
+                        let mut expr = try!(self.parse_variable(false, Some((Expr(Expr_::Variable(Variable::Name(name)), span), false))));
                         expr.1.start = token.1.start;
                         expr.1.end = if_lookahead_expect!(self, Token::CurlyBracesClose, Token::CurlyBracesClose, end_token, end_token.1.end);
-                        parts.push(expr);
+                        expr
                     },
                     Err(_) => {
                         let expr = try!(self.parse_expression(Precedence::None));
                         let end_pos = if_lookahead_expect!(self, Token::CurlyBracesClose, Token::CurlyBracesClose, end_token, end_token.1.end);
-                        parts.push(Expr(Expr_::Variable(Variable::Fetch(Box::new(expr))), mk_span(token.1.start, end_pos)));
+                        Expr(Expr_::Variable(Variable::Fetch(Box::new(expr))), mk_span(token.1.start, end_pos))
                     }
                 }
-                continue;
-            });
-            if_lookahead!(self, Token::CurlyBracesOpen, _tok, {
-                if !str_.is_empty() {
-                    parts.push(Expr(Expr_::String(self.interner.intern(&str_)), mk_span(start_pos.unwrap(), end_pos)));
-                }
-                str_.clear();
-                let expr = try!(self.parse_expression(Precedence::None));
-                end_pos = if_lookahead_expect!(self, Token::CurlyBracesClose, Token::CurlyBracesClose, token, token.1.end);
-                start_pos = Some(end_pos);
-                parts.push(expr);
-                continue;
+            }, {
+                if_lookahead!(self, Token::CurlyBracesOpen, _tok, {
+                    let expr = try!(self.parse_expression(Precedence::None));
+                    if_lookahead_expect!(self, Token::CurlyBracesClose, Token::CurlyBracesClose, _tok, expr)
+                }, {
+                    match self.parse_encaps_var() {
+                        Ok(expr) => expr,
+                        Err(ref e) if !e.is_recognized() => break,
+                        Err(e) => return Err(e),
+                    }
+                })
             });
-            if let Ok(expr) = self.parse_variable(false, None) {
-                if !str_.is_empty() {
-                    parts.push(Expr(Expr_::String(self.interner.intern(&str_)), mk_span(start_pos.unwrap(), end_pos)));
-                }
-                str_.clear();
-                start_pos = Some(expr.1.end);
-                end_pos = expr.1.end;
-                parts.push(expr);
-                continue;
+            if !bytes.is_empty() {
+                parts.push(self.mk_literal_part(mem::replace(&mut bytes, vec![])));
             }
-            break;
+            parts.push(InterpolatedPart::Expr(expr));
         }
-        let str_expr = if !str_.is_empty() {
-            let span = Span {
-              start: start_pos.unwrap(),
-              end: end_pos,
-              ..Span::new()
-            };
-            Some(Expr(Expr_::String(self.interner.intern(&str_)), span))
-        } else {
-            None
-        };
-        if !parts.is_empty() {
-            let initial_expr = match str_expr {
-                Some(x) => x,
-                None => parts.pop().unwrap(),
-            };
-            // concat all parts
-            return Ok(parts.into_iter().rev().fold(initial_expr, |acc, part| {
-                let span = mk_span(part.1.start, acc.1.end);
-                Expr(Expr_::BinaryOp(Op::Concat, Box::new(part), Box::new(acc)), span)
-            }));
+        if !bytes.is_empty() {
+            parts.push(self.mk_literal_part(bytes));
         }
-        if let Some(str_expr) = str_expr {
-            return Ok(str_expr);
+        Ok(parts)
+    }
+
+    fn mk_literal_part(&mut self, bytes: Vec<u8>) -> InterpolatedPart {
+        match String::from_utf8(bytes) {
+            Ok(str_) => InterpolatedPart::Literal(self.interner.intern(&str_)),
+            Err(err) => InterpolatedPart::BinaryLiteral(Rc::new(err.into_bytes())),
         }
-        // use this to generate our error, does not anything related to the grammar
-        if_lookahead_expect!(self, Token::ConstantEncapsedString(_), Token::ConstantEncapsedString(self.interner.intern("")));
-        unreachable!();
+    }
+
+    /// strings consisting only of a literal are simple strings, everything else
+    /// (and any shell command) keeps its interpolated form
+    fn mk_interpolated(&mut self, kind: StringKind, mut parts: Vec<InterpolatedPart>, span: Span) -> Expr {
+        if kind == StringKind::DoubleQuote && parts.len() <= 1 {
+            match parts.pop() {
+                None => return Expr(Expr_::String(self.interner.intern("")), span),
+                Some(InterpolatedPart::Literal(str_)) => return Expr(Expr_::String(str_), span),
+                Some(InterpolatedPart::BinaryLiteral(str_)) => return Expr(Expr_::BinaryString(str_), span),
+                Some(part) => parts.push(part),
+            }
+        }
+        Expr(Expr_::Interpolated(kind, parts), span)
     }
 
     fn parse_dereferencable_scalar(&mut self) -> Result<Expr, ParserError> {
@@ -1303,6 +2009,12 @@ impl Parser {
                 _ => unreachable!(),
             }
         });
+        if_lookahead!(self, Token::BinaryCharSequence(_), token, {
+            match token.0 {
+                Token::BinaryCharSequence(str_) => return Ok(Expr(Expr_::BinaryString(str_), token.1)),
+                _ => unreachable!(),
+            }
+        });
         let expected = vec![Token::Array, Token::SquareBracketOpen, Token::ConstantEncapsedString(self.interner.intern(""))];
         Err(ParserError::new(expected, self.pos))
     }
@@ -1313,28 +2025,37 @@ impl Parser {
         match next_token {
             Some(x) => Ok(Expr(match x.0 {
                 // LNUMBER
-                Token::Int(x) => Expr_::Int(x),
+                Token::Int(i) => Expr_::Int(i, self.spelling(&x.1).is_some()),
                 // DNUMBER
-                Token::Double(x) => Expr_::Double(x),
+                Token::Double(d) => Expr_::Double(d, self.spelling(&x.1).is_some()),
                 // several magic constants
                 Token::MagicLine | Token::MagicFile | Token::MagicDir | Token::MagicTrait | Token::MagicMethod |
                 Token::MagicFunction | Token::MagicClass => Expr_::Path(Path::identifier(true, self.interner.intern(x.0.repr()))),
                 // '"' encaps_list '"'     { $$ = $2; }
                 Token::DoubleQuote => {
-                    if_lookahead!(self, Token::DoubleQuote, token, {
-                        let span = mk_span(x.1.start, token.1.end);
-                        return Ok(Expr(Expr_::String(self.interner.intern("")), span));
+                    let parts = try!(self.parse_encaps_list());
+                    let end_pos = if_lookahead_expect!(self, Token::DoubleQuote, Token::DoubleQuote, token, token.1.end);
+                    return Ok(self.mk_interpolated(StringKind::DoubleQuote, parts, mk_span(x.1.start, end_pos)));
+                },
+                Token::HereDocStart(_) | Token::NowDocStart(_) => {
+                    let parts = try!(self.parse_encaps_list());
+                    let (indentation, code_follows, end_pos) = if_lookahead_expect!(self, Token::HereDocEnd(_, _),
+                                                                                    Token::HereDocEnd(self.interner.intern(""), false), token, match token.0 {
+                        Token::HereDocEnd(indentation, code_follows) => (indentation, code_follows, token.1.end),
+                        _ => unreachable!(),
                     });
-                    let mut ret = try!(self.parse_encaps_list());
-                    ret.1.start = x.1.start;
-                    ret.1.end = if_lookahead_expect!(self, Token::DoubleQuote, Token::DoubleQuote, token, token.1.end);
-                    return Ok(ret);
+                    let kind = match x.0 {
+                        Token::HereDocStart(label) => StringKind::HereDoc(label, indentation, code_follows),
+                        Token::NowDocStart(label) => StringKind::NowDoc(label, indentation, code_follows),
+                        _ => unreachable!(),
+                    };
+                    return Ok(self.mk_interpolated(kind, parts, mk_span(x.1.start, end_pos)));
                 },
-                Token::HereDocStart => {
-                    let mut ret = try!(self.parse_encaps_list());
-                    ret.1.start = x.1.start;
-                    ret.1.end = if_lookahead_expect!(self, Token::HereDocEnd, Token::HereDocEnd, token, token.1.end);
-                    return Ok(ret);
+                // '`' backticks_expr '`'
+                Token::Backquote => {
+                    let parts = try!(self.parse_encaps_list());
+                    let end_pos = if_lookahead_expect!(self, Token::Backquote, Token::Backquote, token, token.1.end);
+                    return Ok(self.mk_interpolated(StringKind::Backquote, parts, mk_span(x.1.start, end_pos)));
                 },
                 _ => {
                     self.advance(-1);
@@ -1350,10 +2071,22 @@ impl Parser {
     fn parse_array_pair_list(&mut self) -> Result<Vec<(Option<Expr>, Expr)>, ParserError> {
         // parse array pairs as long as possible
         let mut pairs = vec![];
-        while let Ok(expr) = self.parse_expression(Precedence::None) {
-            let kv_pair = if_lookahead!(self, Token::DoubleArrow, _tok, {
-                (Some(expr), try!(self.parse_expression(Precedence::None)))
-            }, {(None, expr)});
+        loop {
+            // spread operator (e.g. [...$a, ...$b])
+            let kv_pair = if_lookahead!(self, Token::Ellipsis, token, {
+                try!(self.check_feature(Feature::ArraySpread, self.pos - 1));
+                let expr = try!(self.parse_expression(Precedence::None));
+                let span = Span { start: token.1.start, ..expr.1.clone() };
+                (None, Expr(Expr_::Unpack(Box::new(expr)), span))
+            }, {
+                let expr = match self.parse_expression(Precedence::None) {
+                    Ok(expr) => expr,
+                    Err(_) => break,
+                };
+                if_lookahead!(self, Token::DoubleArrow, _tok, {
+                    (Some(expr), try!(self.parse_expression(Precedence::None)))
+                }, {(None, expr)})
+            });
             pairs.push(kv_pair);
             if_lookahead!(self, Token::Comma, _token, {}, break);
         }
@@ -1363,6 +2096,7 @@ impl Parser {
     fn parse_foreach_variable(&mut self) -> Result<Expr, ParserError> {
         let is_var = self.parse_is_ref();
         let expr = try!(self.parse_variable(false, None));
+        try!(self.check_writable(&expr));
         if is_var {
             let span = mk_span(expr.1.start - 1, expr.1.start);
             return Ok(Expr(Expr_::Reference(Box::new(expr)), span));
@@ -1386,7 +2120,11 @@ impl Parser {
                 Token::Variable(var) => var,
                 _ => unreachable!(),
             });
+            let value_pos = self.pos + 1;
             let value = if_lookahead!(self, Token::Equal, _tok, Some(try!(self.parse_expression(Precedence::None))), None);
+            if value.as_ref().map_or(false, Expr::contains_new) {
+                try!(self.check_feature(Feature::NewInInitializers, value_pos));
+            }
             vars.push((var_name, value));
             if_lookahead!(self, Token::Comma, _tok, continue, break);
         }
@@ -1437,8 +2175,12 @@ impl Parser {
             loop {
                 if_lookahead!(self, Token::Catch, _tok, {
                     if_lookahead_expect!(self, Token::ParenthesesOpen, Token::ParenthesesOpen);
-                    // TODO: support | syntax
-                    let ty = try!(self.parse_name()).0;
+                    let mut types = vec![try!(self.parse_name()).0];
+                    while let Some(&TokenSpan(Token::BwOr, _)) = self.next_token() {
+                        try!(self.check_feature(Feature::MultiCatch, self.pos));
+                        self.advance(1);
+                        types.push(try!(self.parse_name()).0);
+                    }
                     let var_binding = if_lookahead_expect!(self, Token::Variable(_), Token::Variable(self.interner.intern("")), tok, match tok.0 {
                         Token::Variable(varname) => varname,
                         _ => unreachable!(),
@@ -1449,7 +2191,7 @@ impl Parser {
                     if_lookahead_expect!(self, Token::CurlyBracesClose, Token::CurlyBracesClose, _tok, (), if let Some(err) = stmts_err {
                         return Err(err)
                     });
-                    catch_clauses.push(CatchClause { ty: ty, var: var_binding, block: Block(block) });
+                    catch_clauses.push(CatchClause { types: types, var: var_binding, block: Block(block) });
                 }, break);
             }
             // parse finally clause (optional)
@@ -1469,7 +2211,9 @@ impl Parser {
             if_lookahead_expect!(self, Token::ParenthesesOpen, Token::ParenthesesOpen);
             let mut vars = vec![];
             loop {
-                vars.push(try!(self.parse_variable(false, None)));
+                let var = try!(self.parse_variable(false, None));
+                try!(self.check_writable(&var));
+                vars.push(var);
                 if_lookahead!(self, Token::Comma, _tok, continue, break);
             }
             if_lookahead_expect!(self, Token::ParenthesesClose, Token::ParenthesesClose);
@@ -1521,6 +2265,8 @@ impl Parser {
             let expr = try!(self.parse_expression(Precedence::None));
             if_lookahead_expect!(self, Token::ParenthesesClose, Token::ParenthesesClose);
             if_lookahead_expect!(self, Token::CurlyBracesOpen, Token::CurlyBracesOpen);
+            // `switch ($a) { ?>` is allowed, so the case list may start with ';'
+            if_lookahead!(self, Token::SemiColon, _tok, {});
             let (mut cases, mut conds) = (vec![], vec![]);
             loop {
                 let case_expr = if_lookahead!(self, Token::Case, _tok, {
@@ -1630,11 +2376,19 @@ impl Parser {
         if_lookahead_restore!(self, Token::Global, token, {
             deepest!(deepest_err, self.parse_global_var_decl(&token.1));
         });
+        // attributed function or class declaration statement
+        if let Some(&TokenSpan(Token::AttributeStart, _)) = self.next_token() {
+            let old_pos = self.pos;
+            deepest!(deepest_err, self.parse_attributed_declaration());
+            self.pos = old_pos;
+        }
         // function declaration statement
         if_lookahead_restore!(self, Token::Function, token, {
             deepest!(deepest_err, self.parse_function_declaration(token.1, false, false));
         });
+        let old_pos = self.pos;
         deepest!(deepest_err, self.parse_oo_declaration());
+        self.pos = old_pos;
 
         // parse other statements
         deepest!(deepest_err, match self.next_token().cloned() {
@@ -1683,11 +2437,11 @@ impl Parser {
         // expr ';'
         deepest!(deepest_err, match self.parse_expression(Precedence::None) {
             Err(x) => Err(x),
-            Ok(Expr(expr, span)) => {
-                let end_pos = if_lookahead_expect!(self, Token::SemiColon, Token::SemiColon, token, token.1.end);
-                let stmt_span = mk_span(span.start, end_pos);
+            // a missing `;` competes with the errors of declarations (e.g. `enum` is parsed as a constant)
+            Ok(Expr(expr, span)) => if_lookahead!(self, Token::SemiColon, token, {
+                let stmt_span = mk_span(span.start, token.1.end);
                 return Ok(Stmt(Stmt_::Expr(Expr(expr, span)), stmt_span));
-            }
+            }, Err(ParserError::new(vec![Token::SemiColon], self.pos))),
         });
 
         // TODO: error reporting
@@ -1712,11 +2466,33 @@ impl Parser {
                 Err(e) => return (stmts, Some(e)),
                 Ok(stmt) => stmt,
             };
-            stmts.push(stmt);
+            if !is_close_tag_stmt(&stmt) {
+                stmts.push(stmt);
+            }
         }
         (stmts, None)
     }
 
+    /// whether the next token is an identifier which is used as a keyword in this position (e.g. `readonly`)
+    fn next_is_keyword(&self, keyword: &str) -> bool {
+        match self.next_token() {
+            Some(&TokenSpan(Token::String(ref str_), _)) => (str_.borrow() as &str).to_lowercase() == keyword,
+            _ => false,
+        }
+    }
+
+    /// whether the next token is the `readonly` modifier, before PHP 8.1 it's a class name if it's used as a type
+    fn next_is_readonly(&self) -> bool {
+        if !self.next_is_keyword("readonly") {
+            return false;
+        }
+        Feature::ReadonlyProperties.is_supported_by(self.version) || match self.tokens.get(self.pos + 1) {
+            Some(&TokenSpan(Token::Variable(_), _)) | Some(&TokenSpan(Token::Ampersand, _)) |
+            Some(&TokenSpan(Token::Ellipsis, _)) | Some(&TokenSpan(Token::BwOr, _)) => false,
+            _ => true,
+        }
+    }
+
     fn parse_member_modifiers(&mut self) -> Vec<MemberModifier> {
         let mut modifiers = vec![];
         loop {
@@ -1726,6 +2502,11 @@ impl Parser {
             if_lookahead!(self, Token::Static, _tok, { modifiers.push(MemberModifier::Static); continue; });
             if_lookahead!(self, Token::Abstract, _tok, { modifiers.push(MemberModifier::Abstract); continue; });
             if_lookahead!(self, Token::Final, _tok, { modifiers.push(MemberModifier::Final); continue; });
+            if self.next_is_readonly() {
+                self.advance(1);
+                modifiers.push(MemberModifier::Readonly);
+                continue;
+            }
             break;
         }
         modifiers
@@ -1738,14 +2519,20 @@ impl Parser {
         Ok((path_to_trait, identifier_of_member))
     }
 
-    fn parse_class_statement(&mut self) -> Result<Vec<Member>, ParserError> {
+    fn parse_class_statement(&mut self, oo_type: OoType) -> Result<Vec<Member>, ParserError> {
         let mut members = vec![];
+        let attributes_pos = self.pos;
+        let attributes = try!(self.parse_attributes());
+        let modifiers_pos = self.pos;
         let (modifiers, is_var) = if_lookahead!(self, Token::Var, _tok, (MemberModifiers::new(&[MemberModifier::Public]), true),
             (MemberModifiers::new(&self.parse_member_modifiers()), false)
         );
 
         if !is_var {
             if_lookahead!(self, Token::Use, _tok, {
+                if !attributes.is_empty() {
+                    return Err(ParserError::message("cannot use attributes on a trait use", attributes_pos));
+                }
                 let names = try!(self.parse_name_list()).into_iter().map(|x| x.0).collect();
                 // trait_adaptions
                 if_lookahead!(self, Token::SemiColon, _tok, {
@@ -1780,20 +2567,66 @@ impl Parser {
                         });
                     }
                     if_lookahead!(self, Token::As, _tok, {
-                        if_lookahead!(self, Token::String(_), token, match token.0 {
-                            Token::String(str_) => uses.push(TraitUse::As(path_to_trait, trait_method_name, MemberModifiers::none(), Some(str_))),
-                            _ => unreachable!()
-                        }, unimplemented!());
+                        // trait_alias: a changed visibility (e.g. `a as protected`), an alias or both
+                        let modifiers_pos = self.pos;
+                        let modifiers = self.parse_member_modifiers();
+                        match modifiers.as_slice() {
+                            [] | [MemberModifier::Public] | [MemberModifier::Protected] | [MemberModifier::Private] => (),
+                            _ => return Err(ParserError::message("only a single visibility modifier can be used in a trait alias", modifiers_pos)),
+                        }
+                        let alias = if modifiers.is_empty() {
+                            Some(try!(self.parse_identifier()).0)
+                        } else {
+                            let old_pos = self.pos;
+                            match self.parse_identifier() {
+                                Ok((alias, _)) => Some(alias),
+                                Err(_) => {
+                                    self.pos = old_pos;
+                                    None
+                                }
+                            }
+                        };
+                        uses.push(TraitUse::As(path_to_trait, trait_method_name, MemberModifiers::new(&modifiers), alias));
                     });
                 }
                 if_lookahead_expect!(self, Token::CurlyBracesClose, Token::CurlyBracesClose);
                 return Ok(vec![Member::TraitUse(names, uses)]);
             });
+            if_lookahead!(self, Token::Case, _tok, {
+                let case_pos = self.pos - 1;
+                let backed = match oo_type {
+                    OoType::Enum(backed) => backed,
+                    _ => return Err(ParserError::message("cases may only be declared in an enum", case_pos)),
+                };
+                if modifiers != MemberModifiers::none() {
+                    return Err(ParserError::message("an enum case cannot have modifiers", case_pos));
+                }
+                let name_pos = self.pos;
+                let name = try!(self.parse_identifier()).0;
+                if (name.borrow() as &str).to_lowercase() == "class" {
+                    return Err(ParserError::message("class is reserved for fetching the class name", name_pos));
+                }
+                let value = if_lookahead!(self, Token::Equal, _tok, Some(try!(self.parse_expression(Precedence::None))), None);
+                match (backed, value.is_some()) {
+                    (true, false) => return Err(ParserError::message("a case of a backed enum must have a value", case_pos)),
+                    (false, true) => return Err(ParserError::message("a case of a pure enum cannot have a value", case_pos)),
+                    _ => (),
+                }
+                if_lookahead_expect!(self, Token::SemiColon, Token::SemiColon);
+                return Ok(vec![Member::EnumCase(attributes, name, value)]);
+            });
+            if modifiers.has(MemberModifier::Readonly) {
+                try!(self.check_feature(Feature::ReadonlyProperties, modifiers_pos));
+                if let Some(&TokenSpan(Token::Function, _)) | Some(&TokenSpan(Token::Const, _)) = self.next_token() {
+                    return Err(ParserError::message("only properties can be readonly", modifiers_pos));
+                }
+            }
             if_lookahead!(self, Token::Function, token, {
-                let (name, decl) = match try!(self.parse_function_declaration(token.1, false, true)).0 {
+                let (name, mut decl) = match try!(self.parse_function_declaration(token.1, false, true)).0 {
                     Stmt_::Decl(Decl::GlobalFunction(name, decl)) => (name, decl),
                     _ => unreachable!(),
                 };
+                decl.attributes = attributes;
                 members.push(Member::Method(modifiers, name, decl));
                 // function declaration does not require semicolon as constants below, so return early
                 return Ok(members);
@@ -1802,10 +2635,18 @@ impl Parser {
             if members.is_empty() {
                 if_lookahead!(self, Token::Const, _tok, {
                     loop {
+                        let id_pos = self.pos;
                         let id = try!(self.parse_identifier()).0;
+                        if (id.borrow() as &str).to_lowercase() == "class" {
+                            return Err(ParserError::message("class is reserved for fetching the class name", id_pos));
+                        }
                         if_lookahead_expect!(self, Token::Equal, Token::Equal);
+                        let val_pos = self.pos;
                         let val = try!(self.parse_expression(Precedence::None));
-                        members.push(Member::Constant(modifiers, id, val));
+                        if val.contains_new() {
+                            return Err(ParserError::message("new is not allowed in a constant value", val_pos));
+                        }
+                        members.push(Member::Constant(attributes.clone(), modifiers, id, val));
                         if_lookahead!(self, Token::Comma, _tok, continue, break);
                     }
                 });
@@ -1814,13 +2655,32 @@ impl Parser {
 
         // properties
         if members.is_empty() {
+            let ty_pos = self.pos;
+            let ty = match self.parse_type_expr() {
+                Ok(ty) => Some(ty),
+                Err(Some(err)) => return Err(err),
+                Err(None) => None,
+            };
+            if ty.is_some() {
+                try!(self.check_value_type(&ty, ty_pos));
+                try!(self.check_feature(Feature::TypedProperties, ty_pos));
+            } else if modifiers.has(MemberModifier::Readonly) {
+                return Err(ParserError::message("readonly properties must have a type", modifiers_pos));
+            }
             loop {
                 let varname = if_lookahead_expect!(self, Token::Variable(_), Token::Variable(self.interner.intern("")), token, match token.0 {
                     Token::Variable(var) => var,
                     _ => unreachable!(),
                 });
+                if let OoType::Enum(_) = oo_type {
+                    return Err(ParserError::message("enums cannot have properties", ty_pos));
+                }
+                let default_pos = self.pos + 1;
                 let default_val = if_lookahead!(self, Token::Equal, _tok, Some(try!(self.parse_expression(Precedence::None))), None);
-                members.push(Member::Property(modifiers, varname, default_val));
+                if default_val.as_ref().map_or(false, Expr::contains_new) {
+                    return Err(ParserError::message("new is not allowed in a property default value", default_pos));
+                }
+                members.push(Member::Property(attributes.clone(), modifiers, ty.clone(), varname, default_val));
                 if_lookahead!(self, Token::Comma, _tok, continue, break);
             }
         }
@@ -1829,11 +2689,11 @@ impl Parser {
     }
 
     /// refer to `parse_inner_statement_list` for usage
-    fn parse_class_statement_list(&mut self) -> (Vec<Member>, Option<ParserError>) {
+    fn parse_class_statement_list(&mut self, oo_type: OoType) -> (Vec<Member>, Option<ParserError>) {
         let mut exprs = vec![];
         let tokc = self.tokens.len() - 1;
         while self.pos <= tokc {
-            let expr = match self.parse_class_statement() {
+            let expr = match self.parse_class_statement(oo_type) {
                 Err(e) => return (exprs, Some(e)),
                 Ok(expr) => expr,
             };
@@ -1878,14 +2738,19 @@ impl Parser {
         let mut stmts = vec![];
         let tokc = self.tokens.len() - 1;
         while self.pos <= tokc {
-            stmts.push(try!(self.parse_top_statement()));
+            let stmt = try!(self.parse_top_statement());
+            if !is_close_tag_stmt(&stmt) {
+                stmts.push(stmt);
+            }
         }
         Ok(stmts)
     }
 
-    fn parse_tokens(interner: Interner,
+    fn parse_tokens(mut interner: Interner,
                     ext: TokenizerExternalState,
-                    toks: Vec<TokenSpan>)
+                    toks: Vec<TokenSpan>,
+                    spellings: Vec<(u32, String)>,
+                    version: PhpVersion)
                     -> Result<Vec<Stmt>, SpannedParserError> {
         // strip whitespace and unnecessary tokens
         let mut tokens: Vec<TokenSpan> = vec![];
@@ -1893,19 +2758,32 @@ impl Parser {
             match tok.0 {
                 // TODO: pass doc comment in the span on (don't ignore them)
                 Token::Comment(_) |
-                Token::OpenTag |
-                Token::CloseTag => (),
+                Token::Whitespace(_) |
+                Token::OpenTag => (),
+                // the close tag counts as ';', after a statement which already ended it's an empty statement
+                Token::CloseTag => tokens.push(TokenSpan(Token::SemiColon, tok.1)),
                 Token::InlineHtml(str_) => tokens.extend(vec![
                     TokenSpan(Token::Echo, tok.1.clone()),
                     TokenSpan(Token::ConstantEncapsedString(str_), tok.1.clone()),
                     TokenSpan(Token::SemiColon, tok.1),
                 ]),
+                Token::BinaryInlineHtml(str_) => {
+                    let value = match str::from_utf8(&str_).ok().map(|x| interner.intern(x)) {
+                        Some(value) => Token::ConstantEncapsedString(value),
+                        None => Token::BinaryCharSequence(str_),
+                    };
+                    tokens.extend(vec![
+                        TokenSpan(Token::Echo, tok.1.clone()),
+                        TokenSpan(value, tok.1.clone()),
+                        TokenSpan(Token::SemiColon, tok.1),
+                    ])
+                }
                 Token::OpenTagWithEcho => tokens.push(TokenSpan(Token::Echo, tok.1)),
                 _ => tokens.push(tok),
             }
         }
         // println!("{:?}", tokens);
-        let mut p = Parser::new(tokens, ext, interner);
+        let mut p = Parser::new(tokens, ext, interner, spellings, version);
         // error handling..
         Ok(match p.parse_top_statement_list() {
             Err(e) => {
@@ -1915,51 +2793,86 @@ impl Parser {
                     (p.tokens.len() - 1, true)
                 };
                 let span = p.tokens[pos].1.clone();
-                let (start, end) = if after {
-                    (span.end, span.end + 1)
+                let span = if after {
+                    mk_span(span.end as usize, span.end as usize + 1)
                 } else {
-                    (span.start, span.end)
+                    span
                 };
-                let line = p.external.line_map.line_from_position(end as usize);
-                let (line_start, line_end) = p.external.line_map.line(line);
-                return Err(SpannedParserError {
-                    start: start,
-                    end: end,
-                    line: line,
-                    line_start: line_start,
-                    line_end: line_end,
-                    error: e,
-                });
+                return Err(SpannedParserError::new(e, span, &p.external.line_map));
             }
             Ok(x) => x,
         })
     }
 
     pub fn parse_str(s: &str) -> Result<Vec<Stmt>, SpannedParserError> {
-        let ((interner, ext_state), tokens) = {
-            let mut tokenizer = Tokenizer::new(s);
+        Parser::parse_bytes(s.as_bytes())
+    }
+
+    pub fn parse_str_with_options(s: &str, options: &ParserOptions) -> Result<Vec<Stmt>, SpannedParserError> {
+        Parser::parse_bytes_with_options(s.as_bytes(), options)
+    }
+
+    /// parse a source file in any ASCII compatible encoding (e.g. ISO-8859-1),
+    /// string literals which aren't valid UTF-8 are kept as `BinaryString`
+    pub fn parse_bytes(s: &[u8]) -> Result<Vec<Stmt>, SpannedParserError> {
+        Parser::parse_bytes_with_options(s, &ParserOptions::default())
+    }
+
+    pub fn parse_bytes_with_options(s: &[u8], options: &ParserOptions) -> Result<Vec<Stmt>, SpannedParserError> {
+        let ((interner, ext_state), tokens, spellings) = {
+            let mut tokenizer = Tokenizer::from_bytes(s);
+            tokenizer.set_short_tags(options.short_tags);
+            tokenizer.set_asp_tags(options.asp_tags);
+            // `#[` starts a comment before PHP 8
+            tokenizer.set_attributes(Feature::Attributes.is_supported_by(options.version));
+            tokenizer.set_arrow_functions(Feature::ArrowFunctions.is_supported_by(options.version));
+            tokenizer.set_match_expressions(Feature::Match.is_supported_by(options.version));
             let mut tokens = vec![];
+            let mut spellings = vec![];
             loop {
                 match tokenizer.next_token() {
                     Ok(TokenSpan(Token::End, _)) => break,
-                    Ok(tok) => tokens.push(tok),
+                    Ok(tok) => {
+                        // features which can only be told apart by the source of a token
+                        let src = &s[tok.1.start as usize..tok.1.end as usize];
+                        let feature = match tok.0 {
+                            Token::OpenTag | Token::OpenTagWithEcho | Token::CloseTag if src.starts_with(b"<%") || src.starts_with(b"%>") => {
+                                Some(Feature::AspTags)
+                            }
+                            Token::Int(_) | Token::Double(_) if src.contains(&b'_') => Some(Feature::NumericSeparators),
+                            Token::HereDocEnd(ref indentation, code_follows) if code_follows || !(indentation.borrow() as &str).is_empty() => {
+                                Some(Feature::FlexibleHeredoc)
+                            }
+                            _ => None,
+                        };
+                        if let Some(feature) = feature {
+                            if !feature.is_supported_by(options.version) {
+                                let err = ParserError::feature(feature, options.version, 0);
+                                return Err(SpannedParserError::new(err, tok.1, &tokenizer.state.external.line_map));
+                            }
+                        }
+                        let has_spelling = match tok.0 {
+                            Token::Int(_) | Token::Double(_) => feature == Some(Feature::NumericSeparators),
+                            ref x => x.is_semi_reserved() && src != x.repr().as_bytes(),
+                        };
+                        if has_spelling {
+                            spellings.push((tok.1.start, String::from_utf8_lossy(src).into_owned()));
+                        }
+                        tokens.push(tok)
+                    }
+                    // like PHP, send a byte order mark as output
+                    Err(SyntaxError::ByteOrderMark(span)) => {
+                        let bom = s[span.start as usize..span.end as usize].to_vec();
+                        tokens.push(TokenSpan(Token::BinaryInlineHtml(Rc::new(bom)), span))
+                    }
                     Err(e) => {
                         let span = e.span();
-                        let line = tokenizer.state.external.line_map.line_from_position(span.end as usize);
-                        let (line_start, line_end) = tokenizer.state.external.line_map.line(line);
-                        return Err(SpannedParserError {
-                            start: span.start,
-                            end: span.end,
-                            line_start: line_start,
-                            line_end: line_end,
-                            line: line,
-                            error: ParserError::syntax(e, 0),
-                        })
+                        return Err(SpannedParserError::new(ParserError::syntax(e, 0), span, &tokenizer.state.external.line_map));
                     }
                 }
             }
-            (tokenizer.into_external_state(), tokens)
+            (tokenizer.into_external_state(), tokens, spellings)
         };
-        Parser::parse_tokens(interner, ext_state, tokens)
+        Parser::parse_tokens(interner, ext_state, tokens, spellings, options.version)
     }
 }
@@ -1,20 +1,43 @@
 /// ! a pretty-ish printer
 use std::fmt::{self, Write};
+use std::str;
 use std::borrow::Borrow;
-use ast::{Block, ClassModifiers, ClassModifier, Decl, FunctionDecl, Stmt, Stmt_, Expr,
-          Expr_, IncludeTy, Op, Path, UnaryOp, Ty, NullableTy, TraitUse, UseClause};
-use ast::{Member, MemberModifiers, MemberModifier, Variable};
+use ast::{Argument, Attribute, Block, ClassModifiers, ClassModifier, Decl, FunctionDecl, Stmt, Stmt_, Expr,
+          Expr_, IncludeTy, Op, Path, UnaryOp, Ty, TypeExpr, TraitUse, UseClause};
+use ast::{Member, MemberModifiers, MemberModifier, ParamDefinition, Variable, StringKind, InterpolatedPart};
+
+/// the output of the printer, the newline after the end-label of a heredoc is delayed
+/// so a following `;` is written on the same line (e.g. `EOT;`)
+struct Output<W: Write> {
+    target: W,
+    newline_pending: bool,
+}
+
+impl<W: Write> Write for Output<W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        if self.newline_pending && !s.is_empty() {
+            self.newline_pending = false;
+            if !s.starts_with(';') {
+                try!(self.target.write_char('\n'));
+            }
+        }
+        self.target.write_str(s)
+    }
+}
 
 pub struct PrettyPrinter<W: Write> {
     indentation: usize,
-    target: W,
+    target: Output<W>,
 }
 
 impl<W: Write> PrettyPrinter<W> {
     pub fn new(target: W) -> PrettyPrinter<W> {
         PrettyPrinter {
             indentation: 0,
-            target: target,
+            target: Output {
+                target: target,
+                newline_pending: false,
+            },
         }
     }
 
@@ -23,6 +46,9 @@ impl<W: Write> PrettyPrinter<W> {
         for stmt in stmts {
             try!(printer.print_statement(&stmt));
         }
+        if printer.target.newline_pending {
+            try!(printer.target.target.write_char('\n'));
+        }
         Ok(())
     }
 
@@ -52,6 +78,51 @@ impl<W: Write> PrettyPrinter<W> {
         Ok(())
     }
 
+    fn print_arguments(&mut self, args: &[Argument]) -> fmt::Result {
+        for (i, arg) in args.iter().enumerate() {
+            if i > 0 {
+                try!(self.write(", "));
+            }
+            if let Some(ref name) = arg.name {
+                try!(self.write(name.borrow()));
+                try!(self.write(": "));
+            }
+            if arg.unpack {
+                try!(self.write("..."));
+            }
+            if arg.by_ref {
+                try!(self.write("&"));
+            }
+            try!(self.print_expression(&arg.value));
+        }
+        Ok(())
+    }
+
+    /// print the attributes as a single group, followed by a line break for declarations
+    fn print_attributes(&mut self, attributes: &[Attribute], own_line: bool) -> fmt::Result {
+        if attributes.is_empty() {
+            return Ok(());
+        }
+        try!(self.write("#["));
+        for (i, attribute) in attributes.iter().enumerate() {
+            if i > 0 {
+                try!(self.write(", "));
+            }
+            try!(write!(self.target, "{}", attribute.name));
+            if !attribute.args.is_empty() {
+                try!(self.write("("));
+                try!(self.print_arguments(&attribute.args));
+                try!(self.write(")"));
+            }
+        }
+        if own_line {
+            try!(self.write("]\n"));
+            self.write_indented("")
+        } else {
+            self.write("] ")
+        }
+    }
+
     fn print_member_body(&mut self, members: &[Member]) -> fmt::Result {
         try!(self.write(" {\n"));
         self.indentation += 1;
@@ -72,10 +143,12 @@ impl<W: Write> PrettyPrinter<W> {
             }
             Decl::GlobalFunction(ref name, ref decl) => {
                 try!(self.write_indented(""));
+                try!(self.print_attributes(&decl.attributes, true));
                 self.print_function(decl, Some(name.borrow()))
             }
             Decl::Class(ref classdecl) => {
                 try!(self.write_indented(""));
+                try!(self.print_attributes(&classdecl.attributes, true));
                 try!(write!(self.target, "{}", classdecl.cmod));
                 try!(self.write("class "));
                 try!(self.write(classdecl.name.borrow()));
@@ -95,8 +168,10 @@ impl<W: Write> PrettyPrinter<W> {
                 }
                 self.print_member_body(&classdecl.members)
             }
-            Decl::Interface(ref name, ref implements, ref members) => {
-                try!(self.write_indented("interface "));
+            Decl::Interface(ref attributes, ref name, ref implements, ref members) => {
+                try!(self.write_indented(""));
+                try!(self.print_attributes(attributes, true));
+                try!(self.write("interface "));
                 try!(self.write(name.borrow()));
                 if !implements.is_empty() {
                     try!(self.write(" extends "));
@@ -109,8 +184,29 @@ impl<W: Write> PrettyPrinter<W> {
                 }
                 self.print_member_body(members)
             }
-            Decl::Trait(ref name, ref members) => {
-                try!(self.write_indented("trait "));
+            Decl::Enum(ref decl) => {
+                try!(self.write_indented(""));
+                try!(self.print_attributes(&decl.attributes, true));
+                try!(self.write("enum "));
+                try!(self.write(decl.name.borrow()));
+                if let Some(ref ty) = decl.backing_ty {
+                    try!(write!(self.target, ": {}", ty));
+                }
+                if !decl.implements.is_empty() {
+                    try!(self.write(" implements "));
+                    for (i, iface) in decl.implements.iter().enumerate() {
+                        if i > 0 {
+                            try!(self.write(", "));
+                        }
+                        try!(write!(self.target, "{}", iface));
+                    }
+                }
+                self.print_member_body(&decl.members)
+            }
+            Decl::Trait(ref attributes, ref name, ref members) => {
+                try!(self.write_indented(""));
+                try!(self.print_attributes(attributes, true));
+                try!(self.write("trait "));
                 try!(self.write(name.borrow()));
                 self.print_member_body(members)
             }
@@ -135,7 +231,6 @@ impl<W: Write> PrettyPrinter<W> {
                     if i > 0 {
                         try!(self.write(", "));
                     }
-                    try!(self.write("$"));
                     try!(self.print_variable(varname));
                 }
                 self.write(";\n")
@@ -150,15 +245,21 @@ impl<W: Write> PrettyPrinter<W> {
     fn print_member(&mut self, member: &Member) -> fmt::Result {
         try!(self.write_indented(""));
         match *member {
-            Member::Constant(ref modifiers, ref name, ref value) => {
+            Member::Constant(ref attributes, ref modifiers, ref name, ref value) => {
+                try!(self.print_attributes(attributes, true));
                 try!(write!(self.target, "{} const ", modifiers));
                 try!(self.write(name.borrow()));
                 try!(self.write("="));
                 try!(self.print_expression(value));
                 self.write(";")
             }
-            Member::Property(ref modifiers, ref name, ref value) => {
-                try!(write!(self.target, "{} $", modifiers));
+            Member::Property(ref attributes, ref modifiers, ref ty, ref name, ref value) => {
+                try!(self.print_attributes(attributes, true));
+                try!(write!(self.target, "{}", modifiers));
+                if let Some(ref ty) = *ty {
+                    try!(write!(self.target, "{} ", ty));
+                }
+                try!(self.write("$"));
                 try!(self.write(name.borrow()));
                 if let Some(ref default) = *value {
                     try!(self.write("="));
@@ -166,7 +267,18 @@ impl<W: Write> PrettyPrinter<W> {
                 }
                 self.write(";")
             }
+            Member::EnumCase(ref attributes, ref name, ref value) => {
+                try!(self.print_attributes(attributes, true));
+                try!(self.write("case "));
+                try!(self.write(name.borrow()));
+                if let Some(ref value) = *value {
+                    try!(self.write("="));
+                    try!(self.print_expression(value));
+                }
+                self.write(";")
+            }
             Member::Method(ref modifiers, ref name, ref decl) => {
+                try!(self.print_attributes(&decl.attributes, true));
                 try!(write!(self.target, "{} ", modifiers));
                 self.print_function(decl, Some(name.borrow()))
             }
@@ -179,7 +291,7 @@ impl<W: Write> PrettyPrinter<W> {
                     try!(write!(self.target, "{}", name));
                 }
                 if !uses.is_empty() {
-                    try!(self.write("{\n"));
+                    try!(self.write(" {\n"));
                     self.indentation += 1;
                     for use_ in uses {
                         try!(self.write_indented(""));
@@ -189,9 +301,13 @@ impl<W: Write> PrettyPrinter<W> {
                                     try!(write!(self.target, "{}::", path));
                                 }
                                 try!(self.write(method.borrow()));
-                                try!(self.write(" as "));
-                                try!(write!(self.target, "{} ", modifiers));
+                                try!(self.write(" as"));
+                                let modifiers = modifiers.to_string();
+                                if !modifiers.is_empty() {
+                                    try!(write!(self.target, " {}", modifiers.trim()));
+                                }
                                 if let Some(ref alias) = *alias {
+                                    try!(self.write(" "));
                                     try!(self.write(alias.borrow()));
                                 }
                             }
@@ -237,11 +353,54 @@ impl<W: Write> PrettyPrinter<W> {
         if let Some(name) = name {
             try!(self.write(name));
         }
+        try!(self.print_parameters(&func.params));
+        if !func.usev.is_empty() {
+            try!(self.write(" use ("));
+            for (i, &(ref by_ref, ref var)) in func.usev.iter().enumerate() {
+                if i > 0 {
+                    try!(self.write(", "));
+                }
+                if *by_ref {
+                    try!(self.write("&"));
+                }
+                try!(self.write("$"));
+                try!(self.write(var.borrow()));
+            }
+            try!(self.write(")"));
+        }
+        if let Some(ref ty) = func.ret_ty {
+            try!(write!(self.target, ": {}", ty));
+        }
+        try!(self.write(" "));
+        if let Some(ref body) = func.body {
+            self.print_block(body)
+        } else {
+            self.write(";")
+        }
+    }
+
+    /// print an arrow function, the captured variables (`usev`) are implicit
+    fn print_arrow_function(&mut self, func: &FunctionDecl, body: &Expr) -> fmt::Result {
+        try!(self.write("fn"));
+        if func.ret_ref {
+            try!(self.write("&"));
+        }
+        try!(self.print_parameters(&func.params));
+        if let Some(ref ty) = func.ret_ty {
+            try!(write!(self.target, ": {}", ty));
+        }
+        try!(self.write(" => "));
+        self.print_expression(body)
+    }
+
+    fn print_parameters(&mut self, params: &[ParamDefinition]) -> fmt::Result {
         try!(self.write("("));
-        for (i, param) in func.params.iter().enumerate() {
+        for (i, param) in params.iter().enumerate() {
             if i > 0 {
                 try!(self.write(","));
             }
+            try!(self.print_attributes(&param.attributes, false));
+            try!(write!(self.target, "{}", param.modifiers));
             if let Some(ref ty) = param.ty {
                 try!(write!(self.target, "{} ", ty));
             }
@@ -258,26 +417,7 @@ impl<W: Write> PrettyPrinter<W> {
                 try!(self.print_expression(default));
             }
         }
-        try!(self.write(") "));
-        if !func.usev.is_empty() {
-            try!(self.write("use ("));
-            for (i, &(ref by_ref, ref var)) in func.usev.iter().enumerate() {
-                if i > 0 {
-                    try!(self.write(", "));
-                }
-                if *by_ref {
-                    try!(self.write("&"));
-                }
-                try!(self.write("$"));
-                try!(self.write(var.borrow()));
-            }
-            try!(self.write(") "));
-        }
-        if let Some(ref body) = func.body {
-            self.print_block(body)
-        } else {
-            self.write(";")
-        }
+        self.write(")")
     }
 
     fn print_use(&mut self, clauses: &[UseClause]) -> fmt::Result {
@@ -403,7 +543,13 @@ impl<W: Write> PrettyPrinter<W> {
                 try!(self.print_block(bl));
                 for clause in catch {
                     try!(self.write("catch ("));
-                    try!(write!(self.target, "{} ${}) ", clause.ty, clause.var.borrow() as &str));
+                    for (i, ty) in clause.types.iter().enumerate() {
+                        if i > 0 {
+                            try!(self.write(" | "));
+                        }
+                        try!(write!(self.target, "{}", ty));
+                    }
+                    try!(write!(self.target, " ${}) ", clause.var.borrow() as &str));
                     try!(self.print_block(&clause.block));
                 }
                 if let Some(ref finally_bl) = *finally {
@@ -466,12 +612,14 @@ impl<W: Write> PrettyPrinter<W> {
             Expr_::BinaryOp(_, _, _) |
             Expr_::UnaryOp(_, _) |
             Expr_::ArrayIdx(_, _) |
-            Expr_::ObjMember(_, _) |
+            Expr_::ObjMember(_, _, _) |
             Expr_::StaticMember(_, _) |
             Expr_::Call(_, _) |
             Expr_::New(_, _) |
             Expr_::Assign(_, _) |
-            Expr_::TernaryIf(_, _, _) => true,
+            Expr_::TernaryIf(_, _, _) |
+            Expr_::Yield(_, _) |
+            Expr_::YieldFrom(_) => true,
             _ => false,
         };
 
@@ -489,30 +637,214 @@ impl<W: Write> PrettyPrinter<W> {
         self.print_expression_curly_parens(expr, false)
     }
 
+    /// the base of a member or array access, parentheses would end the short-circuiting of a nullsafe chain
+    fn print_chain_base(&mut self, base: &Expr) -> fmt::Result {
+        if base.nullsafe_base().is_some() {
+            self.print_expression(base)
+        } else {
+            self.print_expression_parens(base)
+        }
+    }
+
+    /// prints a variable chain inside `{$...}`, where parentheses would be literal text
+    fn print_interpolated_chain(&mut self, expr: &Expr) -> fmt::Result {
+        match expr.0 {
+            Expr_::Variable(ref var) => self.print_variable(var),
+            Expr_::ArrayIdx(ref base, ref idxs) => {
+                try!(self.print_interpolated_chain(base));
+                for idx in idxs {
+                    try!(self.write("["));
+                    try!(self.print_opt_expression(&idx.as_ref()));
+                    try!(self.write("]"));
+                }
+                Ok(())
+            }
+            Expr_::ObjMember(ref base, ref idxs, nullsafe) => {
+                try!(self.print_interpolated_chain(base));
+                for (i, idx) in idxs.iter().enumerate() {
+                    try!(self.write(if nullsafe && i == 0 { "?->" } else { "->" }));
+                    try!(self.print_expression_curly_parens(idx, true));
+                }
+                Ok(())
+            }
+            Expr_::StaticMember(ref base, ref idxs) => {
+                try!(self.print_interpolated_chain(base));
+                for idx in idxs {
+                    try!(self.write("::"));
+                    try!(self.print_expression_parens(idx));
+                }
+                Ok(())
+            }
+            Expr_::Call(ref target, ref args) => {
+                try!(self.print_interpolated_chain(target));
+                try!(self.write("("));
+                try!(self.print_arguments(args));
+                self.write(")")
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    /// prints an interpolated string whose parts can't all be written as `{$...}` as a concatenation
+    fn print_concatenated(&mut self, kind: &StringKind, parts: &[InterpolatedPart]) -> fmt::Result {
+        try!(self.write(if *kind == StringKind::Backquote { "shell_exec(" } else { "(" }));
+        // the result has to be a string, even if it starts with an expression
+        if let Some(&InterpolatedPart::Expr(_)) = parts.first() {
+            try!(self.write("''."));
+        }
+        for (i, part) in parts.iter().enumerate() {
+            if i > 0 {
+                try!(self.write("."));
+            }
+            match *part {
+                InterpolatedPart::Literal(ref str_) => try!(self.print_string((str_.borrow() as &str).as_bytes())),
+                InterpolatedPart::BinaryLiteral(ref str_) => try!(self.print_string(str_)),
+                InterpolatedPart::Expr(ref expr) => try!(self.print_expression_parens(expr)),
+            }
+        }
+        self.write(")")
+    }
+
+    /// prints a string literal which evaluates to exactly the given bytes.
+    /// single quotes are preferred, unless there are control characters or invalid UTF-8 sequences
+    fn print_string(&mut self, bytes: &[u8]) -> fmt::Result {
+        let str_ = match str::from_utf8(bytes) {
+            Ok(str_) if !str_.bytes().any(|x| x < 0x20 || x == 0x7F) => str_,
+            _ => {
+                try!(self.write("\""));
+                try!(self.print_escaped_bytes(bytes, &StringKind::DoubleQuote));
+                return self.write("\"");
+            }
+        };
+        try!(self.write("'"));
+        let mut chars = str_.chars().peekable();
+        while let Some(c) = chars.next() {
+            // a backslash only needs escaping if it would otherwise form an escape sequence
+            let needs_escape = match (c, chars.peek()) {
+                ('\'', _) | ('\\', Some(&'\\')) | ('\\', Some(&'\'')) | ('\\', None) => true,
+                _ => false,
+            };
+            if needs_escape {
+                try!(self.write("\\"));
+            }
+            try!(self.target.write_char(c));
+        }
+        self.write("'")
+    }
+
+    /// prints the content of a double-quoted string, heredoc or backquote string (or a nowdoc verbatim)
+    /// escaping everything which would be interpreted otherwise and invalid UTF-8 sequences as \xNN
+    fn print_escaped_bytes(&mut self, bytes: &[u8], kind: &StringKind) -> fmt::Result {
+        let mut rest = bytes;
+        while !rest.is_empty() {
+            let (valid, invalid_len) = match str::from_utf8(rest) {
+                Ok(valid) => (valid, 0),
+                Err(err) => {
+                    let valid = str::from_utf8(&rest[..err.valid_up_to()]).unwrap();
+                    (valid, err.error_len().unwrap_or(rest.len() - valid.len()))
+                }
+            };
+            for c in valid.chars() {
+                try!(match (c, kind) {
+                    // a heredoc or nowdoc keeps its line structure, every line is indented like the end-tag
+                    ('\n', &StringKind::HereDoc(_, ref indentation, _)) |
+                    ('\n', &StringKind::NowDoc(_, ref indentation, _)) => write!(self.target, "\n{}", indentation.borrow() as &str),
+                    (c, &StringKind::NowDoc(..)) => self.target.write_char(c),
+                    ('\\', _) => self.write("\\\\"),
+                    ('$', _) => self.write("\\$"),
+                    ('"', &StringKind::DoubleQuote) => self.write("\\\""),
+                    ('`', &StringKind::Backquote) => self.write("\\`"),
+                    ('\n', _) => self.write("\\n"),
+                    ('\r', _) => self.write("\\r"),
+                    ('\t', _) => self.write("\\t"),
+                    ('\x0B', _) => self.write("\\v"),
+                    ('\x0C', _) => self.write("\\f"),
+                    ('\x1B', _) => self.write("\\e"),
+                    (c, _) if c < ' ' || c == '\x7F' => write!(self.target, "\\x{:02X}", c as u8),
+                    (c, _) => self.target.write_char(c),
+                });
+            }
+            for byte in &rest[valid.len()..valid.len() + invalid_len] {
+                try!(write!(self.target, "\\x{:02X}", byte));
+            }
+            rest = &rest[valid.len() + invalid_len..];
+        }
+        Ok(())
+    }
+
     fn print_variable(&mut self, v: &Variable) -> fmt::Result {
         match *v {
             Variable::Name(ref name) => write!(self.target, "${}", name.borrow() as &str),
-            Variable::Fetch(ref expr) => {
-                try!(self.write("$"));
-                self.print_expression_curly_parens(expr, true)
-            }
+            // $$name can be written without braces, everything else requires ${expr}
+            Variable::Fetch(ref expr) => match expr.0 {
+                Expr_::Variable(ref inner) => {
+                    try!(self.write("$"));
+                    self.print_variable(inner)
+                }
+                _ => {
+                    try!(self.write("${"));
+                    try!(self.print_expression(expr));
+                    self.write("}")
+                }
+            },
         }
     }
 
     pub fn print_expression(&mut self, expr: &Expr) -> fmt::Result {
         match expr.0 {
             Expr_::Path(ref path) => write!(self.target, "{}", path),
-            Expr_::String(ref str_) => {
-                let out_str = (str_.borrow() as &str).replace("\\'", "\\\\'").replace('\'', "\\'");
-                try!(write!(self.target, "'{}", out_str));
-                if out_str.ends_with('\\') {
-                    try!(self.write("\\"));
+            Expr_::String(ref str_) => self.print_string((str_.borrow() as &str).as_bytes()),
+            Expr_::BinaryString(ref str_) => self.print_string(str_),
+            Expr_::Interpolated(ref kind, ref parts) if !parts.iter().all(is_interpolatable_part) => {
+                self.print_concatenated(kind, parts)
+            }
+            Expr_::Interpolated(ref kind, ref parts) => {
+                // a nowdoc can't contain escape sequences, so invalid UTF-8 requires a heredoc
+                let heredoc;
+                let kind = match *kind {
+                    StringKind::NowDoc(ref label, ref indentation, code_follows)
+                        if parts.iter().any(|x| if let InterpolatedPart::BinaryLiteral(_) = *x { true } else { false }) => {
+                        heredoc = StringKind::HereDoc(label.clone(), indentation.clone(), code_follows);
+                        &heredoc
+                    }
+                    _ => kind,
+                };
+                match *kind {
+                    StringKind::DoubleQuote => try!(self.write("\"")),
+                    StringKind::HereDoc(ref label, ref indentation, _) => {
+                        try!(write!(self.target, "<<<{}\n{}", label.borrow() as &str, indentation.borrow() as &str))
+                    }
+                    StringKind::NowDoc(ref label, ref indentation, _) => {
+                        try!(write!(self.target, "<<<'{}'\n{}", label.borrow() as &str, indentation.borrow() as &str))
+                    }
+                    StringKind::Backquote => try!(self.write("`")),
+                }
+                for part in parts {
+                    match *part {
+                        InterpolatedPart::Literal(ref str_) => try!(self.print_escaped_bytes((str_.borrow() as &str).as_bytes(), kind)),
+                        InterpolatedPart::BinaryLiteral(ref str_) => try!(self.print_escaped_bytes(str_, kind)),
+                        // the complex {$...} syntax is able to represent every variable chain
+                        InterpolatedPart::Expr(ref expr) => {
+                            try!(self.write("{"));
+                            try!(self.print_interpolated_chain(expr));
+                            try!(self.write("}"));
+                        }
+                    }
+                }
+                match *kind {
+                    StringKind::DoubleQuote => self.write("\""),
+                    // before PHP 7.3 the end-label has to be followed by a newline or `;`
+                    StringKind::HereDoc(ref label, ref indentation, code_follows) |
+                    StringKind::NowDoc(ref label, ref indentation, code_follows) => {
+                        try!(write!(self.target, "\n{}{}", indentation.borrow() as &str, label.borrow() as &str));
+                        self.target.newline_pending = !code_follows;
+                        Ok(())
+                    }
+                    StringKind::Backquote => self.write("`"),
                 }
-                self.write("'")
             }
-            Expr_::BinaryString(ref str_) => unimplemented!(),
-            Expr_::Int(ref i) => write!(self.target, "{}", i),
-            Expr_::Double(ref d) => write!(self.target, "{}", d),
+            Expr_::Int(ref i, _) => write!(self.target, "{}", i),
+            Expr_::Double(ref d, _) => write!(self.target, "{}", d),
             Expr_::Array(ref arr) => {
                 try!(self.write("["));
                 for (i, &(ref k, ref v)) in arr.iter().enumerate() {
@@ -562,7 +894,7 @@ impl<W: Write> PrettyPrinter<W> {
                 self.print_expression(arg)
             }
             Expr_::ArrayIdx(ref base, ref idxs) => {
-                try!(self.print_expression_parens(base));
+                try!(self.print_chain_base(base));
                 for idx in idxs {
                     try!(self.write("["));
                     try!(self.print_opt_expression(&idx.as_ref()));
@@ -570,16 +902,20 @@ impl<W: Write> PrettyPrinter<W> {
                 }
                 Ok(())
             }
-            Expr_::ObjMember(ref base, ref idxs) => {
-                try!(self.print_expression_parens(base));
-                for idx in idxs {
-                    try!(self.write("->"));
+            Expr_::ObjMember(ref base, ref idxs, nullsafe) => {
+                match base.0 {
+                    // otherwise the accesses would continue the nullsafe chain
+                    Expr_::ObjMember(_, _, true) if !nullsafe => try!(self.print_expression_parens(base)),
+                    _ => try!(self.print_chain_base(base)),
+                }
+                for (i, idx) in idxs.iter().enumerate() {
+                    try!(self.write(if nullsafe && i == 0 { "?->" } else { "->" }));
                     try!(self.print_expression_curly_parens(idx, true));
                 }
                 Ok(())
             }
             Expr_::StaticMember(ref base, ref idxs) => {
-                try!(self.print_expression_parens(base));
+                try!(self.print_chain_base(base));
                 for idx in idxs {
                     try!(self.write("::"));
                     try!(self.print_expression_parens(idx));
@@ -589,14 +925,18 @@ impl<W: Write> PrettyPrinter<W> {
             Expr_::Call(ref target, ref args) => {
                 try!(self.print_expression(target));
                 try!(self.write("("));
-                try!(self.print_argument_list(args));
+                try!(self.print_arguments(args));
                 self.write(")")
             }
+            Expr_::FirstClassCallable(ref target) => {
+                try!(self.print_expression(target));
+                self.write("(...)")
+            }
             Expr_::New(ref target, ref args) => {
                 try!(self.write("new "));
                 try!(self.print_expression(target));
                 try!(self.write("("));
-                try!(self.print_argument_list(args));
+                try!(self.print_arguments(args));
                 self.write(")")
             },
             Expr_::Unpack(ref arg) => {
@@ -648,17 +988,62 @@ impl<W: Write> PrettyPrinter<W> {
                     Ty::Double => "double",
                     Ty::String => "string",
                     Ty::Object(None) => "object",
+                    Ty::Null => "unset",
                     _ => unimplemented!(),
                 }));
                 try!(self.write(")("));
                 try!(self.print_expression(op));
                 self.write(")")
             }
-            Expr_::Yield(ref expr) => {
-                try!(self.write("yield "));
-                self.print_opt_expression(&expr.as_ref().map(|x| &**x))
+            Expr_::Yield(ref key, ref value) => {
+                try!(self.write("yield"));
+                if let Some(ref key) = *key {
+                    try!(self.write(" "));
+                    try!(self.print_expression_parens(key));
+                    try!(self.write(" =>"));
+                }
+                if let Some(ref value) = *value {
+                    try!(self.write(" "));
+                    try!(self.print_expression(value));
+                }
+                Ok(())
+            }
+            Expr_::YieldFrom(ref expr) => {
+                try!(self.write("yield from "));
+                self.print_expression(expr)
+            }
+            Expr_::Function(ref decl, is_static) => {
+                try!(self.print_attributes(&decl.attributes, false));
+                if is_static {
+                    try!(self.write("static "));
+                }
+                self.print_function(decl, None)
+            }
+            Expr_::ArrowFunction(ref decl, ref body, is_static) => {
+                try!(self.print_attributes(&decl.attributes, false));
+                if is_static {
+                    try!(self.write("static "));
+                }
+                self.print_arrow_function(decl, body)
+            }
+            Expr_::Match(ref subject, ref arms) => {
+                try!(self.write("match ("));
+                try!(self.print_expression(subject));
+                try!(self.write(") {\n"));
+                self.indentation += 1;
+                for arm in arms {
+                    try!(self.write_indented(""));
+                    if arm.default {
+                        try!(self.write("default"));
+                    }
+                    try!(self.print_argument_list(&arm.conds));
+                    try!(self.write(" => "));
+                    try!(self.print_expression(&arm.expr));
+                    try!(self.write(",\n"));
+                }
+                self.indentation -= 1;
+                self.write_indented("}")
             }
-            Expr_::Function(ref decl) => self.print_function(decl, None),
             Expr_::Assign(ref target, ref value) => {
                 try!(self.print_expression(target));
                 try!(self.write("="));
@@ -725,6 +1110,7 @@ impl fmt::Display for Op {
             Op::BitwiseInclOr => "|",
             Op::BitwiseExclOr => "^",
             Op::Spaceship => "<=>",
+            Op::Coalesce => "??",
             Op::Sl => "<<",
             Op::Sr => ">>",
         })
@@ -739,6 +1125,9 @@ impl fmt::Display for ClassModifiers {
         if self.has(ClassModifier::Final) {
             try!(write!(f, "final "));
         }
+        if self.has(ClassModifier::Readonly) {
+            try!(write!(f, "readonly "));
+        }
         Ok(())
     }
 }
@@ -763,6 +1152,9 @@ impl fmt::Display for MemberModifiers {
         if self.has(MemberModifier::Final) {
             try!(write!(f, "final "));
         }
+        if self.has(MemberModifier::Readonly) {
+            try!(write!(f, "readonly "));
+        }
         Ok(())
     }
 }
@@ -791,6 +1183,11 @@ impl fmt::Display for Ty {
             Ty::Double => "double",
             Ty::String => "string",
             Ty::Object(None) => "object",
+            Ty::Void => "void",
+            Ty::Iterable => "iterable",
+            Ty::Null => "null",
+            Ty::False => "false",
+            Ty::Never => "never",
             Ty::Object(Some(ref path)) => {
                 try!(write!(f, "{}", path));
                 return Ok(());
@@ -800,15 +1197,52 @@ impl fmt::Display for Ty {
     }
 }
 
-impl fmt::Display for NullableTy {
+impl fmt::Display for TypeExpr {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let ty = match *self {
-            NullableTy::Nullable(ref ty) => {
-                try!(write!(f, "?"));
-                ty
-            },
-            NullableTy::NonNullable(ref ty) => ty,
-        };
-        write!(f, "{}", ty)
+        match *self {
+            TypeExpr::Simple(ref ty) => write!(f, "{}", ty),
+            TypeExpr::Nullable(ref ty) => write!(f, "?{}", ty),
+            TypeExpr::Union(ref types) => {
+                for (i, ty) in types.iter().enumerate() {
+                    if i > 0 {
+                        try!(write!(f, "|"));
+                    }
+                    match *ty {
+                        TypeExpr::Intersection(_) => try!(write!(f, "({})", ty)),
+                        _ => try!(write!(f, "{}", ty)),
+                    }
+                }
+                Ok(())
+            }
+            TypeExpr::Intersection(ref types) => {
+                for (i, ty) in types.iter().enumerate() {
+                    if i > 0 {
+                        try!(write!(f, "&"));
+                    }
+                    try!(write!(f, "{}", ty));
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// whether a part of an interpolated string can be written with the `{$...}` syntax
+fn is_interpolatable_part(part: &InterpolatedPart) -> bool {
+    match *part {
+        InterpolatedPart::Expr(ref expr) => is_interpolatable_chain(expr),
+        _ => true,
+    }
+}
+
+/// whether the expression is a chain of accesses starting at a variable, which doesn't need parentheses
+fn is_interpolatable_chain(expr: &Expr) -> bool {
+    match expr.0 {
+        Expr_::Variable(_) => true,
+        Expr_::ArrayIdx(ref base, ref idxs) => idxs.iter().all(|x| x.is_some()) && is_interpolatable_chain(base),
+        // `($a?->b)->c` ends the short-circuiting of the nullsafe chain, which requires parentheses
+        Expr_::ObjMember(ref base, _, false) if match base.0 { Expr_::ObjMember(_, _, true) => true, _ => false } => false,
+        Expr_::ObjMember(ref base, _, _) | Expr_::StaticMember(ref base, _) | Expr_::Call(ref base, _) => is_interpolatable_chain(base),
+        _ => false,
     }
 }
@@ -12,17 +12,17 @@ fn process_expr(input: &str) -> Expr {
 
 #[test]
 fn parse_expr_comment() {
-    assert_eq!(process_expr("1/*test*/+/*test*/2"), enb!(0,19, Expr_::BinaryOp(Op::Add, eb!(0, 1, Expr_::Int(1)), eb!(18, 19, Expr_::Int(2)))));
+    assert_eq!(process_expr("1/*test*/+/*test*/2"), enb!(0,19, Expr_::BinaryOp(Op::Add, eb!(0, 1, Expr_::Int(1, false)), eb!(18, 19, Expr_::Int(2, false)))));
     //TODO: doc comment tests
 }
 
 #[test]
 fn parse_expr_op() {
-    assert_eq!(process_expr(r#"1+2"#), enb!(0,3, Expr_::BinaryOp(Op::Add, eb!(0, 1, Expr_::Int(1)), eb!(2, 3, Expr_::Int(2)))));
-    assert_eq!(process_expr(r#"1+2*3"#), enb!(0,5, Expr_::BinaryOp(Op::Add, eb!(0,1, Expr_::Int(1)), eb!(2,5, Expr_::BinaryOp(Op::Mul,
-        eb!(2,3, Expr_::Int(2)), eb!(4, 5, Expr_::Int(3))))))
+    assert_eq!(process_expr(r#"1+2"#), enb!(0,3, Expr_::BinaryOp(Op::Add, eb!(0, 1, Expr_::Int(1, false)), eb!(2, 3, Expr_::Int(2, false)))));
+    assert_eq!(process_expr(r#"1+2*3"#), enb!(0,5, Expr_::BinaryOp(Op::Add, eb!(0,1, Expr_::Int(1, false)), eb!(2,5, Expr_::BinaryOp(Op::Mul,
+        eb!(2,3, Expr_::Int(2, false)), eb!(4, 5, Expr_::Int(3, false))))))
     );
-    assert_eq!(process_expr(r#"2+$d**$c**$d"#), enb!(0, 12, Expr_::BinaryOp(Op::Add, eb!(0, 1, Expr_::Int(2)),
+    assert_eq!(process_expr(r#"2+$d**$c**$d"#), enb!(0, 12, Expr_::BinaryOp(Op::Add, eb!(0, 1, Expr_::Int(2, false)),
         eb!(2, 12, Expr_::BinaryOp(
             Op::Pow,
             eb!(2, 4, Expr_::Variable("d".into())),
@@ -33,7 +33,7 @@ fn parse_expr_op() {
         Op::Sub,
         eb!(0,7, Expr_::ArrayIdx(eb!(0,2, Expr_::Variable("g".into())), vec![ Some(enb!(3,6, Expr_::String("a".into()))) ])),
         eb!(8,17, Expr_::BinaryOp(Op::Div, eb!(8,15, Expr_::ArrayIdx(eb!(8,10, Expr_::Variable("g".into())), vec![
-            Some(enb!(11,14, Expr_::String("b".into()))) ])), eb!(16,17, Expr_::Int(3)))
+            Some(enb!(11,14, Expr_::String("b".into()))) ])), eb!(16,17, Expr_::Int(3, false)))
         )
     )));
 }
@@ -48,9 +48,24 @@ fn parse_expr_logical() {
     )));
 }
 
+#[test]
+fn parse_expr_coalesce() {
+    assert_eq!(process_expr("$a??$b??$c"), enb!(0,10, Expr_::BinaryOp(Op::Coalesce, eb!(0,2, Expr_::Variable("a".into())),
+        eb!(4,10, Expr_::BinaryOp(Op::Coalesce, eb!(4,6, Expr_::Variable("b".into())), eb!(8,10, Expr_::Variable("c".into()))))
+    )));
+    assert_eq!(process_expr("$a??$b?1:2"), enb!(0,10, Expr_::TernaryIf(
+        eb!(0,6, Expr_::BinaryOp(Op::Coalesce, eb!(0,2, Expr_::Variable("a".into())), eb!(4,6, Expr_::Variable("b".into())))),
+        Some(eb!(7,8, Expr_::Int(1, false))), eb!(9,10, Expr_::Int(2, false))
+    )));
+    assert_eq!(process_expr("$a||$b??$c"), enb!(0,10, Expr_::BinaryOp(Op::Coalesce,
+        eb!(0,6, Expr_::BinaryOp(Op::Or, eb!(0,2, Expr_::Variable("a".into())), eb!(4,6, Expr_::Variable("b".into())))),
+        eb!(8,10, Expr_::Variable("c".into()))
+    )));
+}
+
 #[test]
 fn parse_expr_parens() {
-    assert_eq!(process_expr(r#"(1+2)*3"#), enb!(0,7, Expr_::BinaryOp(Op::Mul, eb!(0,5, Expr_::BinaryOp(Op::Add, eb!(1,2, Expr_::Int(1)), eb!(3,4, Expr_::Int(2)))), eb!(6,7, Expr_::Int(3)))));
+    assert_eq!(process_expr(r#"(1+2)*3"#), enb!(0,7, Expr_::BinaryOp(Op::Mul, eb!(0,5, Expr_::BinaryOp(Op::Add, eb!(1,2, Expr_::Int(1, false)), eb!(3,4, Expr_::Int(2, false)))), eb!(6,7, Expr_::Int(3, false)))));
     assert_eq!(process_expr(r#"(true||false)&&true"#), enb!(0,19, Expr_::BinaryOp(Op::And, eb!(0, 13,
         Expr_::BinaryOp(Op::Or, eb!(1,5, constant!(true)), eb!(7,12,constant!(false)))), eb!(15,19, constant!(true))
     )));
@@ -62,19 +77,62 @@ fn parse_expr_string() {
     assert_eq!(process_expr(r#""""#), enb!(0, 2, Expr_::String("".into())));
     assert_eq!(process_expr(r#""t\nest\tsss\"os\"haha""#), enb!(0, 23, Expr_::String("t\nest\tsss\"os\"haha".into())));
     assert_eq!(process_expr(r#""\xe7\x9a\x84""#), enb!(0, 14, Expr_::String("的".into())));
-    //assert_eq!(process_expr(r#""a\142\143d""#), Expr_::String("abcd".into()));
+    assert_eq!(process_expr(r#""a\142\143d""#), enb!(0, 12, Expr_::String("abcd".into())));
+    assert_eq!(process_expr(r#""\u{1F600}\e""#), enb!(0, 13, Expr_::String("😀\x1B".into())));
     assert_eq!(process_expr(r#""a\"b\\\"c\\\"d\"e""#), enb!(0, 19, Expr_::String(r#"a"b\"c\"d"e"#.into())));
     assert_eq!(process_expr(r#""abc\ClassName""#), enb!(0, 15, Expr_::String("abc\\ClassName".into())));
 }
 
 #[test]
 fn parse_expr_string_fragmented() {
-    assert_eq!(process_expr(r#""hello $world""#), enb!(0,14, Expr_::BinaryOp(Op::Concat, eb!(1,7, Expr_::String("hello ".into())), eb!(7,13, Expr_::Variable("world".into())))));
-    assert_eq!(process_expr(r#""hello {$world}""#), enb!(0,16, Expr_::BinaryOp(Op::Concat, eb!(1,7, Expr_::String("hello ".into())), eb!(8,14, Expr_::Variable("world".into())))));
-    assert_eq!(process_expr(r#""hello $wor->ld""#), enb!(0,16, Expr_::BinaryOp(Op::Concat, eb!(1,7, Expr_::String("hello ".into())), eb!(7,15, Expr_::ObjMember(
-        eb!(7,11, Expr_::Variable("wor".into())), vec![ enb!(13,15, Expr_::Path(Path::identifier(false, "ld".into()))) ]
-    )))));
-    assert_eq!(process_expr(r#""hello ${world}""#), enb!(0,16, Expr_::BinaryOp(Op::Concat, eb!(1,7, Expr_::String("hello ".into())), eb!(7,15, Expr_::Variable("world".into())))));
+    assert_eq!(process_expr(r#""hello $world""#), enb!(0,14, Expr_::Interpolated(StringKind::DoubleQuote, vec![
+        InterpolatedPart::Literal("hello ".into()), InterpolatedPart::Expr(enb!(7,13, Expr_::Variable("world".into()))),
+    ])));
+    assert_eq!(process_expr(r#""hello {$world}!""#), enb!(0,17, Expr_::Interpolated(StringKind::DoubleQuote, vec![
+        InterpolatedPart::Literal("hello ".into()), InterpolatedPart::Expr(enb!(8,14, Expr_::Variable("world".into()))),
+        InterpolatedPart::Literal("!".into()),
+    ])));
+    assert_eq!(process_expr(r#""hello $wor->ld""#), enb!(0,16, Expr_::Interpolated(StringKind::DoubleQuote, vec![
+        InterpolatedPart::Literal("hello ".into()), InterpolatedPart::Expr(enb!(7,15, Expr_::ObjMember(
+            eb!(7,11, Expr_::Variable("wor".into())), vec![ enb!(13,15, Expr_::Path(Path::identifier(false, "ld".into()))) ], false
+        ))),
+    ])));
+    assert_eq!(process_expr(r#""hello ${world}""#), enb!(0,16, Expr_::Interpolated(StringKind::DoubleQuote, vec![
+        InterpolatedPart::Literal("hello ".into()), InterpolatedPart::Expr(enb!(7,15, Expr_::Variable("world".into()))),
+    ])));
+    assert_eq!(process_expr("<<<EOT\na $b\nEOT\n"), enb!(0,15, Expr_::Interpolated(StringKind::HereDoc("EOT".into(), "".into(), false), vec![
+        InterpolatedPart::Literal("a ".into()), InterpolatedPart::Expr(enb!(9,11, Expr_::Variable("b".into()))),
+    ])));
+    assert_eq!(process_expr("<<<EOT\n  a $b\n  EOT"), enb!(0,19, Expr_::Interpolated(StringKind::HereDoc("EOT".into(), "  ".into(), false), vec![
+        InterpolatedPart::Literal("a ".into()), InterpolatedPart::Expr(enb!(11,13, Expr_::Variable("b".into()))),
+    ])));
+    assert_eq!(process_expr("<<<'EOT'\n\ta $b\n\tEOT"), enb!(0,19, Expr_::Interpolated(StringKind::NowDoc("EOT".into(), "\t".into(), false), vec![
+        InterpolatedPart::Literal("a $b".into()),
+    ])));
+    assert_eq!(process_expr("[<<<EOT\na\nEOT, 1]"), enb!(0,17, Expr_::Array(vec![
+        (None, enb!(1,13, Expr_::Interpolated(StringKind::HereDoc("EOT".into(), "".into(), true), vec![ InterpolatedPart::Literal("a".into()) ]))),
+        (None, enb!(15,16, Expr_::Int(1, false))),
+    ])));
+    assert_eq!(process_expr("`ls`"), enb!(0,4, Expr_::Interpolated(StringKind::Backquote, vec![ InterpolatedPart::Literal("ls".into()) ])));
+}
+
+#[test]
+fn parse_expr_string_var_offset() {
+    fn dq_part(expr: Expr) -> Expr_ {
+        Expr_::Interpolated(StringKind::DoubleQuote, vec![ InterpolatedPart::Expr(expr) ])
+    }
+    assert_eq!(process_expr(r#""$a[0]""#), enb!(0,7, dq_part(enb!(1,6, Expr_::ArrayIdx(eb!(1,3, Expr_::Variable("a".into())), vec![ Some(enb!(4,5, Expr_::Int(0, false))) ])))));
+    assert_eq!(process_expr(r#""$a[-1]""#), enb!(0,8, dq_part(enb!(1,7, Expr_::ArrayIdx(eb!(1,3, Expr_::Variable("a".into())), vec![ Some(enb!(4,6, Expr_::Int(-1, false))) ])))));
+    assert_eq!(process_expr(r#""$a[01]""#), enb!(0,8, dq_part(enb!(1,7, Expr_::ArrayIdx(eb!(1,3, Expr_::Variable("a".into())), vec![ Some(enb!(4,6, Expr_::String("01".into()))) ])))));
+    assert_eq!(process_expr(r#""$a[0x1]""#), enb!(0,9, dq_part(enb!(1,8, Expr_::ArrayIdx(eb!(1,3, Expr_::Variable("a".into())), vec![ Some(enb!(4,7, Expr_::String("0x1".into()))) ])))));
+    assert_eq!(process_expr(r#""$a[key]""#), enb!(0,9, dq_part(enb!(1,8, Expr_::ArrayIdx(eb!(1,3, Expr_::Variable("a".into())), vec![ Some(enb!(4,7, Expr_::String("key".into()))) ])))));
+    assert_eq!(process_expr(r#""x $a[$i]""#), enb!(0,10, Expr_::Interpolated(StringKind::DoubleQuote, vec![
+        InterpolatedPart::Literal("x ".into()),
+        InterpolatedPart::Expr(enb!(3,9, Expr_::ArrayIdx(eb!(3,5, Expr_::Variable("a".into())), vec![ Some(enb!(6,8, Expr_::Variable("i".into()))) ]))),
+    ])));
+    assert_eq!(process_expr(r#""{$a->b['c']}""#), enb!(0,14, dq_part(enb!(2,12, Expr_::ArrayIdx(eb!(2,7, Expr_::ObjMember(
+        eb!(2,4, Expr_::Variable("a".into())), vec![ enb!(6,7, Expr_::Path(Path::identifier(false, "b".into()))) ], false
+    )), vec![ Some(enb!(8,11, Expr_::String("c".into()))) ])))));
 }
 
 #[test]
@@ -92,40 +150,70 @@ fn parse_ns_identifier() {
     assert_eq!(process_expr("\\Test\\Ns1\\Ns2\\Ns3"), enb!(0, 17, Expr_::Path(Path::ns_identifier(true, "Test\\Ns1\\Ns2".into(), "Ns3".into()))));
 }
 
+#[test]
+fn parse_expr_variable_variable() {
+    assert_eq!(process_expr("$$a"), enb!(0,3, Expr_::Variable(Variable::Fetch(eb!(1,3, Expr_::Variable("a".into()))))));
+    assert_eq!(process_expr("$$$a"), enb!(0,4, Expr_::Variable(Variable::Fetch(eb!(1,4, Expr_::Variable(Variable::Fetch(
+        eb!(2,4, Expr_::Variable("a".into()))
+    )))))));
+    assert_eq!(process_expr("${'a'}"), enb!(0,6, Expr_::Variable(Variable::Fetch(eb!(2,5, Expr_::String("a".into()))))));
+    assert_eq!(process_expr("${$a . 'b'}"), enb!(0,11, Expr_::Variable(Variable::Fetch(eb!(2,10, Expr_::BinaryOp(Op::Concat,
+        eb!(2,4, Expr_::Variable("a".into())), eb!(7,10, Expr_::String("b".into()))
+    ))))));
+    assert_eq!(process_expr("$obj->$$a"), enb!(0,9, Expr_::ObjMember(eb!(0,4, Expr_::Variable("obj".into())), vec![
+        enb!(6,9, Expr_::Variable(Variable::Fetch(eb!(7,9, Expr_::Variable("a".into())))))
+    ], false)));
+    assert_eq!(process_expr("static::$$a"), enb!(0,11, Expr_::StaticMember(eb!(0,6, Expr_::Path(Path::identifier(false, "static".into()))), vec![
+        enb!(8,11, Expr_::Variable(Variable::Fetch(eb!(9,11, Expr_::Variable("a".into())))))
+    ])));
+}
+
 #[test]
 fn parse_expr_object_property() {
     //TODO: fix line numbers
     assert_eq!(process_expr(r#"$obj->prop"#), enb!(0,10, Expr_::ObjMember(eb!(0,4, Expr_::Variable("obj".into())), vec![
         enb!(6,10, Expr_::Path(Path::identifier(false, "prop".into())))
-    ])));
+    ], false)));
     assert_eq!(process_expr(r#"$obj->a->b->c->d"#), enb!(0,16, Expr_::ObjMember(eb!(0,4, Expr_::Variable("obj".into())),
         vec![enb!(6,7, Expr_::Path(Path::identifier(false, "a".into()))), enb!(9,10, Expr_::Path(Path::identifier(false, "b".into()))),
-            enb!(12,13, Expr_::Path(Path::identifier(false, "c".into()))), enb!(15,16, Expr_::Path(Path::identifier(false, "d".into())))])
+            enb!(12,13, Expr_::Path(Path::identifier(false, "c".into()))), enb!(15,16, Expr_::Path(Path::identifier(false, "d".into())))], false)
     ));
     assert_eq!(process_expr(r#"$obj->$a->b"#), enb!(0,11, Expr_::ObjMember(eb!(0,4, Expr_::Variable("obj".into())), vec![
-        enb!(6,8, Expr_::Variable("a".into())), enb!(10,11, Expr_::Path(Path::identifier(false, "b".into()))) ])
+        enb!(6,8, Expr_::Variable("a".into())), enb!(10,11, Expr_::Path(Path::identifier(false, "b".into()))) ], false)
     ));
     assert_eq!(process_expr("$obj->{$obj->b}->c"), enb!(0,18, Expr_::ObjMember(eb!(0,4, Expr_::Variable("obj".into())), vec![
-        enb!(7,14, Expr_::ObjMember(eb!(7,11, Expr_::Variable("obj".into())), vec![ enb!(13,14, Expr_::Path(Path::identifier(false, "b".into()))) ])),
+        enb!(7,14, Expr_::ObjMember(eb!(7,11, Expr_::Variable("obj".into())), vec![ enb!(13,14, Expr_::Path(Path::identifier(false, "b".into()))) ], false)),
             enb!(17,18, Expr_::Path(Path::identifier(false, "c".into())))
-        ]))
+        ], false))
     );
     assert_eq!(process_expr("$obj->{$a->{$b->c}->d}->e"), enb!(0,25, Expr_::ObjMember(eb!(0,4, Expr_::Variable("obj".into())), vec![
         enb!(7,21, Expr_::ObjMember(eb!(7,9, Expr_::Variable("a".into())), vec![
-            enb!(12,17, Expr_::ObjMember(eb!(12,14, Expr_::Variable("b".into())), vec![ enb!(16,17, Expr_::Path(Path::identifier(false, "c".into()))) ])),
+            enb!(12,17, Expr_::ObjMember(eb!(12,14, Expr_::Variable("b".into())), vec![ enb!(16,17, Expr_::Path(Path::identifier(false, "c".into()))) ], false)),
             enb!(20,21, Expr_::Path(Path::identifier(false, "d".into())))
-        ])), enb!(24,25, Expr_::Path(Path::identifier(false, "e".into())))
-    ])));
+        ], false)), enb!(24,25, Expr_::Path(Path::identifier(false, "e".into())))
+    ], false)));
     assert_eq!(process_expr(r#"$obj->$a->b()"#), enb!(0,13, Expr_::Call(eb!(0,11, Expr_::ObjMember(
         eb!(0,4, Expr_::Variable("obj".into())),
-        vec![ enb!(6,8, Expr_::Variable("a".into())), enb!(10,11, Expr_::Path(Path::identifier(false, "b".into()))) ]
+        vec![ enb!(6,8, Expr_::Variable("a".into())), enb!(10,11, Expr_::Path(Path::identifier(false, "b".into()))) ], false
     )), vec![])));
 }
 
+#[test]
+fn parse_expr_nullsafe_object_property() {
+    // the chain after ?-> belongs to the nullsafe node, until it is interrupted by a call
+    assert_eq!(process_expr("$a?->b->c()?->d"), enb!(0,15, Expr_::ObjMember(eb!(0,11, Expr_::Call(eb!(0,9, Expr_::ObjMember(
+        eb!(0,2, Expr_::Variable("a".into())),
+        vec![ enb!(5,6, Expr_::Path(Path::identifier(false, "b".into()))), enb!(8,9, Expr_::Path(Path::identifier(false, "c".into()))) ], true
+    )), vec![])), vec![ enb!(14,15, Expr_::Path(Path::identifier(false, "d".into()))) ], true)));
+    assert_eq!(process_expr(r#""$a?->b""#), enb!(0,8, Expr_::Interpolated(StringKind::DoubleQuote, vec![
+        InterpolatedPart::Expr(enb!(1,7, Expr_::ObjMember(eb!(1,3, Expr_::Variable("a".into())), vec![ enb!(6,7, Expr_::Path(Path::identifier(false, "b".into()))) ], true))),
+    ])));
+}
+
 #[test]
 fn parse_expr_array_idx() {
     assert_eq!(process_expr(r#"$test["a"]"#), enb!(0,10, Expr_::ArrayIdx(eb!(0,5, Expr_::Variable("test".into())), vec![ Some(enb!(6,9, Expr_::String("a".into()))) ])));
-    assert_eq!(process_expr(r#"$test[9]"#), enb!(0,8, Expr_::ArrayIdx(eb!(0,5, Expr_::Variable("test".into())), vec![ Some(enb!(6,7, Expr_::Int(9))) ])));
+    assert_eq!(process_expr(r#"$test[9]"#), enb!(0,8, Expr_::ArrayIdx(eb!(0,5, Expr_::Variable("test".into())), vec![ Some(enb!(6,7, Expr_::Int(9, false))) ])));
     assert_eq!(process_expr(r#"$test["a"]['b\n']"#), enb!(0,17, Expr_::ArrayIdx(eb!(0,5, Expr_::Variable("test".into())), vec![
         Some(enb!(6,9, Expr_::String("a".into()))), Some(enb!(11,16, Expr_::String("b\\n".into())))
     ])));
@@ -136,33 +224,41 @@ fn parse_expr_array_idx() {
     ])));
 }
 
+#[test]
+fn parse_expr_first_class_callable() {
+    assert_eq!(process_expr("strlen(...)"), enb!(0,11, Expr_::FirstClassCallable(eb!(0,6, Expr_::Path(Path::identifier(false, "strlen".into()))))));
+    assert_eq!(process_expr("$a->b(...)"), enb!(0,10, Expr_::FirstClassCallable(eb!(0,5, Expr_::ObjMember(eb!(0,2, Expr_::Variable("a".into())), vec![
+        enb!(4,5, Expr_::Path(Path::identifier(false, "b".into())))
+    ], false)))));
+}
+
 #[test]
 fn parse_expr_func_call() {
     assert_eq!(process_expr(r#"test()"#), enb!(0,6, Expr_::Call(eb!(0,4, Expr_::Path(Path::identifier(false, "test".into()))), vec![])));
     assert_eq!(process_expr(r#"array_()"#), enb!(0,8, Expr_::Call(eb!(0,6, Expr_::Path(Path::identifier(false, "array_".into()))), vec![])));
     assert_eq!(process_expr(r#"func_x(1, 2)"#), enb!(0,12, Expr_::Call(eb!(0,6, Expr_::Path(Path::identifier(false, "func_x".into()))),
-        vec![ enb!(7,8, Expr_::Int(1)), enb!(10,11, Expr_::Int(2)) ]
+        vec![ enb!(7,8, Expr_::Int(1, false)).into(), enb!(10,11, Expr_::Int(2, false)).into() ]
     )));
     assert_eq!(process_expr(r#"func_x(abc(1), 2)"#), enb!(0,17, Expr_::Call(eb!(0,6, Expr_::Path(Path::identifier(false, "func_x".into()))), vec![
-        enb!(7,13, Expr_::Call(eb!(7,10, Expr_::Path(Path::identifier(false, "abc".into()))), vec![ enb!(11,12, Expr_::Int(1)) ])),
-        enb!(15, 16, Expr_::Int(2))
+        enb!(7,13, Expr_::Call(eb!(7,10, Expr_::Path(Path::identifier(false, "abc".into()))), vec![ enb!(11,12, Expr_::Int(1, false)).into() ])).into(),
+        enb!(15, 16, Expr_::Int(2, false)).into()
     ])));
     assert_eq!(process_expr(r#"$g[0]()"#), enb!(0,7, Expr_::Call(eb!(0,5, Expr_::ArrayIdx(eb!(0,2, Expr_::Variable("g".into())), vec![
-        Some(enb!(3,4, Expr_::Int(0))) ])), vec![])));
+        Some(enb!(3,4, Expr_::Int(0, false))) ])), vec![])));
     assert_eq!(process_expr(r#"$g[0]()[1](true)"#), enb!(0,16, Expr_::Call(
         eb!(0,10, Expr_::ArrayIdx(
             eb!(0,7, Expr_::Call(
-                eb!(0,5, Expr_::ArrayIdx(eb!(0,2, Expr_::Variable("g".into())), vec![ Some(enb!(3,4, Expr_::Int(0))) ])),
+                eb!(0,5, Expr_::ArrayIdx(eb!(0,2, Expr_::Variable("g".into())), vec![ Some(enb!(3,4, Expr_::Int(0, false))) ])),
                 vec![]
-            )), vec![ Some(enb!(8,9, Expr_::Int(1))) ]
-        )), vec![ enb!(11,15, constant!(true)) ]
+            )), vec![ Some(enb!(8,9, Expr_::Int(1, false))) ]
+        )), vec![ enb!(11,15, constant!(true)).into() ]
     )));
 }
 
 #[test]
 fn parse_expr_require() {
     assert_eq!(process_expr("abc(require $path)"), enb!(0,18, Expr_::Call(eb!(0,3, Expr_::Path(Path::identifier(false, "abc".into()))),
-        vec![ enb!(4,17, Expr_::Include(IncludeTy::Require, eb!(12,17, Expr_::Variable("path".into())))) ])
+        vec![ enb!(4,17, Expr_::Include(IncludeTy::Require, eb!(12,17, Expr_::Variable("path".into())))).into() ])
     ));
 }
 
@@ -206,6 +302,16 @@ fn parse_expr_static_const() {
     assert_eq!(process_expr(r#"Obj::test"#), enb!(0,9, Expr_::StaticMember(eb!(0,3, Expr_::Path(Path::identifier(false, "Obj".into()))), vec![ enb!(5,9, Expr_::Path(
         Path::identifier(false, "test".into()))) ]))
     );
+    // keywords keep their spelling when used as a name
+    assert_eq!(process_expr("Obj::DEFAULT"), enb!(0,12, Expr_::StaticMember(eb!(0,3, Expr_::Path(Path::identifier(false, "Obj".into()))), vec![
+        enb!(5,12, Expr_::Path(Path::identifier(false, "DEFAULT".into())))
+    ])));
+    assert_eq!(process_expr("$obj::class"), enb!(0,11, Expr_::StaticMember(eb!(0,4, Expr_::Variable("obj".into())), vec![
+        enb!(6,11, Expr_::Path(Path::identifier(false, "class".into())))
+    ])));
+    assert_eq!(process_expr("$a->b::C"), enb!(0,8, Expr_::StaticMember(eb!(0,5, Expr_::ObjMember(eb!(0,2, Expr_::Variable("a".into())), vec![
+        enb!(4,5, Expr_::Path(Path::identifier(false, "b".into())))
+    ], false)), vec![ enb!(7,8, Expr_::Path(Path::identifier(false, "C".into()))) ])));
 }
 
 #[test]
@@ -218,6 +324,12 @@ fn parse_expr_static_property() {
         enb!(5,9, Expr_::Path(Path::identifier(false, "test".into())))
     ])), vec![])));
     assert_eq!(process_expr("static::$abc"), enb!(0,12, Expr_::StaticMember(eb!(0,6, Expr_::Path(Path::identifier(false, "static".into()))), vec![ enb!(8,12, Expr_::Variable("abc".into())) ])));
+    assert_eq!(process_expr("Obj::static()"), enb!(0,13, Expr_::Call(eb!(0,11, Expr_::StaticMember(eb!(0,3, Expr_::Path(Path::identifier(false, "Obj".into()))), vec![
+        enb!(5,11, Expr_::Path(Path::identifier(false, "static".into())))
+    ])), vec![])));
+    assert_eq!(process_expr("$a::LIST()"), enb!(0,10, Expr_::Call(eb!(0,8, Expr_::StaticMember(eb!(0,2, Expr_::Variable("a".into())), vec![
+        enb!(4,8, Expr_::Path(Path::identifier(false, "LIST".into())))
+    ])), vec![])));
 }
 
 #[test]
@@ -254,33 +366,38 @@ fn parse_expr_clone() {
 fn parse_expr_array_append() {
     // for now we support append-expressions like that, TODO: figure out error reporting (AST_Node -> Position in source file)
     assert_eq!(process_expr(r#"$test[]=1"#), enb!(0,9, Expr_::Assign(eb!(0,7, Expr_::ArrayIdx(eb!(0,5, Expr_::Variable("test".into())), vec![ None ])),
-        eb!(8,9, Expr_::Int(1))
+        eb!(8,9, Expr_::Int(1, false))
     )));
 }
 
 #[test]
 fn parse_expr_assign() {
-    assert_eq!(process_expr("($b=4)"), enb!(0,6, Expr_::Assign(eb!(1,3, Expr_::Variable("b".into())), eb!(4,5, Expr_::Int(4)))));
+    assert_eq!(process_expr("($b=4)"), enb!(0,6, Expr_::Assign(eb!(1,3, Expr_::Variable("b".into())), eb!(4,5, Expr_::Int(4, false)))));
     assert_eq!(process_expr("!($b=1)"), enb!(0,7, Expr_::UnaryOp(UnaryOp::Not, eb!(1,7, Expr_::Assign(eb!(2,4, Expr_::Variable("b".into())),
-        eb!(5,6, Expr_::Int(1)))))));
+        eb!(5,6, Expr_::Int(1, false)))))));
     assert_eq!(process_expr("!$b=1"), enb!(0,5, Expr_::UnaryOp(UnaryOp::Not, eb!(1,5, Expr_::Assign(eb!(1,3, Expr_::Variable("b".into())),
-        eb!(4,5, Expr_::Int(1)))))));
+        eb!(4,5, Expr_::Int(1, false)))))));
 }
 
 #[test]
 fn parse_expr_array() {
     assert_eq!(process_expr("[]"), enb!(0,2, Expr_::Array(vec![])));
-    assert_eq!(process_expr("[1,]"), enb!(0,4, Expr_::Array(vec![ (None, enb!(1,2, Expr_::Int(1))) ])));
+    assert_eq!(process_expr("[1,]"), enb!(0,4, Expr_::Array(vec![ (None, enb!(1,2, Expr_::Int(1, false))) ])));
     assert_eq!(process_expr("[1, 2]"), enb!(0,6, Expr_::Array(vec![
-        (None, enb!(1,2, Expr_::Int(1))), (None, enb!(4,5, Expr_::Int(2)))
+        (None, enb!(1,2, Expr_::Int(1, false))), (None, enb!(4,5, Expr_::Int(2, false)))
     ])));
     assert_eq!(process_expr("[1, [2, 3], 3]"), enb!(0,14, Expr_::Array(vec![
-        (None, enb!(1,2, Expr_::Int(1))), (None, enb!(4,10, Expr_::Array(vec![
-            (None, enb!(5,6, Expr_::Int(2))), (None, enb!(8,9, Expr_::Int(3)))
+        (None, enb!(1,2, Expr_::Int(1, false))), (None, enb!(4,10, Expr_::Array(vec![
+            (None, enb!(5,6, Expr_::Int(2, false))), (None, enb!(8,9, Expr_::Int(3, false)))
         ]))),
-        (None, enb!(12,13, Expr_::Int(3)))
+        (None, enb!(12,13, Expr_::Int(3, false)))
     ])));
     assert_eq!(process_expr("array()"), enb!(0,7, Expr_::Array(vec![])));
+    assert_eq!(process_expr("[...$a, 1, ...b()]"), enb!(0,18, Expr_::Array(vec![
+        (None, enb!(1,6, Expr_::Unpack(eb!(4,6, Expr_::Variable("a".into()))))),
+        (None, enb!(8,9, Expr_::Int(1, false))),
+        (None, enb!(11,17, Expr_::Unpack(eb!(14,17, Expr_::Call(eb!(14,15, Expr_::Path(Path::identifier(false, "b".into()))), vec![]))))),
+    ])));
 }
 
 #[test]
@@ -293,24 +410,132 @@ fn parse_expr_assoc_array() {
 #[test]
 fn parse_expr_closure() {
     assert_eq!(process_expr("function () { c(); }"), enb!(0,20, Expr_::Function(FunctionDecl {
+        attributes: vec![],
         params: vec![],
         body: Some(Block(vec![ senb!(14,17, Expr_::Call(eb!(14,15, Expr_::Path(Path::identifier(false, "c".into()))), vec![])) ])), usev: vec![], ret_ref: false, ret_ty: None,
-    })));
+    }, false)));
+}
+
+#[test]
+fn parse_expr_closure_attributes() {
+    assert_eq!(process_expr("#[Pure] fn(#[A(1)] $x) => $x"), enb!(0,28, Expr_::ArrowFunction(FunctionDecl {
+        attributes: vec![ Attribute { name: Path::identifier(false, "Pure".into()), args: vec![] } ],
+        params: vec![ ParamDefinition {
+            attributes: vec![ Attribute { name: Path::identifier(false, "A".into()), args: vec![ enb!(15,16, Expr_::Int(1, false)).into() ] } ], modifiers: MemberModifiers::none(),
+            name: "x".into(), as_ref: false, variadic: false, ty: None, default: None,
+        } ],
+        body: None, usev: vec![], ret_ref: false, ret_ty: None,
+    }, eb!(26,28, Expr_::Variable("x".into())), false)));
+    match process_expr("#[A] #[B, C] function () {}").0 {
+        Expr_::Function(ref decl, false) => assert_eq!(decl.attributes.iter().map(|x| x.name.to_string()).collect::<Vec<_>>(), vec!["A", "B", "C"]),
+        ref x => panic!("expected closure, got {:?}", x),
+    }
+    assert_eq!(process_expr("#[A] static fn() => 1"), enb!(0,21, Expr_::ArrowFunction(FunctionDecl {
+        attributes: vec![ Attribute { name: Path::identifier(false, "A".into()), args: vec![] } ],
+        params: vec![], body: None, usev: vec![], ret_ref: false, ret_ty: None,
+    }, eb!(20,21, Expr_::Int(1, false)), true)));
+    match process_expr("#[A] static function () {}") {
+        Expr(Expr_::Function(ref decl, true), ref span) => {
+            assert_eq!(decl.attributes.len(), 1);
+            assert_eq!((span.start, span.end), (6, 32));
+        }
+        ref x => panic!("expected static closure, got {:?}", x),
+    }
+}
+
+#[test]
+fn parse_expr_static_closure() {
+    assert_eq!(process_expr("static function () {}"), enb!(0,21, Expr_::Function(FunctionDecl {
+        attributes: vec![], params: vec![], body: Some(Block(vec![])), usev: vec![], ret_ref: false, ret_ty: None,
+    }, true)));
+    assert_eq!(process_expr("static fn() => 1"), enb!(0,16, Expr_::ArrowFunction(FunctionDecl {
+        attributes: vec![], params: vec![], body: None, usev: vec![], ret_ref: false, ret_ty: None,
+    }, eb!(15,16, Expr_::Int(1, false)), true)));
+}
+
+#[test]
+fn parse_expr_arrow_function() {
+    assert_eq!(process_expr("fn($x) => $x * $y"), enb!(0,17, Expr_::ArrowFunction(FunctionDecl {
+        attributes: vec![],
+        params: vec![ ParamDefinition { attributes: vec![], modifiers: MemberModifiers::none(), name: "x".into(), as_ref: false, variadic: false, ty: None, default: None } ],
+        body: None, usev: vec![(false, "y".into())], ret_ref: false, ret_ty: None,
+    }, eb!(10,17, Expr_::BinaryOp(Op::Mul, eb!(10,12, Expr_::Variable("x".into())), eb!(15,17, Expr_::Variable("y".into())))), false)));
+    assert_eq!(process_expr("fn&(int $a): int => $this"), enb!(0,25, Expr_::ArrowFunction(FunctionDecl {
+        attributes: vec![],
+        params: vec![ ParamDefinition { attributes: vec![], modifiers: MemberModifiers::none(), name: "a".into(), as_ref: false, variadic: false, ty: Some(TypeExpr::Simple(Ty::Int)), default: None } ],
+        body: None, usev: vec![], ret_ref: true, ret_ty: Some(TypeExpr::Simple(Ty::Int)),
+    }, eb!(20,25, Expr_::Variable("this".into())), false)));
+    // the captures of nested functions are captured too
+    match process_expr("fn($x) => fn($y) => $x + $y + $z + function () use ($w) {}").0 {
+        Expr_::ArrowFunction(ref decl, ref body, _) => {
+            assert_eq!(decl.usev, vec![(false, "z".into()), (false, "w".into())]);
+            match body.0 {
+                Expr_::ArrowFunction(ref decl, _, _) => assert_eq!(decl.usev, vec![(false, "x".into()), (false, "z".into()), (false, "w".into())]),
+                ref expr => panic!("{:?}", expr),
+            }
+        }
+        ref expr => panic!("{:?}", expr),
+    }
+}
+
+#[test]
+fn parse_expr_match() {
+    assert_eq!(process_expr("match ($a) { 1, 2 => 'x', default => $b, }"), enb!(0,42, Expr_::Match(eb!(7,9, Expr_::Variable("a".into())), vec![
+        MatchArm { conds: vec![ enb!(13,14, Expr_::Int(1, false)), enb!(16,17, Expr_::Int(2, false)) ], default: false, expr: enb!(21,24, Expr_::String("x".into())), span: mk_span(19u32, 30u32) },
+        MatchArm { conds: vec![], default: true, expr: enb!(37,39, Expr_::Variable("b".into())), span: mk_span(32u32, 45u32) },
+    ])));
+    assert_eq!(process_expr("match (true) {}"), enb!(0,15, Expr_::Match(eb!(7,11, Expr_::Path(Path::identifier(false, "true".into()))), vec![])));
 }
 
 #[test]
 fn parse_expr_priority_parents_call() {
     assert_eq!(process_expr("(new Factory)->test"), enb!(0,19, Expr_::ObjMember(eb!(0,13, Expr_::New(eb!(5,12, Expr_::Path(Path::identifier(false, "Factory".into()))), vec![])),
-        vec![ enb!(15,19, Expr_::Path(Path::identifier(false, "test".into()))) ]
+        vec![ enb!(15,19, Expr_::Path(Path::identifier(false, "test".into()))) ], false
     )));
     assert_eq!(process_expr("(new $obj)->method()"), enb!(0,20, Expr_::Call(eb!(0,18, Expr_::ObjMember(eb!(0,10, Expr_::New(eb!(5,9, Expr_::Variable("obj".into())), vec![])),
-        vec![ enb!(12,18, Expr_::Path(Path::identifier(false, "method".into()))) ]
+        vec![ enb!(12,18, Expr_::Path(Path::identifier(false, "method".into()))) ], false
     )), vec![])));
 }
 
+#[test]
+fn parse_expr_numeric_separators() {
+    assert_eq!(process_expr("1_000"), enb!(0,5, Expr_::Int(1000, true)));
+    assert_eq!(process_expr("1_0.5"), enb!(0,5, Expr_::Double(10.5, true)));
+    assert_eq!(process_expr("1000"), enb!(0,4, Expr_::Int(1000, false)));
+}
+
 #[test]
 fn parse_expr_unpack() {
     assert_eq!(process_expr("t(...$email)"), enb!(0,12, Expr_::Call(eb!(0,1, Expr_::Path(Path::identifier(false, "t".into()))), vec![
-        enb!(2,11, Expr_::Unpack(eb!(5,11, Expr_::Variable("email".into()))))
+        Argument { name: None, unpack: true, by_ref: false, value: enb!(5,11, Expr_::Variable("email".into())) },
+    ])));
+}
+
+#[test]
+fn parse_expr_yield() {
+    assert_eq!(process_expr("yield"), enb!(0,5, Expr_::Yield(None, None)));
+    assert_eq!(process_expr("yield $a => $b + 1"), enb!(0,18, Expr_::Yield(Some(eb!(6,8, Expr_::Variable("a".into()))),
+        Some(eb!(12,18, Expr_::BinaryOp(Op::Add, eb!(12,14, Expr_::Variable("b".into())), eb!(17,18, Expr_::Int(1, false))))))));
+    // only `and`, `xor` and `or` bind weaker than yield
+    assert_eq!(process_expr("yield $a or $b"), enb!(0,14, Expr_::BinaryOp(Op::Or,
+        eb!(0,8, Expr_::Yield(None, Some(eb!(6,8, Expr_::Variable("a".into()))))), eb!(12,14, Expr_::Variable("b".into())))));
+    assert_eq!(process_expr("yield from gen()"), enb!(0,16, Expr_::YieldFrom(eb!(11,16, Expr_::Call(eb!(11,14,
+        Expr_::Path(Path::identifier(false, "gen".into()))), vec![])))));
+}
+
+#[test]
+fn parse_expr_named_arguments() {
+    assert_eq!(process_expr("foo(limit: 10, ...$rest)"), enb!(0,24, Expr_::Call(eb!(0,3, Expr_::Path(Path::identifier(false, "foo".into()))), vec![
+        Argument { name: Some("limit".into()), unpack: false, by_ref: false, value: enb!(11,13, Expr_::Int(10, false)) },
+        Argument { name: None, unpack: true, by_ref: false, value: enb!(18,23, Expr_::Variable("rest".into())) },
+    ])));
+    assert_eq!(process_expr("new A($a ? b : c, array: [])"), enb!(0,28, Expr_::New(eb!(4,5, Expr_::Path(Path::identifier(false, "A".into()))), vec![
+        enb!(6,16, Expr_::TernaryIf(eb!(6,8, Expr_::Variable("a".into())), Some(eb!(11,12, Expr_::Path(Path::identifier(false, "b".into())))),
+            eb!(15,16, Expr_::Path(Path::identifier(false, "c".into()))))).into(),
+        Argument { name: Some("array".into()), unpack: false, by_ref: false, value: enb!(25,27, Expr_::Array(vec![])) },
+    ])));
+    // modifiers are valid parameter names too
+    assert_eq!(process_expr("foo(static: 3)"), enb!(0,14, Expr_::Call(eb!(0,3, Expr_::Path(Path::identifier(false, "foo".into()))), vec![
+        Argument { name: Some("static".into()), unpack: false, by_ref: false, value: enb!(12,13, Expr_::Int(3, false)) },
     ])));
 }
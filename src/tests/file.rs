@@ -1,3 +1,4 @@
+use std::rc::Rc;
 use parser::*;
 
 fn process_script(input: &str) -> Vec<Stmt> {
@@ -15,4 +16,331 @@ fn parse_simple_file_echos() {
     ]);
 }
 
+#[test]
+fn parse_close_tag_as_semicolon() {
+    assert_eq!(process_script("<?= $a ?>b<?php if ($c) { ?>d<?php } ?>"), vec![
+        rsnb!(0,9, Stmt_::Echo(vec![ rnb!(4,6, Expr_::Variable("a".into())) ])),
+        rsnb!(9,10, Stmt_::Echo(vec![ rnb!(9,10, Expr_::String("b".into())) ])),
+        rsnb!(16,36, Stmt_::If(Box::new(rnb!(20,22, Expr_::Variable("c".into()))), Block(vec![
+            rsnb!(28,29, Stmt_::Echo(vec![ rnb!(28,29, Expr_::String("d".into())) ])),
+        ]), Block::empty())),
+    ]);
+    // `?>` ends an expression which ends in `}`
+    assert_eq!(process_script("<?php $f = function() { } ?>x").len(), 2);
+    assert_eq!(process_script("<?php echo match (1) { default => 2 } ?>x").len(), 2);
+    assert_eq!(process_script("<?php switch ($b) { ?><?php case 1: ?>y<?php } ?>"), vec![
+        rsnb!(6,46, Stmt_::Switch(Box::new(rnb!(14,16, Expr_::Variable("b".into()))), vec![
+            SwitchCase { default: false, conds: vec![ rnb!(33,34, Expr_::Int(1, false)) ], block: Block(vec![
+                rsnb!(38,39, Stmt_::Echo(vec![ rnb!(38,39, Expr_::String("y".into())) ])),
+            ]) },
+        ])),
+    ]);
+}
+
 // TEST invalid cases TODO: like <?php echo "test" (missing semicolon, should actually parse?)
+
+#[test]
+fn parse_invalid_escape_sequence() {
+    let src = "<?php\necho \"\\u{110000}\";";
+    let err = Parser::parse_str(src).unwrap_err();
+    assert_eq!(err.error_message(Some(src)), "Invalid UTF-8 codepoint escape sequence: Codepoint too large at line 1\necho \"\\u{110000}\";\n      ^~~~~~~~~~");
+}
+
+#[test]
+fn parse_non_utf8_file() {
+    let src = b"\xe9t\xe9<?php echo 'caf\xe9', \"caf\xe9\", 'ok';";
+    assert_eq!(Parser::parse_bytes(src).unwrap(), vec![
+        rsnb!(0,3, Stmt_::Echo(vec![ rnb!(0,3, Expr_::BinaryString(Rc::new(b"\xe9t\xe9".to_vec()))) ])),
+        rsnb!(9,35, Stmt_::Echo(vec![
+            rnb!(14,20, Expr_::BinaryString(Rc::new(b"caf\xe9".to_vec()))),
+            rnb!(22,28, Expr_::BinaryString(Rc::new(b"caf\xe9".to_vec()))),
+            rnb!(30,34, Expr_::String("ok".into())),
+        ])),
+    ]);
+}
+
+#[test]
+fn parse_byte_order_mark() {
+    // the BOM is output like any other inline HTML
+    let src = "\u{FEFF}<?php echo 1;";
+    assert_eq!(Parser::parse_str(src).unwrap(), vec![
+        rsnb!(0,3, Stmt_::Echo(vec![ rnb!(0,3, Expr_::String("\u{FEFF}".into())) ])),
+        rsnb!(9,16, Stmt_::Echo(vec![ rnb!(14,15, Expr_::Int(1, false)) ])),
+    ]);
+}
+
+#[test]
+fn parse_short_tags() {
+    let src = "a<? echo 1; ?>b";
+    assert_eq!(Parser::parse_str(src).unwrap(), vec![
+        rsnb!(0,1, Stmt_::Echo(vec![ rnb!(0,1, Expr_::String("a".into())) ])),
+        rsnb!(4,11, Stmt_::Echo(vec![ rnb!(9,10, Expr_::Int(1, false)) ])),
+        rsnb!(14,15, Stmt_::Echo(vec![ rnb!(14,15, Expr_::String("b".into())) ])),
+    ]);
+    let options = ParserOptions { short_tags: false, ..ParserOptions::default() };
+    assert_eq!(Parser::parse_str_with_options(src, &options).unwrap(), vec![
+        rsnb!(0,15, Stmt_::Echo(vec![ rnb!(0,15, Expr_::String(src.into())) ])),
+    ]);
+    assert_eq!(Parser::parse_str_with_options("<?xml ?>\n<?PHP ECHO 1;", &options).unwrap(), vec![
+        rsnb!(0,9, Stmt_::Echo(vec![ rnb!(0,9, Expr_::String("<?xml ?>\n".into())) ])),
+        rsnb!(15,22, Stmt_::Echo(vec![ rnb!(20,21, Expr_::Int(1, false)) ])),
+    ]);
+}
+
+#[test]
+fn parse_asp_tags() {
+    let src = "<% echo 1 %>a<%= 2 %>";
+    let options = ParserOptions { asp_tags: true, version: PhpVersion::new(5, 6), ..ParserOptions::default() };
+    assert_eq!(Parser::parse_str_with_options(src, &options).unwrap(), vec![
+        rsnb!(3,12, Stmt_::Echo(vec![ rnb!(8,9, Expr_::Int(1, false)) ])),
+        rsnb!(12,13, Stmt_::Echo(vec![ rnb!(12,13, Expr_::String("a".into())) ])),
+        rsnb!(13,21, Stmt_::Echo(vec![ rnb!(17,18, Expr_::Int(2, false)) ])),
+    ]);
+    let options = ParserOptions { asp_tags: true, version: PhpVersion::new(7, 0), ..ParserOptions::default() };
+    let err = Parser::parse_str_with_options(src, &options).unwrap_err();
+    assert_eq!(err.error_message(Some(src)), "ASP-style tags: removed in PHP 7.0 (targeting PHP 7.0) at line 0\n<% echo 1 %>a<%= 2 %>\n^~");
+    assert_eq!(Parser::parse_str(src).unwrap(), vec![
+        rsnb!(0,21, Stmt_::Echo(vec![ rnb!(0,21, Expr_::String(src.into())) ])),
+    ]);
+}
+
+#[test]
+fn parse_match_duplicate_default() {
+    let src = "<?php\n$a = match ($b) { default => 1, default => 2 };";
+    let err = Parser::parse_str(src).unwrap_err();
+    assert_eq!(err.error_message(Some(src)), "match expressions may only contain one default arm at line 1\n$a = match ($b) { default => 1, default => 2 };\n                                ^~~~~~~");
+}
+
+#[test]
+fn parse_nullsafe_write_context() {
+    let src = "<?php\n$a?->b->c = 1;";
+    let err = Parser::parse_str(src).unwrap_err();
+    assert_eq!(err.error_message(Some(src)), "cannot use the nullsafe operator in write context at line 1\n$a?->b->c = 1;\n  ^~~");
+    assert!(Parser::parse_str("<?php $a?->b()[0]++;").is_err());
+    assert!(Parser::parse_str("<?php unset($a?->b);").is_err());
+    assert!(Parser::parse_str("<?php foreach ($a as $b?->c) {}").is_err());
+    assert!(Parser::parse_str("<?php $x = &$a?->b;").is_err());
+    assert!(Parser::parse_str("<?php [$a?->b] = [1];").is_err());
+    assert!(Parser::parse_str("<?php list($a?->b) = [1];").is_err());
+    assert!(Parser::parse_str("<?php [$x, ['k' => $a?->b]] = $c;").is_err());
+    assert!(Parser::parse_str("<?php $a->b = $c?->d;").is_ok());
+    assert!(Parser::parse_str("<?php [$a, list($b)] = [$c?->d, [1]];").is_ok());
+}
+
+#[test]
+fn parse_nullsafe_without_property() {
+    let src = "<?php\n$a?->;";
+    let err = Parser::parse_str(src).unwrap_err();
+    assert_eq!(err.error_message(Some(src)), "expected one of [String(RcStr(\"\")), Variable(RcStr(\"\")), CurlyBracesOpen] at line 1\n$a?->;\n     ^");
+    assert!(Parser::parse_str("<?php $a?->b?->;").is_err());
+}
+
+#[test]
+fn parse_positional_after_named_argument() {
+    let src = "<?php\nfoo(a: 1, 2);";
+    let err = Parser::parse_str(src).unwrap_err();
+    assert_eq!(err.error_message(Some(src)), "cannot use a positional argument after a named argument at line 1\nfoo(a: 1, 2);\n          ^");
+    let src = "<?php\nfoo(...$a, 1);";
+    let err = Parser::parse_str(src).unwrap_err();
+    assert_eq!(err.error_message(Some(src)), "cannot use a positional argument after argument unpacking at line 1\nfoo(...$a, 1);\n           ^");
+    // unpacking after named arguments is only rejected by PHP when compiling
+    assert!(Parser::parse_str("<?php foo(a: 1, ...$rest);").is_ok());
+    assert!(Parser::parse_str("<?php foo(...$a, ...$b, c: 1);").is_ok());
+}
+
+#[test]
+fn parse_attributes_misplaced() {
+    assert!(Parser::parse_str("<?php class A { #[B] use C; }").is_err());
+    assert!(Parser::parse_str("<?php #[A] echo 1;").is_err());
+}
+
+#[test]
+fn parse_invalid_composite_types() {
+    let src = "<?php\nfunction a(?int|string $b) {}";
+    let err = Parser::parse_str(src).unwrap_err();
+    assert_eq!(err.error_message(Some(src)), "a nullable type cannot be part of a union or intersection type at line 1\nfunction a(?int|string $b) {}\n               ^");
+    let src = "<?php\nfunction a(): int|A|INT {}";
+    let err = Parser::parse_str(src).unwrap_err();
+    assert_eq!(err.error_message(Some(src)), "a type may only occur once in a union or intersection type at line 1\nfunction a(): int|A|INT {}\n                    ^~~");
+    assert!(Parser::parse_str("<?php function a(A&B|null $c) {}").is_err());
+    assert!(Parser::parse_str("<?php function a(int&string $c) {}").is_err());
+    assert!(Parser::parse_str("<?php function a((A&B) $c) {}").is_err());
+    assert!(Parser::parse_str("<?php function a(): void|int {}").is_err());
+}
+
+#[test]
+fn parse_invalid_constructor_promotion() {
+    let src = "<?php\nfunction a(public $b) {}";
+    let err = Parser::parse_str(src).unwrap_err();
+    assert_eq!(err.error_message(Some(src)), "properties may only be promoted in a constructor at line 1\nfunction a(public $b) {}\n           ^~~~~~");
+    assert!(Parser::parse_str("<?php class A { function b(public $c) {} }").is_err());
+    assert!(Parser::parse_str("<?php class A { function __construct(public ...$c) {} }").is_err());
+    assert!(Parser::parse_str("<?php class A { function __construct(static $c) {} }").is_err());
+    assert!(Parser::parse_str("<?php class A { function __CONSTRUCT(public $c) {} }").is_ok());
+}
+
+#[test]
+fn parse_invalid_heredoc_indentation() {
+    let src = "<?php\n$a = <<<EOT\n  b\n c\n  EOT;";
+    let err = Parser::parse_str(src).unwrap_err();
+    assert_eq!(err.error_message(None), "Invalid body indentation level (expecting an indentation level of at least 2) at line 3\n");
+    assert!(Parser::parse_str("<?php $a = <<<'EOT'\n\t b\n \tEOT;").is_err());
+    assert!(Parser::parse_str("<?php $a = <<<EOT\n  b\n\n  EOT;").is_ok());
+}
+
+#[test]
+fn parse_invalid_readonly() {
+    let src = "<?php\nclass A { public readonly $b; }";
+    let err = Parser::parse_str(src).unwrap_err();
+    assert_eq!(err.error_message(Some(src)), "readonly properties must have a type at line 1\nclass A { public readonly $b; }\n          ^~~~~~");
+    assert!(Parser::parse_str("<?php class A { readonly function b() {} }").is_err());
+    assert!(Parser::parse_str("<?php class A { function __construct(readonly $b) {} }").is_err());
+    assert!(Parser::parse_str("<?php class A { function __construct(public readonly int $b) {} }").is_ok());
+    // `readonly` remains usable as a function name
+    assert!(Parser::parse_str("<?php readonly($a);").is_ok());
+}
+
+#[test]
+fn parse_invalid_first_class_callable() {
+    assert!(Parser::parse_str("<?php new A(...);").is_err());
+    assert!(Parser::parse_str("<?php $a?->b(...);").is_err());
+    assert!(Parser::parse_str("<?php function a(): never|int {}").is_err());
+}
+
+#[test]
+fn parse_invalid_return_only_types() {
+    let src = "<?php\nfunction a(never $b) {}";
+    let err = Parser::parse_str(src).unwrap_err();
+    assert_eq!(err.error_message(Some(src)), "never can only be used as a return type at line 1\nfunction a(never $b) {}\n           ^~~~~");
+    assert!(Parser::parse_str("<?php function a(): ?never {}").is_err());
+    assert!(Parser::parse_str("<?php function a(): ?void {}").is_err());
+    assert!(Parser::parse_str("<?php function a(void $b) {}").is_err());
+    assert!(Parser::parse_str("<?php class A { public never $b; }").is_err());
+}
+
+#[test]
+fn parse_invalid_new_in_initializer() {
+    let src = "<?php\nclass A { public $b = new C; }";
+    let err = Parser::parse_str(src).unwrap_err();
+    assert_eq!(err.error_message(Some(src)), "new is not allowed in a property default value at line 1\nclass A { public $b = new C; }\n                      ^~~");
+    assert!(Parser::parse_str("<?php class A { const B = [new C]; }").is_err());
+    assert!(Parser::parse_str("<?php function a() { static $b = new C; }").is_ok());
+    assert!(Parser::parse_str("<?php #[A(new B)] function c() {}").is_ok());
+}
+
+#[test]
+fn parse_invalid_class_constant_name() {
+    let src = "<?php\nclass A { const B = 1, CLASS = 2; }";
+    let err = Parser::parse_str(src).unwrap_err();
+    assert_eq!(err.error_message(Some(src)), "class is reserved for fetching the class name at line 1\nclass A { const B = 1, CLASS = 2; }\n                       ^~~~~");
+    assert!(Parser::parse_str("<?php enum A { case class; }").is_err());
+    // keywords can't name a global function
+    assert!(Parser::parse_str("<?php function list() {}").is_err());
+}
+
+#[test]
+fn parse_invalid_trait_alias() {
+    let src = "<?php\nclass A { use B { c as public static d; } }";
+    let err = Parser::parse_str(src).unwrap_err();
+    assert_eq!(err.error_message(Some(src)),
+        "only a single visibility modifier can be used in a trait alias at line 1\nclass A { use B { c as public static d; } }\n                       ^~~~~~");
+    assert!(Parser::parse_str("<?php class A { use B { c as; } }").is_err());
+    // `final` is only allowed since PHP 8.3
+    assert!(Parser::parse_str("<?php class A { use B { c as final; } }").is_err());
+}
+
+#[test]
+fn parse_invalid_enum() {
+    let src = "<?php\nenum A { case B = 1; }";
+    let err = Parser::parse_str(src).unwrap_err();
+    assert_eq!(err.error_message(Some(src)), "a case of a pure enum cannot have a value at line 1\nenum A { case B = 1; }\n         ^~~~");
+    assert!(Parser::parse_str("<?php enum A: int { case B; }").is_err());
+    assert!(Parser::parse_str("<?php enum A: float { case B = 1.0; }").is_err());
+    assert!(Parser::parse_str("<?php enum A { public $b; }").is_err());
+    assert!(Parser::parse_str("<?php enum A { public case B; }").is_err());
+    assert!(Parser::parse_str("<?php class A { case B; }").is_err());
+    // `enum` remains usable as a name
+    assert!(Parser::parse_str("<?php enum($a); class Enum {}").is_ok());
+}
+
+#[test]
+fn parse_version_gated_syntax() {
+    fn check(src: &str, major: u32, minor: u32) -> Result<Vec<Stmt>, SpannedParserError> {
+        let options = ParserOptions { version: PhpVersion::new(major, minor), ..ParserOptions::default() };
+        Parser::parse_str_with_options(src, &options)
+    }
+    let src = "<?php\nfunction a(?int $b): void {}";
+    assert!(check(src, 7, 1).is_ok());
+    let err = check(src, 7, 0).unwrap_err();
+    assert_eq!(err.feature(), Some(Feature::NullableTypes));
+    assert_eq!(err.error_message(Some(src)), "nullable types: requires PHP 7.1 (targeting PHP 7.0) at line 1\nfunction a(?int $b): void {}\n           ^");
+    let err = check("<?php function a(): void {}", 7, 0).unwrap_err();
+    assert_eq!(err.feature(), Some(Feature::VoidType));
+    assert_eq!(check("<?php function a(int $b) {}", 5, 6).unwrap_err().feature(), Some(Feature::ScalarTypes));
+    assert_eq!(check("<?php function a(): Foo {}", 5, 6).unwrap_err().feature(), Some(Feature::ReturnTypes));
+    assert_eq!(check("<?php function a(iterable $b) {}", 7, 0).unwrap_err().feature(), Some(Feature::IterableType));
+    assert_eq!(check("<?php function a(object $b) {}", 7, 1).unwrap_err().feature(), Some(Feature::ObjectType));
+    assert_eq!(check("<?php $a ?? $b;", 5, 6).unwrap_err().feature(), Some(Feature::NullCoalesce));
+    assert_eq!(check("<?php $a <=> $b;", 5, 6).unwrap_err().feature(), Some(Feature::Spaceship));
+    // before PHP 7.4 `fn` is an identifier
+    assert!(check("<?php $a = fn() => 1;", 7, 3).is_err());
+    assert!(check("<?php function fn() {} fn();", 7, 3).is_ok());
+    assert_eq!(check("<?php class A { public int $a; }", 7, 3).unwrap_err().feature(), Some(Feature::TypedProperties));
+    assert_eq!(check("<?php [...$a];", 7, 3).unwrap_err().feature(), Some(Feature::ArraySpread));
+    let src = "<?php\n$a = 1_000;";
+    assert_eq!(check(src, 7, 3).unwrap_err().error_message(Some(src)),
+        "numeric literal separators: requires PHP 7.4 (targeting PHP 7.3) at line 1\n$a = 1_000;\n     ^~~~~");
+    // before PHP 8 `match` is an identifier
+    assert!(check("<?php match ($a) {};", 7, 4).is_err());
+    assert!(check("<?php function match() {} match($a);", 7, 4).is_ok());
+    assert_eq!(check("<?php $a?->b();", 7, 4).unwrap_err().feature(), Some(Feature::NullsafeOperator));
+    assert_eq!(check("<?php $c = $a?->b;", 7, 4).unwrap_err().feature(), Some(Feature::NullsafeOperator));
+    assert_eq!(check("<?php foo(a: 1);", 7, 4).unwrap_err().feature(), Some(Feature::NamedArguments));
+    assert_eq!(check("<?php foo(&$a);", 5, 6).unwrap_err().feature(), Some(Feature::CallTimePassByRef));
+    assert_eq!(check("<?php foo(&$a);", 5, 3).unwrap(), vec![
+        rsnb!(6,15, Stmt_::Expr(rnb!(6,14, Expr_::Call(Box::new(rnb!(6,9, Expr_::Path(Path::identifier(false, "foo".into())))), vec![
+            Argument { name: None, unpack: false, by_ref: true, value: rnb!(11,13, Expr_::Variable("a".into())) },
+        ])))),
+    ]);
+    assert_eq!(check("<?php function a(int|string $b) {}", 7, 4).unwrap_err().feature(), Some(Feature::UnionTypes));
+    assert_eq!(check("<?php function a(A&B $b) {}", 8, 0).unwrap_err().feature(), Some(Feature::IntersectionTypes));
+    assert_eq!(check("<?php function a((A&B)|null $b) {}", 8, 1).unwrap_err().feature(), Some(Feature::DnfTypes));
+    assert!(check("<?php function a(A &$b) {}", 5, 6).is_ok());
+    assert_eq!(check("<?php class A { function __construct(public $b) {} }", 7, 4).unwrap_err().feature(), Some(Feature::ConstructorPromotion));
+    assert_eq!(check("<?php enum A {}", 8, 0).unwrap_err().feature(), Some(Feature::Enums));
+    assert_eq!(check("<?php function a() { yield from b(); }", 5, 6).unwrap_err().feature(), Some(Feature::YieldFrom));
+    assert_eq!(check("<?php $a = <<<EOT\n  b\n  EOT;\n", 7, 2).unwrap_err().feature(), Some(Feature::FlexibleHeredoc));
+    assert_eq!(check("<?php $a = <<<'EOT'\n\tb\n\tEOT;\n", 7, 2).unwrap_err().feature(), Some(Feature::FlexibleHeredoc));
+    assert_eq!(check("<?php a(<<<EOT\nb\nEOT, 1);", 7, 2).unwrap_err().feature(), Some(Feature::FlexibleHeredoc));
+    assert!(check("<?php $a = <<<EOT\nb\nEOT;\n", 7, 2).is_ok());
+    assert_eq!(check("<?php class A { public readonly int $b; }", 8, 0).unwrap_err().feature(), Some(Feature::ReadonlyProperties));
+    assert_eq!(check("<?php readonly class A {}", 8, 1).unwrap_err().feature(), Some(Feature::ReadonlyClasses));
+    assert_eq!(check("<?php function a(): never {}", 8, 0).unwrap_err().feature(), Some(Feature::NeverType));
+    assert_eq!(check("<?php $a = strlen(...);", 8, 0).unwrap_err().feature(), Some(Feature::FirstClassCallables));
+    assert_eq!(check("<?php function a($b = new C) {}", 8, 0).unwrap_err().feature(), Some(Feature::NewInInitializers));
+    assert!(check("<?php readonly($a);", 8, 0).is_ok());
+    // before PHP 8.1 `readonly` is a class name where a type is expected
+    assert!(check("<?php function a(Readonly $b) {} class C { public Readonly $d; }", 7, 4).is_ok());
+    assert!(check("<?php class C { public Readonly $d; }", 8, 1).is_err());
+    assert_eq!(check("<?php $a::class;", 7, 4).unwrap_err().feature(), Some(Feature::ClassNameOnObjects));
+    assert!(check("<?php $a::B; static::class;", 7, 4).is_ok());
+    // before PHP 8 `#[` starts a comment
+    for &(major, count) in &[(7, 0), (8, 1)] {
+        match check("<?php #[A]\nfunction b() {}", major, 0).unwrap()[0].0 {
+            Stmt_::Decl(Decl::GlobalFunction(_, ref decl)) => assert_eq!(decl.attributes.len(), count),
+            ref x => panic!("expected function, got {:?}", x),
+        }
+    }
+    assert_eq!(check("<?php \"$a?->b\";", 7, 4).unwrap_err().feature(), Some(Feature::NullsafeOperator));
+
+    let src = "<?php try {} catch (A | B $e) {}";
+    assert!(check(src, 7, 1).is_ok());
+    assert_eq!(check(src, 7, 0).unwrap_err().error_message(Some(src)),
+        "catching multiple exception types: requires PHP 7.1 (targeting PHP 7.0) at line 0\n<?php try {} catch (A | B $e) {}\n                      ^");
+
+    let src = "<?php (unset) $a;";
+    assert_eq!(check(src, 7, 4).unwrap(), vec![
+        rsnb!(6,17, Stmt_::Expr(rnb!(6,16, Expr_::Cast(Ty::Null, Box::new(rnb!(14,16, Expr_::Variable("a".into()))))))),
+    ]);
+    assert_eq!(check(src, 8, 0).unwrap_err().error_message(None), "the (unset) cast: removed in PHP 8.0 (targeting PHP 8.0) at line 0\n");
+}
@@ -0,0 +1,84 @@
+use parser::*;
+use min_version::{FeatureUse, min_version};
+
+fn process_min_version(input: &str) -> (PhpVersion, Vec<(Feature, u32, u32)>) {
+    let stmts = Parser::parse_str(&("<?php ".to_owned() + input)).unwrap();
+    let result = min_version(&stmts);
+    (result.version, result.required_by.into_iter().map(|FeatureUse { feature, span }| (feature, span.start - 6, span.end - 6)).collect())
+}
+
+#[test]
+fn min_version_oldest() {
+    assert_eq!(process_min_version("echo $a ** 2;"), (PhpVersion::OLDEST, vec![]));
+    assert_eq!(process_min_version("function a(array $b, Foo $c) {}"), (PhpVersion::OLDEST, vec![]));
+}
+
+#[test]
+fn min_version_php7() {
+    assert_eq!(process_min_version("echo $a ?? 1; $b = $a <=> 2;"), (PhpVersion::new(7, 0), vec![
+        (Feature::NullCoalesce, 5, 12),
+        (Feature::Spaceship, 19, 27),
+    ]));
+    assert_eq!(process_min_version("function a(int $b, string $c): Foo {}"), (PhpVersion::new(7, 0), vec![
+        (Feature::ReturnTypes, 0, 37),
+        (Feature::ScalarTypes, 0, 37),
+    ]));
+    assert_eq!(process_min_version("$f = function () use ($a): array {};"), (PhpVersion::new(7, 0), vec![
+        (Feature::ReturnTypes, 5, 35),
+    ]));
+    assert_eq!(process_min_version("function a() { yield from b(); }"), (PhpVersion::new(7, 0), vec![(Feature::YieldFrom, 15, 29)]));
+}
+
+#[test]
+fn min_version_php71() {
+    assert_eq!(process_min_version("class A { public function b(?int $c): void { try {} catch (B | C $e) {} } }"), (PhpVersion::new(7, 1), vec![
+        (Feature::NullableTypes, 0, 75),
+        (Feature::VoidType, 0, 75),
+        (Feature::MultiCatch, 45, 71),
+    ]));
+    assert_eq!(process_min_version("function a(iterable $b) { return $b ?? []; }").0, PhpVersion::new(7, 1));
+}
+
+#[test]
+fn min_version_php73() {
+    assert_eq!(process_min_version("$a = <<<EOT\n  $b\n  EOT;"), (PhpVersion::new(7, 3), vec![(Feature::FlexibleHeredoc, 5, 22)]));
+    assert_eq!(process_min_version("$a = <<<'EOT'\n    a\n    EOT;"), (PhpVersion::new(7, 3), vec![(Feature::FlexibleHeredoc, 5, 27)]));
+    assert_eq!(process_min_version("$a = [<<<EOT\na\nEOT, 1];"), (PhpVersion::new(7, 3), vec![(Feature::FlexibleHeredoc, 6, 18)]));
+    assert_eq!(process_min_version("$a = <<<'EOT'\na\nEOT;\n"), (PhpVersion::new(5, 6), vec![]));
+}
+
+#[test]
+fn min_version_php74() {
+    assert_eq!(process_min_version("class A { public int $b; public function c() { return fn() => $this->b; } }"), (PhpVersion::new(7, 4), vec![
+        (Feature::TypedProperties, 0, 75),
+        (Feature::ArrowFunctions, 54, 70),
+    ]));
+    assert_eq!(process_min_version("$a = [1, ...$b];"), (PhpVersion::new(7, 4), vec![(Feature::ArraySpread, 5, 15)]));
+    assert_eq!(process_min_version("$a = 1_000 + 1.5 + 0x1_F;"), (PhpVersion::new(7, 4), vec![
+        (Feature::NumericSeparators, 5, 10),
+        (Feature::NumericSeparators, 19, 24),
+    ]));
+}
+
+#[test]
+fn min_version_php82() {
+    assert_eq!(process_min_version("function a(int|string $b): A&B {}"), (PhpVersion::new(8, 1), vec![(Feature::IntersectionTypes, 0, 33)]));
+    assert_eq!(process_min_version("enum A: string { case B = 'b'; }"), (PhpVersion::new(8, 1), vec![(Feature::Enums, 0, 32)]));
+    assert_eq!(process_min_version("$a = strlen(...);"), (PhpVersion::new(8, 1), vec![(Feature::FirstClassCallables, 5, 16)]));
+    assert_eq!(process_min_version("readonly class A {}"), (PhpVersion::new(8, 2), vec![(Feature::ReadonlyClasses, 0, 19)]));
+    assert_eq!(process_min_version("class A { public (B&C)|null $d; }"), (PhpVersion::new(8, 2), vec![(Feature::DnfTypes, 0, 33)]));
+}
+
+#[test]
+fn min_version_php80() {
+    assert_eq!(process_min_version("echo match ($a ?? 1) { 1 => fn() => 2 };"), (PhpVersion::new(8, 0), vec![(Feature::Match, 5, 39)]));
+    assert_eq!(process_min_version("$a = $b?->c();"), (PhpVersion::new(8, 0), vec![(Feature::NullsafeOperator, 5, 11)]));
+    assert_eq!(process_min_version("new A(...$b, c: 1);"), (PhpVersion::new(8, 0), vec![(Feature::NamedArguments, 0, 18)]));
+    assert_eq!(process_min_version("echo $a::class, A::class;"), (PhpVersion::new(8, 0), vec![(Feature::ClassNameOnObjects, 5, 14)]));
+    assert_eq!(process_min_version("class A { function __construct(private $b) {} }"), (PhpVersion::new(8, 0), vec![
+        (Feature::ConstructorPromotion, 0, 47),
+    ]));
+    assert_eq!(process_min_version("class A { #[B] public $c; public function d(#[E] $f) {} }"), (PhpVersion::new(8, 0), vec![
+        (Feature::Attributes, 0, 57),
+    ]));
+}
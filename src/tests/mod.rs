@@ -37,4 +37,7 @@ macro_rules! constant {
 
 mod expr;
 mod file;
+mod min_version;
+mod printer;
 mod stmt;
+mod token_dump;
@@ -0,0 +1,208 @@
+use std::borrow::Borrow;
+use std::rc::Rc;
+use parser::*;
+use printer::PrettyPrinter;
+
+fn print_script(input: &str) -> String {
+    let str_ = "<?php ".to_owned() + input;
+    let stmts = Parser::parse_str(&str_).unwrap();
+    let mut output = String::new();
+    PrettyPrinter::print_statements(&mut output, stmts).unwrap();
+    output
+}
+
+#[test]
+fn print_variable_variable() {
+    assert_eq!(print_script("$$a;"), "$$a;\n");
+    assert_eq!(print_script("$$$a;"), "$$$a;\n");
+    assert_eq!(print_script("${'a'};"), "${'a'};\n");
+    assert_eq!(print_script("${$a . 'b'};"), "${$a.'b'};\n");
+    assert_eq!(print_script("global $a, $$b;"), "global $a, $$b;\n");
+}
+
+#[test]
+fn print_array_spread() {
+    assert_eq!(print_script("[...$a, 'b' => 1_000];"), "[...$a, 'b' => 1000];\n");
+}
+
+#[test]
+fn print_match() {
+    assert_eq!(print_script("$a = match ($b) { 1, 2 => 'c', default => $d };"), "$a=match ($b) {\n    1, 2 => 'c',\n    default => $d,\n};\n");
+}
+
+#[test]
+fn print_nullsafe() {
+    assert_eq!(print_script("$a?->b->c()?->d;"), "$a?->b->c()?->d;\n");
+    assert_eq!(print_script("$a?->b()[0]->c;"), "$a?->b()[0]->c;\n");
+    assert_eq!(print_script("($a?->b)->c;"), "($a?->b)->c;\n");
+}
+
+#[test]
+fn print_named_arguments() {
+    assert_eq!(print_script("foo(1, limit: 10, ...$rest);"), "foo(1, limit: 10, ...$rest);\n");
+    assert_eq!(print_script("foo(1, ...$rest, limit: 10);"), "foo(1, ...$rest, limit: 10);\n");
+}
+
+#[test]
+fn print_attributes() {
+    assert_eq!(print_script("#[A] #[B(1, c: 2)] function d(#[E] $f) {}"), "#[A, B(1, c: 2)]\nfunction d(#[E] $f) {\n}\n");
+    assert_eq!(print_script("class A { #[B] const C=1; #[D] public $e; }"), "class A  {\n    #[B]\n     const C=1;\n    #[D]\n    public $e;\n}");
+    assert_eq!(print_script("$a = #[B] fn() => 1;"), "$a=#[B] fn() => 1;\n");
+    assert_eq!(print_script("$a = #[B] static fn() => 1;"), "$a=#[B] static fn() => 1;\n");
+    assert_eq!(print_script("#[A] interface B {}"), "#[A]\ninterface B {\n}");
+    assert_eq!(print_script("#[A] trait B {}"), "#[A]\ntrait B {\n}");
+}
+
+#[test]
+fn print_desugared_promoted_params() {
+    let mut stmts = Parser::parse_str("<?php class A { public function __construct(#[B] protected ?C $d, $e = 1) { run(); } }").unwrap();
+    match stmts[0].0 {
+        Stmt_::Decl(Decl::Class(ref mut decl)) => decl.desugar_promoted_params(),
+        _ => unreachable!(),
+    }
+    let mut output = String::new();
+    PrettyPrinter::print_statements(&mut output, stmts).unwrap();
+    assert_eq!(output, "class A  {\n    #[B]\n    protected ?C $d;\n    public  function __construct(#[B] ?C $d,$e=1) {\n        $this->d=$d;\n        run();\n    }\n\n}");
+}
+
+#[test]
+fn print_desugared_promoted_params_by_ref_in_trait() {
+    let mut stmts = Parser::parse_str("<?php trait A { function __construct(private &$b) {} }").unwrap();
+    match stmts[0].0 {
+        Stmt_::Decl(Decl::Trait(_, _, ref mut members)) => ::ast::desugar_promoted_params(members),
+        _ => unreachable!(),
+    }
+    let mut output = String::new();
+    PrettyPrinter::print_statements(&mut output, stmts).unwrap();
+    assert_eq!(output, "trait A {\n    private $b;\n     function __construct(&$b) {\n        $this->b=&$b;\n    }\n\n}");
+}
+
+#[test]
+fn print_enum() {
+    assert_eq!(print_script("enum A: int implements B, C { case D = 1; #[E] case F = 2; }"),
+        "enum A: int implements B, C {\n    case D=1;\n    #[E]\n    case F=2;\n}");
+    assert_eq!(print_script("enum A { case B; }"), "enum A {\n    case B;\n}");
+}
+
+#[test]
+fn print_php81_syntax() {
+    assert_eq!(print_script("readonly class A { public readonly int $b; }"), "readonly class A  {\n    public readonly int $b;\n}");
+    assert_eq!(print_script("function a($b = new C): never { $d = strlen(...); }"), "function a($b=new C()): never {\n    $d=strlen(...);\n}\n");
+}
+
+#[test]
+fn print_yield() {
+    assert_eq!(print_script("function a() { $b = yield $c => $d; yield; yield from e(); $f = (yield $g) + 1; }"),
+        "function a() {\n    $b=yield $c => $d;\n    yield;\n    yield from e();\n    $f=(yield $g)+1;\n}\n");
+}
+
+#[test]
+fn print_semi_reserved_names() {
+    assert_eq!(print_script("echo $a::class, $b->c::D, E::LIST, F::new();"), "echo $a::class, ($b->c)::D, E::LIST, F::new();\n");
+}
+
+#[test]
+fn print_trait_use() {
+    assert_eq!(print_script("class A { use B, C { B::d insteadof C; e as protected; C::f as private g; h as i; } }"),
+        "class A  {\n    use B, C {\n        B::d insteadof C;\n        e as protected;\n        C::f as private g;\n        h as i;\n    }\n\n}");
+}
+
+#[test]
+fn print_coalesce() {
+    assert_eq!(print_script("$a ?? $b ?? $c;"), "$a??($b??$c);\n");
+    assert_eq!(print_script("($a ?? $b) ?? $c;"), "($a??$b)??$c;\n");
+}
+
+#[test]
+fn print_types() {
+    assert_eq!(print_script("function a(?iterable $b): void {}"), "function a(?iterable $b): void {\n}\n");
+    assert_eq!(print_script("$a = function () use ($b): int {};"), "$a=function () use ($b): int {\n}\n;\n");
+    assert_eq!(print_script("$a = fn&(int $b): ?int => $b * $c;"), "$a=fn&(int $b): ?int => $b*$c;\n");
+    assert_eq!(print_script("class A { public static ?Foo $a = null; }"), "class A  {\n    public static ?Foo $a=null;\n}");
+    assert_eq!(print_script("function a(int|string $b, A&B &$c, (A&B)|null $d): ?int {}"),
+        "function a(int|string $b,A&B &$c,(A&B)|null $d): ?int {\n}\n");
+    assert_eq!(print_script("class A { function __construct(public int $b) {} }"), "class A  {\n     function __construct(public int $b) {\n    }\n\n}");
+    assert_eq!(print_script("try {} catch (A | B $e) {}"), "try {\n}\ncatch (A | B $e) {\n}\n");
+}
+
+#[test]
+fn print_interpolated() {
+    assert_eq!(print_script(r#""Hello $name!";"#), "\"Hello {$name}!\";\n");
+    assert_eq!(print_script(r#""a\\b \$c \"d\" $e[0] {$f->g}";"#), "\"a\\\\b \\$c \\\"d\\\" {$e[0]} {$f->g}\";\n");
+    assert_eq!(print_script("`ls $dir`;"), "`ls {$dir}`;\n");
+    assert_eq!(print_script(r#""caf\xe9\t$a";"#), "\"caf\\xE9\\t{$a}\";\n");
+    assert_eq!(print_script("echo <<<EOT\na \"$b\"\nEOT;\n"), "echo <<<EOT\na \"{$b}\"\nEOT;\n");
+    assert_eq!(print_script("echo <<<EOT\n    a\n      {$b}\n    EOT;\n"), "echo <<<EOT\n    a\n      {$b}\n    EOT;\n");
+    assert_eq!(print_script("echo <<<'EOT'\n  a\\n $b\n  EOT;\n"), "echo <<<'EOT'\n  a\\n $b\n  EOT;\n");
+    assert_eq!(print_script("echo <<<EOT\nb\nEOT;\n"), "echo <<<EOT\nb\nEOT;\n");
+    // the end-label is only followed by code on the same line if it was in the source
+    assert_eq!(print_script("foo(<<<EOT\nb\nEOT\n, 1);"), "foo(<<<EOT\nb\nEOT\n, 1);\n");
+    assert_eq!(print_script("foo(<<<EOT\nb\nEOT, 1);"), "foo(<<<EOT\nb\nEOT, 1);\n");
+    // constant strings stay simple strings
+    assert_eq!(print_script(r#""Hello";"#), "'Hello';\n");
+}
+
+#[test]
+fn print_interpolated_roundtrip() {
+    // `{(` isn't the start of an interpolation, so chains are printed without parentheses
+    for &src in &[r#""{$a->b["c"]}";"#, r#""{$a[0]->b}";"#, r#""$a[0] {$a?->b[1]->c()} {$a::$b[2]} ${a}";"#] {
+        let printed = print_script(src);
+        assert!(!printed.contains("{("), "{}", printed);
+        assert_eq!(print_script(&printed), printed);
+    }
+    assert_eq!(print_script(r#""{$a->b["c"]}";"#), "\"{$a->b['c']}\";\n");
+    assert_eq!(print_script(r#""$a[0] {$a?->b[1]->c()} {$a::$b[2]} ${a}";"#), "\"{$a[0]} {$a?->b[1]->c()} {$a::$b[2]} {$a}\";\n");
+    // other expressions are concatenated
+    for &(src, printed, reprinted) in &[
+        (r#""a {$a = 1}";"#, "('a '.($a=1));\n", "'a '.($a=1);\n"),
+        (r#""{$a + 1}b";"#, "(''.($a+1).'b');\n", "(''.($a+1)).'b';\n"),
+        ("`ls {$a + 1}`;", "shell_exec('ls '.($a+1));\n", "shell_exec('ls '.($a+1));\n"),
+    ] {
+        assert_eq!(print_script(src), printed);
+        assert_eq!(print_script(printed), reprinted);
+    }
+}
+
+/// prints a string literal containing the given bytes and parses it again
+fn roundtrip_string(bytes: &[u8]) -> (String, Vec<u8>) {
+    let expr = match String::from_utf8(bytes.to_vec()) {
+        Ok(str_) => Expr_::String(str_.into()),
+        Err(err) => Expr_::BinaryString(Rc::new(err.into_bytes())),
+    };
+    let mut output = String::new();
+    PrettyPrinter::new(&mut output).print_expression(&Expr(expr, Span::new())).unwrap();
+    let stmts = Parser::parse_str(&format!("<?php {};", output)).unwrap();
+    let parsed = match stmts[0].0 {
+        Stmt_::Expr(Expr(Expr_::String(ref str_), _)) => (str_.borrow() as &str).as_bytes().to_vec(),
+        Stmt_::Expr(Expr(Expr_::BinaryString(ref str_), _)) => (**str_).clone(),
+        ref stmt => panic!("{} parsed to {:?}", output, stmt),
+    };
+    (output, parsed)
+}
+
+#[test]
+fn print_string_quotes() {
+    assert_eq!(roundtrip_string(b"it's").0, r#"'it\'s'"#);
+    assert_eq!(roundtrip_string(br"App\Models").0, r#"'App\Models'"#);
+    assert_eq!(roundtrip_string(br"a\\b\").0, r#"'a\\\b\\'"#);
+    assert_eq!(roundtrip_string("转注字 $a".as_bytes()).0, "'转注字 $a'");
+    assert_eq!(roundtrip_string(b"a\n\"$b\"").0, r#""a\n\"\$b\"""#);
+    assert_eq!(roundtrip_string(b"caf\xe9\x00\x7f1").0, r#""caf\xE9\x00\x7F1""#);
+}
+
+#[test]
+fn print_string_all_bytes() {
+    let all_bytes: Vec<u8> = (0..256).map(|x| x as u8).collect();
+    assert_eq!(roundtrip_string(&all_bytes).1, all_bytes);
+    for byte in 0..256 {
+        let byte = byte as u8;
+        // surround each byte with characters which might combine with an escape sequence
+        for &(prefix, suffix) in &[(&b""[..], &b""[..]), (b"\\", b"0"), (b"$", b"F"), (b"{", b"7'")] {
+            let mut bytes = prefix.to_vec();
+            bytes.push(byte);
+            bytes.extend(suffix);
+            let (output, parsed) = roundtrip_string(&bytes);
+            assert_eq!(parsed, bytes, "{:?} printed as {}", bytes, output);
+        }
+    }
+}
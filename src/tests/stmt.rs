@@ -9,12 +9,14 @@ fn process_stmt(input: &str) -> Stmt {
 
 #[test]
 fn parse_stmt_heredoc() {
-    assert_eq!(process_stmt("<<<EOT\ntest\nEOT;\n"), senb!(0,15, Expr_::String("test".into())));
+    assert_eq!(process_stmt("<<<EOT\ntest\nEOT;\n"), senb!(0,15, Expr_::Interpolated(StringKind::HereDoc("EOT".into(), "".into(), false), vec![
+        InterpolatedPart::Literal("test".into()),
+    ])));
 }
 
 #[test]
 fn parse_stmt_echo() {
-    assert_eq!(process_stmt("echo 1;"), st!(0,7, Stmt_::Echo(vec![ enb!(5,6, Expr_::Int(1)) ])));
+    assert_eq!(process_stmt("echo 1;"), st!(0,7, Stmt_::Echo(vec![ enb!(5,6, Expr_::Int(1, false)) ])));
 }
 
 #[test]
@@ -26,13 +28,13 @@ fn parse_stmt_return() {
 #[test]
 fn parse_stmt_continue() {
     assert_eq!(process_stmt("continue;"), st!(0,9, Stmt_::Continue(None)));
-    assert_eq!(process_stmt("continue 2;"), st!(0,11, Stmt_::Continue(Some(eb!(9,10, Expr_::Int(2))))));
+    assert_eq!(process_stmt("continue 2;"), st!(0,11, Stmt_::Continue(Some(eb!(9,10, Expr_::Int(2, false))))));
 }
 
 #[test]
 fn parse_stmt_break() {
     assert_eq!(process_stmt("break;"), st!(0,6, Stmt_::Break(None)));
-    assert_eq!(process_stmt("break 2;"), st!(0,8, Stmt_::Break(Some(eb!(6,7, Expr_::Int(2))))));
+    assert_eq!(process_stmt("break 2;"), st!(0,8, Stmt_::Break(Some(eb!(6,7, Expr_::Int(2, false))))));
 }
 
 #[test]
@@ -50,7 +52,7 @@ fn parse_stmt_new() {
 #[test]
 fn parse_stmt_throw() {
     assert_eq!(process_stmt(r#"throw new Exception("test");"#), st!(0,28, Stmt_::Throw(eb!(6,27, Expr_::New(eb!(10,19, Expr_::Path(Path::identifier(false, "Exception".into()))),
-        vec![ enb!(20,26, Expr_::String("test".into())) ])))
+        vec![ enb!(20,26, Expr_::String("test".into())).into() ])))
     ));
     assert_eq!(process_stmt(r#"throw new Exception;"#), st!(0,20, Stmt_::Throw(eb!(6,19, Expr_::New(eb!(10,19, Expr_::Path(Path::identifier(false, "Exception".into()))), vec![])))));
 }
@@ -72,13 +74,13 @@ fn parse_stmt_exit() {
 
 #[test]
 fn parse_stmt_assignment() {
-    assert_eq!(process_stmt(r#"$test=4;"#), senb!(0,7, Expr_::Assign(eb!(0,5, Expr_::Variable("test".into())), eb!(6,7, Expr_::Int(4)))));
+    assert_eq!(process_stmt(r#"$test=4;"#), senb!(0,7, Expr_::Assign(eb!(0,5, Expr_::Variable("test".into())), eb!(6,7, Expr_::Int(4, false)))));
     assert_eq!(process_stmt(r#"$test["a"]=4+$b;"#), senb!(0,15, Expr_::Assign(eb!(0,10,
         Expr_::ArrayIdx(
             eb!(0,5, Expr_::Variable("test".into())),
             vec![ Some(enb!(6,9, Expr_::String("a".into()))) ]
         )),
-        eb!(11,15, Expr_::BinaryOp(Op::Add, eb!(11, 12, Expr_::Int(4)), eb!(13,15, Expr_::Variable("b".into()))))
+        eb!(11,15, Expr_::BinaryOp(Op::Add, eb!(11, 12, Expr_::Int(4, false)), eb!(13,15, Expr_::Variable("b".into()))))
     )));
 }
 
@@ -92,8 +94,8 @@ fn parse_stmt_return_ternary_assign() {
 
 #[test]
 fn parse_stmt_compound_assignment() {
-    assert_eq!(process_stmt("$test+=4;"), senb!(0,8, Expr_::CompoundAssign(eb!(0,5, Expr_::Variable("test".into())), Op::Add, eb!(7,8, Expr_::Int(4)))));
-    assert_eq!(process_stmt("$test|=0;"), senb!(0,8, Expr_::CompoundAssign(eb!(0,5, Expr_::Variable("test".into())), Op::Or, eb!(7,8, Expr_::Int(0)))));
+    assert_eq!(process_stmt("$test+=4;"), senb!(0,8, Expr_::CompoundAssign(eb!(0,5, Expr_::Variable("test".into())), Op::Add, eb!(7,8, Expr_::Int(4, false)))));
+    assert_eq!(process_stmt("$test|=0;"), senb!(0,8, Expr_::CompoundAssign(eb!(0,5, Expr_::Variable("test".into())), Op::Or, eb!(7,8, Expr_::Int(0, false)))));
 }
 
 #[test]
@@ -101,7 +103,7 @@ fn parse_stmt_if_assign() {
     assert_eq!(process_stmt("if (! $a && $b = $c) { echo 1; }"), st!(0,32, Stmt_::If(eb!(4,19, Expr_::BinaryOp(Op::And,
         eb!(4,8, Expr_::UnaryOp(UnaryOp::Not, eb!(6,8, Expr_::Variable("a".into())))),
         eb!(12,19, Expr_::Assign(eb!(12,14, Expr_::Variable("b".into())), eb!(17,19, Expr_::Variable("c".into())))))
-    ), Block(vec![ st!(23,30, Stmt_::Echo(vec![ enb!(28,29, Expr_::Int(1)) ])) ]), Block(vec![]))));
+    ), Block(vec![ st!(23,30, Stmt_::Echo(vec![ enb!(28,29, Expr_::Int(1, false)) ])) ]), Block(vec![]))));
 }
 
 #[test]
@@ -152,17 +154,17 @@ fn parse_stmt_if_else() {
 fn parse_stmt_do_while() {
     assert_eq!(process_stmt("do { test(); } while(count($a));"), st!(0,32, Stmt_::DoWhile(
         Block(vec![ senb!(5,11, Expr_::Call(eb!(5,9, Expr_::Path(Path::identifier(false, "test".into()))), vec![])) ]),
-        eb!(21,30, Expr_::Call(eb!(21,26, Expr_::Path(Path::identifier(false, "count".into()))), vec![ enb!(27,29, Expr_::Variable("a".into())) ]))
+        eb!(21,30, Expr_::Call(eb!(21,26, Expr_::Path(Path::identifier(false, "count".into()))), vec![ enb!(27,29, Expr_::Variable("a".into())).into() ]))
     )));
 }
 
 #[test]
 fn parse_stmt_for() {
     assert_eq!(process_stmt("for ($i = 0; $i < 10; $i++) { echo 1; }"), st!(0,39, Stmt_::For(
-        vec![enb!(5,11, Expr_::Assign(eb!(5,7, Expr_::Variable("i".into())), eb!(10,11, Expr_::Int(0))))],
-        vec![enb!(13,20, Expr_::BinaryOp(Op::Lt, eb!(13,15, Expr_::Variable("i".into())), eb!(18,20, Expr_::Int(10))))],
+        vec![enb!(5,11, Expr_::Assign(eb!(5,7, Expr_::Variable("i".into())), eb!(10,11, Expr_::Int(0, false))))],
+        vec![enb!(13,20, Expr_::BinaryOp(Op::Lt, eb!(13,15, Expr_::Variable("i".into())), eb!(18,20, Expr_::Int(10, false))))],
         vec![enb!(22,26, Expr_::UnaryOp(UnaryOp::PostInc, eb!(22,24, Expr_::Variable("i".into()))))],
-        Block(vec![ st!(30,37, Stmt_::Echo(vec![ enb!(35,36, Expr_::Int(1)) ])) ]),
+        Block(vec![ st!(30,37, Stmt_::Echo(vec![ enb!(35,36, Expr_::Int(1, false)) ])) ]),
     )));
 }
 
@@ -170,11 +172,11 @@ fn parse_stmt_for() {
 fn parse_stmt_for_exprs() {
     assert_eq!(process_stmt("for ($i = 0, $c=4;;)        { echo 1; }"), st!(0,39, Stmt_::For(
         vec![
-            enb!(5,11, Expr_::Assign(eb!(5,7, Expr_::Variable("i".into())), eb!(10,11, Expr_::Int(0)))),
-            enb!(13,17, Expr_::Assign(eb!(13,15, Expr_::Variable("c".into())), eb!(16,17, Expr_::Int(4)))),
+            enb!(5,11, Expr_::Assign(eb!(5,7, Expr_::Variable("i".into())), eb!(10,11, Expr_::Int(0, false)))),
+            enb!(13,17, Expr_::Assign(eb!(13,15, Expr_::Variable("c".into())), eb!(16,17, Expr_::Int(4, false)))),
         ],
         vec![], vec![],
-        Block(vec![ st!(30,37, Stmt_::Echo(vec![ enb!(35,36, Expr_::Int(1)) ])) ]),
+        Block(vec![ st!(30,37, Stmt_::Echo(vec![ enb!(35,36, Expr_::Int(1, false)) ])) ]),
     )));
 }
 
@@ -206,7 +208,7 @@ fn parse_stmt_instanceof() {
 #[test]
 fn parse_stmt_new_as_param() {
     assert_eq!(process_stmt("r(new Foo);"), senb!(0,10, Expr_::Call(eb!(0,1, Expr_::Path(Path::identifier(false, "r".into()))), vec![
-        enb!(2,9, Expr_::New(eb!(6,9, Expr_::Path(Path::identifier(false, "Foo".into()))), vec![]))
+        enb!(2,9, Expr_::New(eb!(6,9, Expr_::Path(Path::identifier(false, "Foo".into()))), vec![])).into()
     ])));
 }
 
@@ -214,7 +216,7 @@ fn parse_stmt_new_as_param() {
 fn parse_stmt_try() {
     assert_eq!(process_stmt(r#"try { echo "ok"; } catch (Exception $e) { return false;}"#), st!(0, 56, Stmt_::Try(
         Block(vec![ st!(6,16, Stmt_::Echo(vec![ enb!(11,15, Expr_::String("ok".into())) ])) ]),
-        vec![ CatchClause { ty: Path::identifier(false, "Exception".into()), var: "e".into(),
+        vec![ CatchClause { types: vec![Path::identifier(false, "Exception".into())], var: "e".into(),
             block: Block(vec![ st!(42,55, Stmt_::Return(Some(eb!(49,54, constant!(false))))) ]),
         } ],
         None,
@@ -222,11 +224,16 @@ fn parse_stmt_try() {
     assert_eq!(process_stmt(r#"try { echo "ok"; } catch (Exception $e) { return false; } catch (Throwable $e) { return true; }"#), st!(0,95, Stmt_::Try(
         Block(vec![ st!(6,16, Stmt_::Echo(vec![ enb!(11,15, Expr_::String("ok".into())) ])) ]),
         vec![
-            CatchClause { ty: Path::identifier(false, "Exception".into()), var: "e".into(), block: Block(vec![ st!(42,55, Stmt_::Return(Some(eb!(49,54, constant!(false))))) ]) },
-            CatchClause { ty: Path::identifier(false, "Throwable".into()), var: "e".into(), block: Block(vec![ st!(81,93, Stmt_::Return(Some(eb!(88,92, constant!(true))))) ]) },
+            CatchClause { types: vec![Path::identifier(false, "Exception".into())], var: "e".into(), block: Block(vec![ st!(42,55, Stmt_::Return(Some(eb!(49,54, constant!(false))))) ]) },
+            CatchClause { types: vec![Path::identifier(false, "Throwable".into())], var: "e".into(), block: Block(vec![ st!(81,93, Stmt_::Return(Some(eb!(88,92, constant!(true))))) ]) },
         ],
         None,
     )));
+    assert_eq!(process_stmt(r#"try {} catch (A | \B\C $e) {}"#), st!(0,29, Stmt_::Try(
+        Block(vec![]),
+        vec![ CatchClause { types: vec![Path::identifier(false, "A".into()), Path::ns_identifier(true, "B".into(), "C".into())], var: "e".into(), block: Block(vec![]) } ],
+        None,
+    )));
 }
 
 #[test]
@@ -243,7 +250,7 @@ fn parse_stmt_use() {
 fn parse_stmt_switch() {
     assert_eq!(process_stmt(r#"switch ($test) { case 1: echo "1"; break; default: echo "2"; }"#), st!(0,62, Stmt_::Switch(
         eb!(8,13, Expr_::Variable("test".into())), vec![
-            SwitchCase { default: false, conds: vec![ enb!(22,23, Expr_::Int(1)) ], block: Block(vec![
+            SwitchCase { default: false, conds: vec![ enb!(22,23, Expr_::Int(1, false)) ], block: Block(vec![
                 st!(25,34, Stmt_::Echo(vec![ enb!(30,33, Expr_::String("1".into())) ])), st!(35,41, Stmt_::Break(None))
             ])},
             SwitchCase { default: true, conds: vec![], block: Block(vec![ st!(51,60, Stmt_::Echo(vec![ enb!(56,59, Expr_::String("2".into())) ])) ]) },
@@ -251,20 +258,20 @@ fn parse_stmt_switch() {
     )));
     assert_eq!(process_stmt(r#"switch ($test) { case 1: echo "1"; default: echo "2"; }"#), st!(0,55, Stmt_::Switch(eb!(8,13, Expr_::Variable("test".into())),
         vec![
-        SwitchCase { default: false, conds: vec![ enb!(22,23, Expr_::Int(1)) ], block: Block(vec![
+        SwitchCase { default: false, conds: vec![ enb!(22,23, Expr_::Int(1, false)) ], block: Block(vec![
             st!(25,34, Stmt_::Echo(vec![ enb!(30,33, Expr_::String("1".into())) ]))
         ]) },
         SwitchCase { default: true, conds: vec![], block: Block(vec![ st!(44,53, Stmt_::Echo(vec![ enb!(49,52, Expr_::String("2".into())) ])) ]) }
     ])));
     assert_eq!(process_stmt("switch ($test) { case 1: case 2: echo 1; }"), st!(0,42, Stmt_::Switch(eb!(8,13, Expr_::Variable("test".into())), vec![
-        SwitchCase { default: false, conds: vec![ enb!(22,23, Expr_::Int(1)), enb!(30,31, Expr_::Int(2)) ], block: Block(vec![
-            st!(33,40, Stmt_::Echo(vec![ enb!(38,39, Expr_::Int(1)) ]))
+        SwitchCase { default: false, conds: vec![ enb!(22,23, Expr_::Int(1, false)), enb!(30,31, Expr_::Int(2, false)) ], block: Block(vec![
+            st!(33,40, Stmt_::Echo(vec![ enb!(38,39, Expr_::Int(1, false)) ]))
         ])}
     ])));
     assert_eq!(process_stmt("switch ($test) { case 1: case 2: case 3: case 4: echo 1; }"), st!(0,58, Stmt_::Switch(eb!(8,13, Expr_::Variable("test".into())), vec![
         SwitchCase { default: false,
-            conds: vec![ enb!(22,23, Expr_::Int(1)), enb!(30,31, Expr_::Int(2)), enb!(38,39, Expr_::Int(3)), enb!(46,47, Expr_::Int(4)) ],
-            block: Block(vec![ st!(49,56, Stmt_::Echo(vec![ enb!(54,55, Expr_::Int(1)) ]))])
+            conds: vec![ enb!(22,23, Expr_::Int(1, false)), enb!(30,31, Expr_::Int(2, false)), enb!(38,39, Expr_::Int(3, false)), enb!(46,47, Expr_::Int(4, false)) ],
+            block: Block(vec![ st!(49,56, Stmt_::Echo(vec![ enb!(54,55, Expr_::Int(1, false)) ]))])
         }
     ])));
 }
@@ -276,67 +283,94 @@ fn parse_stmt_goto() {
 
 #[test]
 fn parse_stmt_func_decl() {
-    assert_eq!(process_stmt("function test() { ok(); }"), st!(0,25, Stmt_::Decl(Decl::GlobalFunction("test".into(), FunctionDecl { params: vec![],
+    assert_eq!(process_stmt("function test() { ok(); }"), st!(0,25, Stmt_::Decl(Decl::GlobalFunction("test".into(), FunctionDecl { attributes: vec![], params: vec![],
         body: Some(Block(vec![ senb!(18,22, Expr_::Call(eb!(18,20, Expr_::Path(Path::identifier(false, "ok".into()))), vec![])) ])), usev: vec![], ret_ref: false, ret_ty: None })
     )));
-    assert_eq!(process_stmt("function &test() { ok(); }"), st!(0,26, Stmt_::Decl(Decl::GlobalFunction("test".into(), FunctionDecl { params: vec![],
+    assert_eq!(process_stmt("function &test() { ok(); }"), st!(0,26, Stmt_::Decl(Decl::GlobalFunction("test".into(), FunctionDecl { attributes: vec![], params: vec![],
         body: Some(Block(vec![ senb!(19,23, Expr_::Call(eb!(19,21, Expr_::Path(Path::identifier(false, "ok".into()))), vec![])) ])), usev: vec![], ret_ref: true, ret_ty: None })
     )));
     assert_eq!(process_stmt("function test($a) { ok(); }"), st!(0,27, Stmt_::Decl(Decl::GlobalFunction("test".into(), FunctionDecl {
-        params: vec![ParamDefinition { name: "a".into(), as_ref: false, variadic: false, ty: None, default: None }],
+        attributes: vec![],
+        params: vec![ParamDefinition { attributes: vec![], modifiers: MemberModifiers::none(), name: "a".into(), as_ref: false, variadic: false, ty: None, default: None }],
         body: Some(Block(vec![ senb!(20,24, Expr_::Call(eb!(20,22, Expr_::Path(Path::identifier(false, "ok".into()))), vec![])) ])), usev: vec![], ret_ref: false, ret_ty: None })
     )));
     assert_eq!(process_stmt("function test($a, $b) { ok(); }"), st!(0,31, Stmt_::Decl(Decl::GlobalFunction("test".into(), FunctionDecl {
+        attributes: vec![],
         params: vec![
-            ParamDefinition { name: "a".into(), as_ref: false, variadic: false, ty: None, default: None },
-            ParamDefinition { name: "b".into(), as_ref: false, variadic: false, ty: None, default: None }
+            ParamDefinition { attributes: vec![], modifiers: MemberModifiers::none(), name: "a".into(), as_ref: false, variadic: false, ty: None, default: None },
+            ParamDefinition { attributes: vec![], modifiers: MemberModifiers::none(), name: "b".into(), as_ref: false, variadic: false, ty: None, default: None }
         ],
         body: Some(Block(vec![ senb!(24,28, Expr_::Call(eb!(24,26, Expr_::Path(Path::identifier(false, "ok".into()))), vec![])) ])), usev: vec![], ret_ref: false, ret_ty: None })
     )));
     assert_eq!(process_stmt("function test(...$a) { ok(); }"), st!(0,30, Stmt_::Decl(Decl::GlobalFunction("test".into(), FunctionDecl {
-        params: vec![ParamDefinition { name: "a".into(), as_ref: false, variadic: true, ty: None, default: None }],
+        attributes: vec![],
+        params: vec![ParamDefinition { attributes: vec![], modifiers: MemberModifiers::none(), name: "a".into(), as_ref: false, variadic: true, ty: None, default: None }],
         body: Some(Block(vec![ senb!(23,27, Expr_::Call(eb!(23,25, Expr_::Path(Path::identifier(false, "ok".into()))), vec![])) ])), usev: vec![], ret_ref: false, ret_ty: None })
     )));
 }
 
 #[test]
 fn parse_stmt_func_decl_ret() {
-    assert_eq!(process_stmt("function test() : bool { ok(); }"), st!(0,32, Stmt_::Decl(Decl::GlobalFunction("test".into(), FunctionDecl { params: vec![],
+    assert_eq!(process_stmt("function test() : bool { ok(); }"), st!(0,32, Stmt_::Decl(Decl::GlobalFunction("test".into(), FunctionDecl { attributes: vec![], params: vec![],
         body: Some(Block(vec![ senb!(25,29, Expr_::Call(eb!(25,27, Expr_::Path(Path::identifier(false, "ok".into()))), vec![])) ])), usev: vec![], ret_ref: false,
-            ret_ty: Some(NullableTy::NonNullable(Ty::Bool)) })
+            ret_ty: Some(TypeExpr::Simple(Ty::Bool)) })
     )));
-    assert_eq!(process_stmt(r#"function test() : \foo\bar { ok(); }"#), st!(0,36, Stmt_::Decl(Decl::GlobalFunction("test".into(), FunctionDecl { params: vec![],
+    assert_eq!(process_stmt(r#"function test() : \foo\bar { ok(); }"#), st!(0,36, Stmt_::Decl(Decl::GlobalFunction("test".into(), FunctionDecl { attributes: vec![], params: vec![],
         body: Some(Block(vec![ senb!(29,33, Expr_::Call(eb!(29,31, Expr_::Path(Path::identifier(false, "ok".into()))), vec![])) ])), usev: vec![], ret_ref: false,
-            ret_ty: Some(NullableTy::NonNullable(Ty::Object(Some(Path::ns_identifier(true, "foo".into(), "bar".into()))))) })
+            ret_ty: Some(TypeExpr::Simple(Ty::Object(Some(Path::ns_identifier(true, "foo".into(), "bar".into()))))) })
     )));
-    assert_eq!(process_stmt(r#"function test() : ?FoooBar { ok(); }"#), st!(0,36, Stmt_::Decl(Decl::GlobalFunction("test".into(), FunctionDecl { params: vec![],
+    assert_eq!(process_stmt(r#"function test() : ?FoooBar { ok(); }"#), st!(0,36, Stmt_::Decl(Decl::GlobalFunction("test".into(), FunctionDecl { attributes: vec![], params: vec![],
         body: Some(Block(vec![ senb!(29,33, Expr_::Call(eb!(29,31, Expr_::Path(Path::identifier(false, "ok".into()))), vec![])) ])), usev: vec![], ret_ref: false,
-            ret_ty: Some(NullableTy::Nullable(Ty::Object(Some(Path::identifier(false, "FoooBar".into()))))) })
+            ret_ty: Some(TypeExpr::Nullable(Ty::Object(Some(Path::identifier(false, "FoooBar".into()))))) })
     )));
 }
 
 #[test]
 fn parse_func_decl_typehint() {
     assert_eq!(process_stmt("function test(Test $a) { ok(); }"), st!(0,32, Stmt_::Decl(Decl::GlobalFunction("test".into(), FunctionDecl {
-        params: vec![ ParamDefinition { name: "a".into(), as_ref: false, variadic: false,
-            ty: Some(NullableTy::NonNullable(Ty::Object(Some(Path::identifier(false, "Test".into()))))), default: None }
+        attributes: vec![],
+        params: vec![ ParamDefinition { attributes: vec![], modifiers: MemberModifiers::none(), name: "a".into(), as_ref: false, variadic: false,
+            ty: Some(TypeExpr::Simple(Ty::Object(Some(Path::identifier(false, "Test".into()))))), default: None }
         ],
         body: Some(Block(vec![ senb!(25,29, Expr_::Call(eb!(25,27, Expr_::Path(Path::identifier(false, "ok".into()))), vec![])) ])), usev: vec![], ret_ref: false, ret_ty: None })
     )));
 }
 
+#[test]
+fn parse_func_decl_composite_types() {
+    let class = |name: &str| Ty::Object(Some(Path::identifier(false, name.into())));
+    let param = |name: &str, as_ref: bool, ty: TypeExpr| ParamDefinition {
+        attributes: vec![], modifiers: MemberModifiers::none(), name: name.into(), as_ref: as_ref, variadic: false, ty: Some(ty), default: None,
+    };
+    assert_eq!(process_stmt("function a(int|string $b, A&B $c, A & $d, (A&B)|null $e): int|false {}"), st!(0,70, Stmt_::Decl(Decl::GlobalFunction("a".into(), FunctionDecl {
+        attributes: vec![],
+        params: vec![
+            param("b", false, TypeExpr::Union(vec![TypeExpr::Simple(Ty::Int), TypeExpr::Simple(Ty::String)])),
+            param("c", false, TypeExpr::Intersection(vec![class("A"), class("B")])),
+            param("d", true, TypeExpr::Simple(class("A"))),
+            param("e", false, TypeExpr::Union(vec![TypeExpr::Intersection(vec![class("A"), class("B")]), TypeExpr::Simple(Ty::Null)])),
+        ],
+        body: Some(Block(vec![])), usev: vec![], ret_ref: false,
+        ret_ty: Some(TypeExpr::Union(vec![TypeExpr::Simple(Ty::Int), TypeExpr::Simple(Ty::False)])),
+    }))));
+}
+
 #[test]
 fn parse_class_decl() {
     assert_eq!(process_stmt("class Test {}"), st!(0,13, Stmt_::Decl(Decl::Class(ClassDecl {
+        attributes: vec![],
         cmod: ClassModifiers::none(), name: "Test".into(), base_class: None, implements: vec![], members: vec![]
     }))));
     assert_eq!(process_stmt("final class Test {}"), st!(0,19, Stmt_::Decl(Decl::Class(ClassDecl {
+        attributes: vec![],
         cmod: ClassModifiers::new(&[ClassModifier::Final]), name: "Test".into(), base_class: None, implements: vec![], members: vec![]
     }))));
     assert_eq!(process_stmt("class Test extends Abc\\Test2 {}"), st!(0,31, Stmt_::Decl(Decl::Class(ClassDecl {
+        attributes: vec![],
         cmod: ClassModifiers::none(), name: "Test".into(), base_class: Some(Path::ns_identifier(false, "Abc".into(), "Test2".into())), implements: vec![], members: vec![]
     }))));
     assert_eq!(process_stmt("class Test implements ITest {}"), st!(0,30, Stmt_::Decl(Decl::Class(ClassDecl {
+        attributes: vec![],
         cmod: ClassModifiers::none(), name: "Test".into(), base_class: None, implements: vec![Path::identifier(false, "ITest".into())], members: vec![]
     }))));
 }
@@ -344,50 +378,156 @@ fn parse_class_decl() {
 #[test]
 fn parse_class_properties() {
     assert_eq!(process_stmt("class Test { public $test; }"), st!(0,28, Stmt_::Decl(Decl::Class(ClassDecl {
+        attributes: vec![],
         cmod: ClassModifiers::none(), name: "Test".into(), base_class: None, implements: vec![],
-        members: vec![ Member::Property(MemberModifiers::new(&[MemberModifier::Public]), "test".into(), None)],
+        members: vec![ Member::Property(vec![], MemberModifiers::new(&[MemberModifier::Public]), None, "test".into(), None)],
     }))));
     assert_eq!(process_stmt("class Test { protected $ab = []; }"), st!(0,34, Stmt_::Decl(Decl::Class(ClassDecl {
+        attributes: vec![],
         cmod: ClassModifiers::none(), name: "Test".into(), base_class: None, implements: vec![],
-        members: vec![ Member::Property(MemberModifiers::new(&[MemberModifier::Protected]), "ab".into(), Some(enb!(29,31, Expr_::Array(vec![])))) ],
+        members: vec![ Member::Property(vec![], MemberModifiers::new(&[MemberModifier::Protected]), None, "ab".into(), Some(enb!(29,31, Expr_::Array(vec![])))) ],
+    }))));
+    assert_eq!(process_stmt("class Test { private ?Foo $a = null, $b; static int $c; }"), st!(0,57, Stmt_::Decl(Decl::Class(ClassDecl {
+        attributes: vec![],
+        cmod: ClassModifiers::none(), name: "Test".into(), base_class: None, implements: vec![],
+        members: vec![
+            Member::Property(vec![], MemberModifiers::new(&[MemberModifier::Private]), Some(TypeExpr::Nullable(Ty::Object(Some(Path::identifier(false, "Foo".into()))))),
+                "a".into(), Some(enb!(31,35, constant!(null)))),
+            Member::Property(vec![], MemberModifiers::new(&[MemberModifier::Private]), Some(TypeExpr::Nullable(Ty::Object(Some(Path::identifier(false, "Foo".into()))))),
+                "b".into(), None),
+            Member::Property(vec![], MemberModifiers::new(&[MemberModifier::Static]), Some(TypeExpr::Simple(Ty::Int)), "c".into(), None),
+        ],
     }))));
 }
 
 #[test]
 fn parse_class_const() {
     assert_eq!(process_stmt("class Test { const C=true; }"), st!(0, 28, Stmt_::Decl(Decl::Class(ClassDecl {
+        attributes: vec![],
         cmod: ClassModifiers::none(), name: "Test".into(), base_class: None, implements: vec![],
-        members: vec![ Member::Constant(MemberModifiers::none(), "C".into(), enb!(21,25, constant!(true))) ]
+        members: vec![ Member::Constant(vec![], MemberModifiers::none(), "C".into(), enb!(21,25, constant!(true))) ]
     }))));
 }
 
 #[test]
 fn parse_class_methods() {
     assert_eq!(process_stmt("class Test { public function a() { run(); } }"), st!(0,45, Stmt_::Decl(Decl::Class(ClassDecl {
+        attributes: vec![],
         cmod: ClassModifiers::none(), name: "Test".into(), base_class: None, implements: vec![],
         members: vec![ Member::Method(MemberModifiers::new(&[MemberModifier::Public]), "a".into(), FunctionDecl {
+            attributes: vec![],
             params: vec![], body: Some(Block(vec![ senb!(35,40, Expr_::Call(eb!(35,38, Expr_::Path(Path::identifier(false, "run".into()))), vec![])) ])),
             usev: vec![], ret_ref: false, ret_ty: None,
         })]
     }))));
     assert_eq!(process_stmt("class Test { public function __construct(array $param1 = []) { $this->param = $param1; } }"),
         st!(0,90, Stmt_::Decl(Decl::Class(ClassDecl {
+            attributes: vec![],
             cmod: ClassModifiers::none(), name: "Test".into(), base_class: None, implements: vec![],
             members: vec![ Member::Method(MemberModifiers::new(&[MemberModifier::Public]), "__construct".into(), FunctionDecl {
-                params: vec![ParamDefinition { name: "param1".into(), as_ref: false, variadic: false, ty: Some(NullableTy::NonNullable(Ty::Array)),
+                attributes: vec![],
+                params: vec![ParamDefinition { attributes: vec![], modifiers: MemberModifiers::none(), name: "param1".into(), as_ref: false, variadic: false, ty: Some(TypeExpr::Simple(Ty::Array)),
                     default: Some(enb!(57,59, Expr_::Array(vec![]))) }
                 ],
                 body: Some(Block(vec![ senb!(63,85, Expr_::Assign(eb!(63,75, Expr_::ObjMember(eb!(63,68, Expr_::Variable("this".into())), vec![
-                    enb!(70,75, Expr_::Path(Path::identifier(false, "param".into()))) ])), eb!(78,85, Expr_::Variable("param1".into()))))
+                    enb!(70,75, Expr_::Path(Path::identifier(false, "param".into()))) ], false)), eb!(78,85, Expr_::Variable("param1".into()))))
                 ])), usev: vec![], ret_ref: false, ret_ty: None,
             })]
         })))
     );
 }
 
+#[test]
+fn parse_attributes() {
+    assert_eq!(process_stmt("#[Route('/x', methods: ['GET'])] function a() {}"), st!(0,48, Stmt_::Decl(Decl::GlobalFunction("a".into(), FunctionDecl {
+        attributes: vec![ Attribute { name: Path::identifier(false, "Route".into()), args: vec![
+            enb!(8,12, Expr_::String("/x".into())).into(),
+            Argument { name: Some("methods".into()), unpack: false, by_ref: false,
+                value: enb!(23,30, Expr_::Array(vec![ (None, enb!(24,29, Expr_::String("GET".into()))) ])) },
+        ] } ],
+        params: vec![], body: Some(Block(vec![])), usev: vec![], ret_ref: false, ret_ty: None,
+    }))));
+    assert_eq!(process_stmt("#[A] class B { #[C] const D = 1; #[E(1)] public $f; #[G] public function h(#[I] $j) {} }"), st!(0,88, Stmt_::Decl(Decl::Class(ClassDecl {
+        attributes: vec![ Attribute { name: Path::identifier(false, "A".into()), args: vec![] } ],
+        cmod: ClassModifiers::none(), name: "B".into(), base_class: None, implements: vec![],
+        members: vec![
+            Member::Constant(vec![ Attribute { name: Path::identifier(false, "C".into()), args: vec![] } ], MemberModifiers::none(), "D".into(), enb!(30,31, Expr_::Int(1, false))),
+            Member::Property(vec![ Attribute { name: Path::identifier(false, "E".into()), args: vec![ enb!(37,38, Expr_::Int(1, false)).into() ] } ],
+                MemberModifiers::new(&[MemberModifier::Public]), None, "f".into(), None),
+            Member::Method(MemberModifiers::new(&[MemberModifier::Public]), "h".into(), FunctionDecl {
+                attributes: vec![ Attribute { name: Path::identifier(false, "G".into()), args: vec![] } ],
+                params: vec![ ParamDefinition {
+                    attributes: vec![ Attribute { name: Path::identifier(false, "I".into()), args: vec![] } ], modifiers: MemberModifiers::none(),
+                    name: "j".into(), as_ref: false, variadic: false, ty: None, default: None,
+                } ],
+                body: Some(Block(vec![])), usev: vec![], ret_ref: false, ret_ty: None,
+            }),
+        ],
+    }))));
+}
+
+#[test]
+fn parse_class_constructor_promotion() {
+    assert_eq!(process_stmt("class A { public function __construct(private int $b, $c) {} }"), st!(0,62, Stmt_::Decl(Decl::Class(ClassDecl {
+        attributes: vec![],
+        cmod: ClassModifiers::none(), name: "A".into(), base_class: None, implements: vec![],
+        members: vec![ Member::Method(MemberModifiers::new(&[MemberModifier::Public]), "__construct".into(), FunctionDecl {
+            attributes: vec![],
+            params: vec![
+                ParamDefinition { attributes: vec![], modifiers: MemberModifiers::new(&[MemberModifier::Private]), name: "b".into(), as_ref: false,
+                    variadic: false, ty: Some(TypeExpr::Simple(Ty::Int)), default: None },
+                ParamDefinition { attributes: vec![], modifiers: MemberModifiers::none(), name: "c".into(), as_ref: false,
+                    variadic: false, ty: None, default: None },
+            ],
+            body: Some(Block(vec![])), usev: vec![], ret_ref: false, ret_ty: None,
+        })]
+    }))));
+}
+
+#[test]
+fn parse_readonly_and_never() {
+    assert_eq!(process_stmt("readonly class A { public readonly int $b; }"), st!(0,44, Stmt_::Decl(Decl::Class(ClassDecl {
+        attributes: vec![],
+        cmod: ClassModifiers::new(&[ClassModifier::Readonly]), name: "A".into(), base_class: None, implements: vec![],
+        members: vec![ Member::Property(vec![], MemberModifiers::new(&[MemberModifier::Public, MemberModifier::Readonly]), Some(TypeExpr::Simple(Ty::Int)),
+            "b".into(), None) ],
+    }))));
+    assert_eq!(process_stmt("class A { public function __construct(public readonly int $b) {} }"), st!(0,66, Stmt_::Decl(Decl::Class(ClassDecl {
+        attributes: vec![],
+        cmod: ClassModifiers::none(), name: "A".into(), base_class: None, implements: vec![],
+        members: vec![ Member::Method(MemberModifiers::new(&[MemberModifier::Public]), "__construct".into(), FunctionDecl {
+            attributes: vec![],
+            params: vec![
+                ParamDefinition { attributes: vec![], modifiers: MemberModifiers::new(&[MemberModifier::Public, MemberModifier::Readonly]), name: "b".into(),
+                    as_ref: false, variadic: false, ty: Some(TypeExpr::Simple(Ty::Int)), default: None },
+            ],
+            body: Some(Block(vec![])), usev: vec![], ret_ref: false, ret_ty: None,
+        })]
+    }))));
+    assert_eq!(process_stmt("function a(): never {}"), st!(0,22, Stmt_::Decl(Decl::GlobalFunction("a".into(), FunctionDecl {
+        attributes: vec![], params: vec![], body: Some(Block(vec![])), usev: vec![], ret_ref: false, ret_ty: Some(TypeExpr::Simple(Ty::Never)),
+    }))));
+}
+
+#[test]
+fn parse_class_semi_reserved_member_names() {
+    let decl = FunctionDecl { attributes: vec![], params: vec![], body: Some(Block(vec![])), usev: vec![], ret_ref: false, ret_ty: None };
+    assert_eq!(process_stmt("class A { const DEFAULT = 1; function print() {} public static function new() {} function List() {} }"),
+        st!(0,101, Stmt_::Decl(Decl::Class(ClassDecl {
+            attributes: vec![],
+            cmod: ClassModifiers::none(), name: "A".into(), base_class: None, implements: vec![],
+            members: vec![
+                Member::Constant(vec![], MemberModifiers::none(), "DEFAULT".into(), enb!(26,27, Expr_::Int(1, false))),
+                Member::Method(MemberModifiers::none(), "print".into(), decl.clone()),
+                Member::Method(MemberModifiers::new(&[MemberModifier::Public, MemberModifier::Static]), "new".into(), decl.clone()),
+                Member::Method(MemberModifiers::none(), "List".into(), decl),
+            ],
+        }))));
+}
+
 #[test]
 fn parse_class_trait_use() {
-    assert_eq!(process_stmt("class Test { use Abc; }"), st!(0,23, Stmt_::Decl(Decl::Class(ClassDecl { name: "Test".into(), base_class: None, implements: vec![], members: vec![
+    assert_eq!(process_stmt("class Test { use Abc; }"), st!(0,23, Stmt_::Decl(Decl::Class(ClassDecl { attributes: vec![], name: "Test".into(), base_class: None, implements: vec![], members: vec![
         Member::TraitUse(vec![Path::identifier(false, "Abc".into())], vec![])
     ], cmod: ClassModifiers::none() }))));
 }
@@ -401,21 +541,27 @@ fn parse_stmt_list() {
 
 #[test]
 fn parse_trait_decl() {
-    assert_eq!(process_stmt("trait Test {}"), st!(0,13, Stmt_::Decl(Decl::Trait("Test".into(), vec![]))));
+    assert_eq!(process_stmt("trait Test {}"), st!(0,13, Stmt_::Decl(Decl::Trait(vec![], "Test".into(), vec![]))));
     // http://php.net/manual/de/language.oop5.traits.php
-    assert_eq!(process_stmt("trait HelloWorld {use Hello, World;}"), st!(0,36, Stmt_::Decl(Decl::Trait("HelloWorld".into(), vec![
+    assert_eq!(process_stmt("trait HelloWorld {use Hello, World;}"), st!(0,36, Stmt_::Decl(Decl::Trait(vec![], "HelloWorld".into(), vec![
         Member::TraitUse(vec![Path::identifier(false, "Hello".into()), Path::identifier(false, "World".into())], vec![])
     ]))));
+    assert_eq!(process_stmt("#[A] trait B {}"), st!(0,15, Stmt_::Decl(Decl::Trait(vec![
+        Attribute { name: Path::identifier(false, "A".into()), args: vec![] }
+    ], "B".into(), vec![]))));
 }
 
 #[test]
 fn parse_interface_decl() {
-    assert_eq!(process_stmt("interface ITest {}"), st!(0,18, Stmt_::Decl(Decl::Interface("ITest".into(), vec![], vec![]))));
+    assert_eq!(process_stmt("interface ITest {}"), st!(0,18, Stmt_::Decl(Decl::Interface(vec![], "ITest".into(), vec![], vec![]))));
     assert_eq!(process_stmt("interface ITest { public function test(); }"), st!(0,43, Stmt_::Decl(
-        Decl::Interface("ITest".into(), vec![], vec![ Member::Method(MemberModifiers::new(&[MemberModifier::Public]),
-            "test".into(), FunctionDecl {params: vec![], body: None, usev: vec![], ret_ref: false, ret_ty: None})
+        Decl::Interface(vec![], "ITest".into(), vec![], vec![ Member::Method(MemberModifiers::new(&[MemberModifier::Public]),
+            "test".into(), FunctionDecl { attributes: vec![], params: vec![], body: None, usev: vec![], ret_ref: false, ret_ty: None})
         ])
     )));
+    assert_eq!(process_stmt("#[A] interface B {}"), st!(0,19, Stmt_::Decl(Decl::Interface(vec![
+        Attribute { name: Path::identifier(false, "A".into()), args: vec![] }
+    ], "B".into(), vec![], vec![]))));
 }
 
 #[test]
@@ -429,6 +575,7 @@ fn parse_class_use_trait_complex() {
         }
     }";
     assert_eq!(process_stmt(code), st!(0,163, Stmt_::Decl(Decl::Class(ClassDecl {
+        attributes: vec![],
         cmod: ClassModifiers::none(), name: "Aliased_Talker".into(), base_class: None, implements: vec![], members: vec![
             Member::TraitUse(vec![Path::identifier(false, "A".into()), Path::identifier(false, "B".into())], vec![
                 TraitUse::InsteadOf(Path::identifier(false, "B".into()), "smallTalk".into(), vec![Path::identifier(false, "A".into())]),
@@ -439,6 +586,55 @@ fn parse_class_use_trait_complex() {
     }))));
 }
 
+#[test]
+fn parse_class_use_trait_visibility() {
+    assert_eq!(process_stmt("class A { use B { c as protected; B::d as private e; f as list; } }"), st!(0,67, Stmt_::Decl(Decl::Class(ClassDecl {
+        attributes: vec![],
+        cmod: ClassModifiers::none(), name: "A".into(), base_class: None, implements: vec![], members: vec![
+            Member::TraitUse(vec![Path::identifier(false, "B".into())], vec![
+                TraitUse::As(None, "c".into(), MemberModifiers::new(&[MemberModifier::Protected]), None),
+                TraitUse::As(Some(Path::identifier(false, "B".into())), "d".into(), MemberModifiers::new(&[MemberModifier::Private]), Some("e".into())),
+                TraitUse::As(None, "f".into(), MemberModifiers::none(), Some("list".into())),
+            ])
+        ]
+    }))));
+}
+
+#[test]
+fn parse_enum_decl() {
+    assert_eq!(process_stmt("enum Suit: string implements HasLabel { case Hearts = 'H'; const X = 1; use T; public function label() {} }"),
+        st!(0,107, Stmt_::Decl(Decl::Enum(EnumDecl {
+            attributes: vec![],
+            name: "Suit".into(),
+            backing_ty: Some(Ty::String),
+            implements: vec![Path::identifier(false, "HasLabel".into())],
+            members: vec![
+                Member::EnumCase(vec![], "Hearts".into(), Some(enb!(54,57, Expr_::String("H".into())))),
+                Member::Constant(vec![], MemberModifiers::none(), "X".into(), enb!(69,70, Expr_::Int(1, false))),
+                Member::TraitUse(vec![Path::identifier(false, "T".into())], vec![]),
+                Member::Method(MemberModifiers::new(&[MemberModifier::Public]), "label".into(), FunctionDecl {
+                    attributes: vec![], params: vec![], body: Some(Block(vec![])), usev: vec![], ret_ref: false, ret_ty: None,
+                }),
+            ],
+        }))));
+    assert_eq!(process_stmt("enum A { case B; }"), st!(0,18, Stmt_::Decl(Decl::Enum(EnumDecl {
+        attributes: vec![], name: "A".into(), backing_ty: None, implements: vec![],
+        members: vec![ Member::EnumCase(vec![], "B".into(), None) ],
+    }))));
+}
+
+#[test]
+fn function_decl_is_generator() {
+    let is_generator = |src: &str| match process_stmt(src).0 {
+        Stmt_::Decl(Decl::GlobalFunction(_, ref decl)) => decl.is_generator(),
+        _ => unreachable!(),
+    };
+    assert!(is_generator("function a() { if ($b) { $c = yield; } }"));
+    assert!(is_generator("function a() { return yield from b(); }"));
+    assert!(!is_generator("function a() { return function () { yield 1; }; }"));
+    assert!(!is_generator("function a() { $b = fn() => yield 1; }"));
+}
+
 #[test]
 fn parse_static_decl() {
     assert_eq!(process_stmt("static $t=true;"), st!(0,15, Stmt_::Decl(Decl::StaticVars(vec![ ("t".into(), Some(enb!(10,14, constant!(true)))) ]))));
@@ -447,13 +643,17 @@ fn parse_static_decl() {
 #[test]
 fn parse_global_decl() {
     assert_eq!(process_stmt("global $t;"), st!(0,10, Stmt_::Decl(Decl::GlobalVars(vec![ "t".into() ]))));
+    assert_eq!(process_stmt("global $$t, ${'u'};"), st!(0,19, Stmt_::Decl(Decl::GlobalVars(vec![
+        Variable::Fetch(eb!(8,10, Expr_::Variable("t".into()))), Variable::Fetch(eb!(14,17, Expr_::String("u".into())))
+    ]))));
 }
 
 #[test]
 fn parse_stmt_closure_use() {
     assert_eq!(process_stmt("return function () use ($t) {};"), st!(0,31, Stmt_::Return(Some(eb!(7,30, Expr_::Function(FunctionDecl {
+        attributes: vec![],
         params: vec![], body: Some(Block(vec![])), usev: vec![(false, "t".into())], ret_ref: false, ret_ty: None,
-    }))))));
+    }, false))))));
 }
 
 #[test]
@@ -0,0 +1,32 @@
+use std::env;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::Path;
+use token_dump::{token_get_all, tokens_to_json};
+
+/// compares the token dump of every .php file in src/tests/token_dump with the .json file next to it,
+/// set UPDATE_GOLDEN=1 to rewrite the expected output
+#[test]
+fn token_dump_golden_files() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("src/tests/token_dump");
+    let update = env::var("UPDATE_GOLDEN").is_ok();
+    let mut count = 0;
+    for entry in fs::read_dir(&dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().map_or(true, |x| x != "php") {
+            continue;
+        }
+        let mut src = vec![];
+        File::open(&path).unwrap().read_to_end(&mut src).unwrap();
+        let json = tokens_to_json(&token_get_all(&src).unwrap());
+        let json_path = path.with_extension("json");
+        if update {
+            File::create(&json_path).unwrap().write_all(json.as_bytes()).unwrap();
+        }
+        let mut expected = String::new();
+        File::open(&json_path).unwrap().read_to_string(&mut expected).unwrap();
+        assert_eq!(json, expected, "token dump of {}", path.display());
+        count += 1;
+    }
+    assert!(count > 0);
+}
@@ -0,0 +1,11 @@
+<html><?php
+// comment
+# another one
+/** doc */
+namespace Foo\Bar;
+
+$a = (int) 1.5 + 0x1F ?? null; /* multi
+line */ $b->c::D();
+yield from gen();
+?>
+tail
@@ -0,0 +1,12 @@
+<?php
+echo "plain", "a $b c", "{$d[0]}${e}${f[1]}", "$g[h] $i->j $k[-1]";
+echo `ls $dir`, b"bin", "esc\t\x41$l";
+echo <<<EOT
+a $x
+  {$y}
+EOT;
+echo <<<'EOT'
+raw $x
+EOT;
+echo <<<"EOT"
+EOT;
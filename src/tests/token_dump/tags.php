@@ -0,0 +1,4 @@
+<p><?= $a ?>
+<? echo 1 ?>x<?php
+
+$b;
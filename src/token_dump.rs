@@ -0,0 +1,349 @@
+//! export the token stream in the shape of PHP's `token_get_all()` (as of PHP 7.1), which allows
+//! differential testing against PHP and reusing tools which work on PHP's token stream
+use std::fmt::Write;
+use tokenizer::{Tokenizer, LineMap};
+use tokens::{Span, Token, TokenSpan, SyntaxError};
+
+/// A token as `token_get_all()` returns it
+#[derive(Clone, Debug, PartialEq)]
+pub enum PhpToken {
+    /// a token which PHP returns as plain string, such as ";"
+    Char(String),
+    /// the name of the token (e.g. T_VARIABLE), its source text and the (1-based) line it starts on
+    Token(&'static str, String, usize),
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Context {
+    /// within the literal parts of a string, which ends with the given token
+    Str(StrEnd),
+    /// within a `{` block
+    Block,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum StrEnd {
+    DoubleQuote,
+    Backquote,
+    HereDoc,
+}
+
+/// a token name (None for plain string tokens) and the source range of the token
+type Entry = (Option<&'static str>, usize, usize);
+
+fn token_name(token: &Token, span: &Span, in_string: bool) -> Option<&'static str> {
+    Some(match *token {
+        Token::OpenTagWithEcho => "T_OPEN_TAG_WITH_ECHO",
+        Token::OpenTag => "T_OPEN_TAG",
+        Token::CloseTag => "T_CLOSE_TAG",
+        Token::Exit => "T_EXIT",
+        Token::Function => "T_FUNCTION",
+        Token::Fn => "T_FN",
+        Token::Const => "T_CONST",
+        Token::Return => "T_RETURN",
+        Token::Yield => "T_YIELD",
+        Token::YieldFrom => "T_YIELD_FROM",
+        Token::Try => "T_TRY",
+        Token::Catch => "T_CATCH",
+        Token::Finally => "T_FINALLY",
+        Token::Throw => "T_THROW",
+        Token::If => "T_IF",
+        Token::ElseIf => "T_ELSEIF",
+        Token::EndIf => "T_ENDIF",
+        Token::Else => "T_ELSE",
+        Token::While => "T_WHILE",
+        Token::EndWhile => "T_ENDWHILE",
+        Token::Do => "T_DO",
+        Token::For => "T_FOR",
+        Token::Endfor => "T_ENDFOR",
+        Token::Foreach => "T_FOREACH",
+        Token::EndForeach => "T_ENDFOREACH",
+        Token::Declare => "T_DECLARE",
+        Token::EndDeclare => "T_ENDDECLARE",
+        Token::InstanceOf => "T_INSTANCEOF",
+        Token::As => "T_AS",
+        Token::Switch => "T_SWITCH",
+        Token::EndSwitch => "T_ENDSWITCH",
+        Token::Match => "T_MATCH",
+        Token::Case => "T_CASE",
+        Token::Default => "T_DEFAULT",
+        Token::Break => "T_BREAK",
+        Token::Continue => "T_CONTINUE",
+        Token::Goto => "T_GOTO",
+        Token::Echo => "T_ECHO",
+        Token::Print => "T_PRINT",
+        Token::Class => "T_CLASS",
+        Token::Interface => "T_INTERFACE",
+        Token::Trait => "T_TRAIT",
+        Token::Extends => "T_EXTENDS",
+        Token::Implements => "T_IMPLEMENTS",
+        Token::ObjectOp => "T_OBJECT_OPERATOR",
+        Token::NullsafeObjectOp => "T_NULLSAFE_OBJECT_OPERATOR",
+        Token::AttributeStart => "T_ATTRIBUTE",
+        Token::ScopeOp => "T_DOUBLE_COLON",
+        Token::NsSeparator => "T_NS_SEPARATOR",
+        Token::Ellipsis => "T_ELLIPSIS",
+        Token::Coalesce => "T_COALESCE",
+        Token::New => "T_NEW",
+        Token::Clone => "T_CLONE",
+        Token::Var => "T_VAR",
+        Token::CastInt => "T_INT_CAST",
+        Token::CastDouble => "T_DOUBLE_CAST",
+        Token::CastString => "T_STRING_CAST",
+        Token::CastArray => "T_ARRAY_CAST",
+        Token::CastObject => "T_OBJECT_CAST",
+        Token::CastBool => "T_BOOL_CAST",
+        Token::CastUnset => "T_UNSET_CAST",
+        Token::Eval => "T_EVAL",
+        Token::Include => "T_INCLUDE",
+        Token::IncludeOnce => "T_INCLUDE_ONCE",
+        Token::Require => "T_REQUIRE",
+        Token::RequireOnce => "T_REQUIRE_ONCE",
+        Token::Namespace => "T_NAMESPACE",
+        Token::Use => "T_USE",
+        Token::Insteadof => "T_INSTEADOF",
+        Token::Global => "T_GLOBAL",
+        Token::Isset => "T_ISSET",
+        Token::Empty => "T_EMPTY",
+        Token::HaltCompiler => "T_HALT_COMPILER",
+        Token::Static => "T_STATIC",
+        Token::Abstract => "T_ABSTRACT",
+        Token::Final => "T_FINAL",
+        Token::Private => "T_PRIVATE",
+        Token::Protected => "T_PROTECTED",
+        Token::Public => "T_PUBLIC",
+        Token::Unset => "T_UNSET",
+        Token::DoubleArrow => "T_DOUBLE_ARROW",
+        Token::List => "T_LIST",
+        Token::Array => "T_ARRAY",
+        Token::Callable => "T_CALLABLE",
+        Token::Increment => "T_INC",
+        Token::Decrement => "T_DEC",
+        Token::IsIdentical => "T_IS_IDENTICAL",
+        Token::IsNotIdentical => "T_IS_NOT_IDENTICAL",
+        Token::IsEqual => "T_IS_EQUAL",
+        Token::IsNotEqual => "T_IS_NOT_EQUAL",
+        Token::SpaceShip => "T_SPACESHIP",
+        Token::IsSmallerOrEqual => "T_IS_SMALLER_OR_EQUAL",
+        Token::IsGreaterOrEqual => "T_IS_GREATER_OR_EQUAL",
+        Token::PlusEqual => "T_PLUS_EQUAL",
+        Token::MinusEqual => "T_MINUS_EQUAL",
+        Token::MulEqual => "T_MUL_EQUAL",
+        Token::Pow => "T_POW",
+        Token::PowEqual => "T_POW_EQUAL",
+        Token::DivEqual => "T_DIV_EQUAL",
+        Token::ConcatEqual => "T_CONCAT_EQUAL",
+        Token::ModEqual => "T_MOD_EQUAL",
+        Token::SlEqual => "T_SL_EQUAL",
+        Token::SrEqual => "T_SR_EQUAL",
+        Token::AndEqual => "T_AND_EQUAL",
+        Token::OrEqual => "T_OR_EQUAL",
+        Token::XorEqual => "T_XOR_EQUAL",
+        Token::BoolOr => "T_BOOLEAN_OR",
+        Token::BoolAnd => "T_BOOLEAN_AND",
+        Token::LogicalOr => "T_LOGICAL_OR",
+        Token::LogicalAnd => "T_LOGICAL_AND",
+        Token::LogicalXor => "T_LOGICAL_XOR",
+        Token::Sl => "T_SL",
+        Token::Sr => "T_SR",
+        Token::DollarCurlyBracesOpen => "T_DOLLAR_OPEN_CURLY_BRACES",
+        Token::CurlyBracesOpen if in_string => "T_CURLY_OPEN",
+        Token::HereDocStart(_) | Token::NowDocStart(_) => "T_START_HEREDOC",
+        Token::HereDocEnd(_, _) => "T_END_HEREDOC",
+        Token::Variable(_) => "T_VARIABLE",
+        Token::Int(_) => "T_LNUMBER",
+        Token::Double(_) => "T_DNUMBER",
+        Token::Comment(_) if span.doc_comment.is_some() => "T_DOC_COMMENT",
+        Token::Comment(_) => "T_COMMENT",
+        Token::Whitespace(_) => "T_WHITESPACE",
+        Token::String(_) => "T_STRING",
+        Token::ConstantEncapsedString(_) |
+        Token::BinaryCharSequence(_) if in_string => "T_ENCAPSED_AND_WHITESPACE",
+        Token::ConstantEncapsedString(_) |
+        Token::BinaryCharSequence(_) => "T_CONSTANT_ENCAPSED_STRING",
+        Token::NumString(_) => "T_NUM_STRING",
+        Token::InlineHtml(_) |
+        Token::BinaryInlineHtml(_) => "T_INLINE_HTML",
+        Token::MagicClass => "T_CLASS_C",
+        Token::MagicTrait => "T_TRAIT_C",
+        Token::MagicFunction => "T_FUNC_C",
+        Token::MagicMethod => "T_METHOD_C",
+        Token::MagicLine => "T_LINE",
+        Token::MagicFile => "T_FILE",
+        Token::MagicDir => "T_DIR",
+        Token::MagicNamespace => "T_NS_C",
+        _ => return None,
+    })
+}
+
+/// the length of the newline at the start of the input (0 if there is none)
+fn newline_len(input: &[u8]) -> usize {
+    if input.starts_with(b"\r\n") {
+        2
+    } else if input.starts_with(b"\n") || input.starts_with(b"\r") {
+        1
+    } else {
+        0
+    }
+}
+
+fn mk_entries(tokens: &[TokenSpan]) -> Vec<Entry> {
+    let mut entries: Vec<Entry> = vec![];
+    let mut stack = vec![];
+    let mut i = 0;
+    while i < tokens.len() {
+        let TokenSpan(ref token, ref span) = tokens[i];
+        let (start, end) = (span.start as usize, span.end as usize);
+        let in_string = match stack.last() {
+            Some(&Context::Str(_)) => true,
+            _ => false,
+        };
+        // parts of a string which don't contribute to its value don't have a token, e.g. the last newline of a heredoc
+        if in_string {
+            let last_end = entries.last().map_or(start, |x| x.2);
+            if last_end < start {
+                entries.push((Some("T_ENCAPSED_AND_WHITESPACE"), last_end, start));
+            }
+        }
+        let str_end = match *token {
+            Token::DoubleQuote => Some(StrEnd::DoubleQuote),
+            Token::Backquote => Some(StrEnd::Backquote),
+            Token::HereDocStart(_) | Token::NowDocStart(_) => Some(StrEnd::HereDoc),
+            Token::HereDocEnd(_, _) => Some(StrEnd::HereDoc),
+            _ => None,
+        };
+        match (token, str_end) {
+            (_, Some(str_end)) if stack.last() == Some(&Context::Str(str_end)) => {
+                stack.pop();
+            }
+            // a double quoted string without variables is a single token
+            (&Token::DoubleQuote, _) => {
+                let skip = match tokens.get(i + 1) {
+                    Some(&TokenSpan(Token::DoubleQuote, _)) => Some(1),
+                    Some(&TokenSpan(Token::ConstantEncapsedString(_), _)) |
+                    Some(&TokenSpan(Token::BinaryCharSequence(_), _)) => match tokens.get(i + 2) {
+                        Some(&TokenSpan(Token::DoubleQuote, _)) => Some(2),
+                        _ => None,
+                    },
+                    _ => None,
+                };
+                if let Some(skip) = skip {
+                    i += skip + 1;
+                    entries.push((Some("T_CONSTANT_ENCAPSED_STRING"), start, tokens[i - 1].1.end as usize));
+                    continue;
+                }
+                stack.push(Context::Str(StrEnd::DoubleQuote));
+            }
+            (_, Some(str_end)) => stack.push(Context::Str(str_end)),
+            (&Token::CurlyBracesOpen, _) => stack.push(Context::Block),
+            (&Token::DollarCurlyBracesOpen, _) => {
+                stack.push(Context::Block);
+                // ${name} and ${name[...]} refer to a variable by name
+                if let (Some(&TokenSpan(Token::String(_), ref span)), Some(&TokenSpan(ref next, _))) = (tokens.get(i + 1), tokens.get(i + 2)) {
+                    if *next == Token::CurlyBracesClose || *next == Token::SquareBracketOpen {
+                        entries.push((Some("T_DOLLAR_OPEN_CURLY_BRACES"), start, end));
+                        entries.push((Some("T_STRING_VARNAME"), span.start as usize, span.end as usize));
+                        i += 2;
+                        continue;
+                    }
+                }
+            }
+            (&Token::CurlyBracesClose, _) => {
+                if stack.last() == Some(&Context::Block) {
+                    stack.pop();
+                }
+            }
+            _ => (),
+        }
+        entries.push((token_name(token, span, in_string), start, end));
+        i += 1;
+    }
+    entries
+}
+
+/// PHP includes the following newline in some tokens, which the tokenizer leaves in the next token
+fn move_newlines(src: &[u8], entries: &mut Vec<Entry>) {
+    let mut i = 0;
+    while i + 1 < entries.len() {
+        let (name, start, end) = entries[i];
+        let text = &src[start..end];
+        let next_name = entries[i + 1].0;
+        let steal = match (name, next_name) {
+            // "<?php" is followed by exactly one whitespace character, which belongs to it
+            (Some("T_OPEN_TAG"), Some("T_WHITESPACE")) => match newline_len(&src[end..]) {
+                0 => 1,
+                len => len,
+            },
+            (Some("T_CLOSE_TAG"), Some("T_INLINE_HTML")) => newline_len(&src[end..]),
+            (Some("T_COMMENT"), Some("T_WHITESPACE")) if text.starts_with(b"#") || text.starts_with(b"//") => newline_len(&src[end..]),
+            _ => 0,
+        };
+        if steal > 0 {
+            entries[i].2 += steal;
+            entries[i + 1].1 += steal;
+            if entries[i + 1].1 == entries[i + 1].2 {
+                entries.remove(i + 1);
+            }
+        }
+        i += 1;
+    }
+}
+
+/// Tokenize the given source the same way PHP's `token_get_all()` does.
+/// Source texts which aren't valid UTF-8 are converted lossily.
+pub fn token_get_all(src: &[u8]) -> Result<Vec<PhpToken>, SyntaxError> {
+    let mut tokenizer = Tokenizer::from_bytes(src);
+    tokenizer.set_trivia(true);
+    let mut tokens = vec![];
+    loop {
+        match try!(tokenizer.next_token()) {
+            TokenSpan(Token::End, _) => break,
+            token => tokens.push(token),
+        }
+    }
+    let line_map: LineMap = tokenizer.into_external_state().1.line_map;
+    let mut entries = mk_entries(&tokens);
+    move_newlines(src, &mut entries);
+    Ok(entries.into_iter().map(|(name, start, end)| {
+        let text = String::from_utf8_lossy(&src[start..end]).into_owned();
+        match name {
+            Some(name) => PhpToken::Token(name, text, line_map.line_from_position(start) + 1),
+            None => PhpToken::Char(text),
+        }
+    }).collect())
+}
+
+fn write_json_str(out: &mut String, str_: &str) {
+    out.push('"');
+    for chr in str_.chars() {
+        match chr {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '\u{0}'...'\u{1F}' | '\u{7F}' => write!(out, "\\u{:04x}", chr as u32).unwrap(),
+            _ => out.push(chr),
+        }
+    }
+    out.push('"');
+}
+
+/// Serialize tokens as JSON array, one token per line. Single character tokens are strings,
+/// all others are arrays of the token name, the source text and the line number like `["T_VARIABLE","$a",1]`.
+pub fn tokens_to_json(tokens: &[PhpToken]) -> String {
+    let mut out = "[".to_owned();
+    for (i, token) in tokens.iter().enumerate() {
+        out.push_str(if i == 0 { "\n    " } else { ",\n    " });
+        match *token {
+            PhpToken::Char(ref text) => write_json_str(&mut out, text),
+            PhpToken::Token(name, ref text, line) => {
+                write!(out, "[\"{}\",", name).unwrap();
+                write_json_str(&mut out, text);
+                write!(out, ",{}]", line).unwrap();
+            }
+        }
+    }
+    out.push_str("\n]\n");
+    out
+}
@@ -1,6 +1,8 @@
 //! tokenizer based on [Zend LS](https://github.com/php/php-src/blob/ebb99a1a3a2ec9216d95c63b267ae0f66074f4de/Zend/zend_language_scanner.l)
 //! since the reference doesn't seem very correct in some cases
 use std::str::{self, FromStr};
+use std::iter;
+use std::slice;
 use std::rc::Rc;
 use std::mem;
 
@@ -43,10 +45,29 @@ enum State {
     Done,
 }
 
+/// The kind of string literal an escape sequence occurs in, since each one decodes a different set of escapes
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum EscapeTy {
+    SingleQuote,
+    DoubleQuote,
+    Backquote,
+    HereDoc,
+}
+
 pub struct Tokenizer<'a> {
-    code: &'a str,
+    code: &'a [u8],
     /// whether to support short tags, equal to CG(short_tags)
     short_tags: bool,
+    /// whether to support ASP-style tags (<% %>), equal to CG(asp_tags)
+    asp_tags: bool,
+    /// whether `#[` starts an attribute instead of a comment (PHP 8)
+    attributes: bool,
+    /// whether `fn` is a keyword instead of an identifier (PHP 7.4)
+    arrow_functions: bool,
+    /// whether `match` is a keyword instead of an identifier (PHP 8)
+    match_expressions: bool,
+    /// whether to emit whitespace tokens
+    trivia: bool,
     pub state: TokenizerState,
     queue: Vec<TokenSpan>,
     interner: Interner,
@@ -126,15 +147,49 @@ impl TokenizerState {
     }
 }
 
-/// Check if a string staarts with a token (case-insensitive)
+/// Check if a string starts with a token (case-insensitive)
 trait StrStartsWithCI {
     fn starts_with_ci(&self, s: &str) -> bool;
 }
 
-impl<'a> StrStartsWithCI for &'a str {
+impl<'a> StrStartsWithCI for &'a [u8] {
     #[inline]
     fn starts_with_ci(&self, s: &str) -> bool {
-        self.chars().take(s.len()).collect::<String>() == s.to_lowercase()
+        self.len() >= s.len() && self[..s.len()].eq_ignore_ascii_case(s.as_bytes())
+    }
+}
+
+#[inline]
+fn byte_to_char(x: &u8) -> char {
+    *x as char
+}
+
+/// find the first occurrence of needle within the haystack
+#[inline]
+fn find_bytes(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|x| x == needle)
+}
+
+/// find the line of a here/nowdoc body which contains the end-tag (after optional indentation),
+/// returns the offset of that line and the length of the indentation
+fn find_doc_end_tag(input: &[u8], end_tag: &[u8]) -> Option<(usize, usize)> {
+    let mut line_start = 0;
+    loop {
+        let line = &input[line_start..];
+        let indentation_len = line.iter().take_while(|&&x| x == b' ' || x == b'\t').count();
+        let rest = &line[indentation_len..];
+        // the end-tag must not be followed by further label characters
+        let continues_label = match rest.get(end_tag.len()) {
+            Some(&b'a'...b'z') | Some(&b'A'...b'Z') | Some(&b'0'...b'9') | Some(&b'_') | Some(&0x80...0xFF) => true,
+            _ => false,
+        };
+        if rest.starts_with(end_tag) && !continues_label {
+            return Some((line_start, indentation_len));
+        }
+        match line.iter().position(|&x| x == b'\n') {
+            Some(pos) => line_start += pos + 1,
+            None => return None,
+        }
     }
 }
 
@@ -204,7 +259,14 @@ macro_rules! ret_token {
 
 /// helper to transform string-members into the appropriate token
 impl<'a> Tokenizer<'a> {
+    #[cfg(test)]
     pub fn new(src: &'a str) -> Tokenizer<'a> {
+        Tokenizer::from_bytes(src.as_bytes())
+    }
+
+    /// tokenize the raw bytes of a source file, which doesn't have to be valid UTF-8
+    /// (e.g. ISO-8859-1 encoded), string literals which aren't valid UTF-8 are kept as BinaryCharSequence
+    pub fn from_bytes(src: &'a [u8]) -> Tokenizer<'a> {
         let mut tokenizer = Tokenizer {
             code: src,
             state: TokenizerState {
@@ -216,6 +278,11 @@ impl<'a> Tokenizer<'a> {
                 restart: false,
             },
             short_tags: true,
+            asp_tags: false,
+            attributes: true,
+            arrow_functions: true,
+            match_expressions: true,
+            trivia: false,
             queue: vec![],
             interner: Interner::new(),
         };
@@ -223,36 +290,69 @@ impl<'a> Tokenizer<'a> {
         tokenizer
     }
 
+    /// whether `<?` starts a PHP block
     #[inline]
-    pub fn into_external_state(self) -> (Interner, TokenizerExternalState) {
-        (self.interner, self.state.external)
+    pub fn set_short_tags(&mut self, short_tags: bool) {
+        self.short_tags = short_tags;
     }
 
-    /// advances by n-positions where a position is a char-index
-    /// this returns a substring of the skipped part (old[..n])
+    /// whether `<%`, `<%=` and `%>` are recognized as open and close tags
     #[inline]
-    fn advance_chars(&mut self, n: usize) -> &'a str {
-        let end_byte_pos = match self.input().char_indices().nth(n) {
-            Some((byte_pos, _)) => byte_pos,
-            None => self.input().len(),
-        };
-        self.advance_bytes(end_byte_pos)
+    pub fn set_asp_tags(&mut self, asp_tags: bool) {
+        self.asp_tags = asp_tags;
+    }
+
+    /// whether `#[` starts an attribute, in older versions it starts a comment
+    #[inline]
+    pub fn set_attributes(&mut self, attributes: bool) {
+        self.attributes = attributes;
     }
 
+    /// whether `fn` starts an arrow function, in older versions it's an identifier
     #[inline]
-    fn advance_bytes(&mut self, n: usize) -> &'a str {
+    pub fn set_arrow_functions(&mut self, arrow_functions: bool) {
+        self.arrow_functions = arrow_functions;
+    }
+
+    /// whether `match` starts a match expression, in older versions it's an identifier
+    #[inline]
+    pub fn set_match_expressions(&mut self, match_expressions: bool) {
+        self.match_expressions = match_expressions;
+    }
+
+    /// emit whitespace as `Token::Whitespace` instead of skipping it
+    #[inline]
+    pub fn set_trivia(&mut self, trivia: bool) {
+        self.trivia = trivia;
+    }
+
+    #[inline]
+    pub fn into_external_state(self) -> (Interner, TokenizerExternalState) {
+        (self.interner, self.state.external)
+    }
+
+    /// advances by n bytes, this returns a slice of the skipped part (old[..n])
+    #[inline]
+    fn advance_bytes(&mut self, n: usize) -> &'a [u8] {
         let ret = &self.input()[..n];
         self.state.src_pos += n;
         ret
     }
 
     #[inline]
-    fn input(&self) -> &'a str {
+    fn input(&self) -> &'a [u8] {
         &self.code[self.state.src_pos..]
     }
 
+    /// the remaining input as chars, where each byte is mapped to the char of the same value (as in ISO-8859-1)
+    /// which allows to match the input just like PHP does, byte by byte
+    #[inline]
+    fn input_chars(&self) -> iter::Map<slice::Iter<'a, u8>, fn(&u8) -> char> {
+        self.input().iter().map(byte_to_char as fn(&u8) -> char)
+    }
+
     #[inline]
-    fn input_pos(&self) -> usize {
+    pub fn input_pos(&self) -> usize {
         self.state.src_pos
     }
 
@@ -268,7 +368,7 @@ impl<'a> Tokenizer<'a> {
     /// handle whitespace
     fn whitespace(&mut self) {
         while !self.input().is_empty() {
-            match self.input().chars().nth(0).unwrap() {
+            match self.input_chars().nth(0).unwrap() {
                 ' ' | '\t' | '\r' => {
                     self.advance_bytes(1);
                 }
@@ -281,10 +381,29 @@ impl<'a> Tokenizer<'a> {
         }
     }
 
+    /// handle whitespace, which is returned as a token if trivia are kept
+    fn whitespace_token(&mut self) -> Option<TokenSpan> {
+        let old_pos = self.input_pos();
+        self.whitespace();
+        if !self.trivia || old_pos == self.input_pos() {
+            return None;
+        }
+        let whitespace = self.interner.intern_bytes(&self.code[old_pos..self.input_pos()]);
+        Some(TokenSpan(Token::Whitespace(whitespace), mk_span(old_pos, self.input_pos())))
+    }
+
+    /// register the start of every line within the already consumed input since `start`
+    fn register_newlines(&mut self, start: usize) {
+        for (i, _) in self.code[start..self.input_pos()].iter().enumerate().filter(|&(_, &x)| x == b'\n') {
+            self.state.external.line_map.push((start + i + 1) as u32);
+            self.state.line_num += 1;
+        }
+    }
+
     /// handle tabs and spaces
     fn whitespace_only(&mut self) {
         while !self.input().is_empty() {
-            match self.input().chars().nth(0).unwrap() {
+            match self.input_chars().nth(0).unwrap() {
                 ' ' | '\t' => {
                     self.advance_bytes(1);
                 }
@@ -295,9 +414,9 @@ impl<'a> Tokenizer<'a> {
 
     /// match exactly one newline
     fn newline(&mut self) -> bool {
-        let amount = if self.input().starts_with("\r\n") {
+        let amount = if self.input().starts_with(b"\r\n") {
             2
-        } else if self.input().starts_with('\n') || self.input().starts_with('\r') {
+        } else if self.input().starts_with(b"\n") || self.input().starts_with(b"\r") {
             1
         } else {
             return false;
@@ -308,17 +427,17 @@ impl<'a> Tokenizer<'a> {
     }
 
     // re2c stuff (mostly prefixed with _)
-    fn _label(&mut self) -> Option<(&'a str, Span)> {
+    fn _label(&mut self) -> Option<(&'a [u8], Span)> {
         if self.input().is_empty() {
             return None;
         }
-        if let Some('0'...'9') = self.input().chars().nth(0) {
+        if let Some('0'...'9') = self.input_chars().nth(0) {
             return None;
         }
 
         // \u{0080} U+0080
         // \u{00BF} U+00FF
-        let end_pos = self.input().chars().position(|x| match x {
+        let end_pos = self.input_chars().position(|x| match x {
             'a'...'z' |
             'A'...'Z' |
             '\u{80}'...'\u{FF}' |
@@ -329,10 +448,10 @@ impl<'a> Tokenizer<'a> {
         let end_pos = match end_pos {
             Some(0) => return None,
             Some(x) => x,
-            None => self.input().chars().count(),
+            None => self.input().len(),
         };
         let old_pos = self.input_pos();
-        let ret = self.advance_chars(end_pos);
+        let ret = self.advance_bytes(end_pos);
         Some((ret, mk_span(old_pos, old_pos + end_pos)))
     }
 
@@ -341,7 +460,7 @@ impl<'a> Tokenizer<'a> {
         if self.input().is_empty() {
             return Ok(TokenSpan(Token::End, mk_span(self.code.len(), self.code.len())));
         }
-        let tok = match self.input().chars().nth(0).unwrap() {
+        let tok = match self.input_chars().nth(0).unwrap() {
             ';' => Token::SemiColon,
             ':' => Token::Colon,
             ',' => Token::Comma,
@@ -368,27 +487,44 @@ impl<'a> Tokenizer<'a> {
             '@' => Token::Silence,
             _ => return Err(SyntaxError::None),
         };
-        self.advance_chars(1);
+        self.advance_bytes(1);
         Ok(TokenSpan(tok, mk_span(self.input_pos() - 1, self.input_pos())))
     }
 
+    /// matches digits which may be separated by single underscores (e.g. 1_000),
+    /// returns the digits without the separators
+    fn _digits<F: Fn(u8) -> bool>(&mut self, is_digit: F) -> String {
+        let mut digits = String::new();
+        let len = {
+            let input = self.input();
+            let mut i = 0;
+            while i < input.len() {
+                if is_digit(input[i]) {
+                    digits.push(input[i] as char);
+                } else if !(input[i] == b'_' && i > 0 && i + 1 < input.len() && is_digit(input[i + 1])) {
+                    break;
+                }
+                i += 1;
+            }
+            i
+        };
+        self.advance_bytes(len);
+        digits
+    }
+
     /// match a double number (or a long number/octal number)
     fn _onum_dnum_lnum(&mut self) -> Result<TokenSpan, SyntaxError> {
         // valid inputs for double: "long.", ".long", "long.long"
         if self.input().is_empty() {
             return Ok(TokenSpan(Token::End, mk_span(self.code.len(), self.code.len())));
         }
-        let end_pos = match self.input().chars().position(|x| x < '0' || x > '9') {
-            None => self.input().len(),
-            Some(end_pos) => end_pos,
-        };
         let old_pos = self.input_pos();
-        let str_ = self.advance_chars(end_pos).to_owned();
-        if !self.input().starts_with('.') {
+        let str_ = self._digits(|x| x >= b'0' && x <= b'9');
+        if !self.input().starts_with(b".") {
             {
                 let span = mk_span(old_pos, self.input_pos());
                 // long sub-match
-                if end_pos != 0 {
+                if !str_.is_empty() {
                     if str_.starts_with('0') && str_.len() > 1 {
                         return Ok(TokenSpan(Token::Int(i64::from_str_radix(&str_[1..], 8).unwrap()), span));
                     } else {
@@ -399,64 +535,62 @@ impl<'a> Tokenizer<'a> {
             self.state.src_pos = old_pos;
             return Err(SyntaxError::None);
         }
-        let mut str_ = str_ + self.advance_chars(1);
+        let mut str_ = str_ + ".";
+        self.advance_bytes(1);
         // at this point we either matched "long." or just "."
-        let end_pos2 = match self.input().chars().position(|x| x < '0' || x > '9') {
-            None => self.input().chars().count(),
-            Some(0) if str_.len() == 1 => {
-                self.state.src_pos = old_pos;
-                return Err(SyntaxError::None);
-            }
-            Some(end_pos) => end_pos,
-        };
-        let span = mk_span(self.input_pos(), end_pos);
-        str_.push_str(self.advance_chars(end_pos2));
+        let fraction = self._digits(|x| x >= b'0' && x <= b'9');
+        if fraction.is_empty() && str_.len() == 1 {
+            self.state.src_pos = old_pos;
+            return Err(SyntaxError::None);
+        }
+        str_.push_str(&fraction);
+        let span = mk_span(old_pos, self.input_pos());
         Ok(TokenSpan(Token::Double(f64::from_str(&str_).unwrap()), span))
     }
 
     /// match a hex number
     fn _hnum(&mut self) -> Result<TokenSpan, SyntaxError> {
-        if self.input().len() < 3 || !self.input().starts_with("0x") {
+        if self.input().len() < 3 || !self.input().starts_with(b"0x") {
             return Err(SyntaxError::None);
         }
+        let old_pos = self.input_pos();
         self.advance_bytes(2);
-        let end_pos = match self.input().chars().position(|x| match x {
-            'a'...'f' | 'A'...'F' | '0'...'9' => false,
-            _ => true,
-        }) {
-            None => self.input().chars().count(),
-            Some(0) => return Err(SyntaxError::None),
-            Some(end_pos) => end_pos,
-        };
-        let span = mk_span(self.input_pos() - 2, end_pos);
-        let str_ = self.advance_chars(end_pos);
-        Ok(TokenSpan(Token::Int(i64::from_str_radix(str_, 16).unwrap()), span))
+        let str_ = self._digits(|x| match x {
+            b'a'...b'f' | b'A'...b'F' | b'0'...b'9' => true,
+            _ => false,
+        });
+        if str_.is_empty() {
+            self.state.src_pos = old_pos;
+            return Err(SyntaxError::None);
+        }
+        let span = mk_span(old_pos, self.input_pos());
+        Ok(TokenSpan(Token::Int(i64::from_str_radix(&str_, 16).unwrap()), span))
     }
 
     /// match a binary number
     fn _bnum(&mut self) -> Result<TokenSpan, SyntaxError> {
-        if self.input().len() < 3 || !self.input().starts_with("0b") {
+        if self.input().len() < 3 || !self.input().starts_with(b"0b") {
             return Err(SyntaxError::None);
         }
+        let old_pos = self.input_pos();
         self.advance_bytes(2);
-        let end_pos = match self.input().chars().position(|x| x != '0' && x != '1') {
-            None => self.input().chars().count(),
-            Some(0) => return Err(SyntaxError::None),
-            Some(end_pos) => end_pos,
-        };
-        let span = mk_span(self.input_pos() - 2, end_pos);
-        let str_ = self.advance_chars(end_pos);
-        Ok(TokenSpan(Token::Int(i64::from_str_radix(str_, 2).unwrap()), span))
+        let str_ = self._digits(|x| x == b'0' || x == b'1');
+        if str_.is_empty() {
+            self.state.src_pos = old_pos;
+            return Err(SyntaxError::None);
+        }
+        let span = mk_span(old_pos, self.input_pos());
+        Ok(TokenSpan(Token::Int(i64::from_str_radix(&str_, 2).unwrap()), span))
     }
 
     /// matches ${label} so any valid variable_name
     fn match_variable(&mut self) -> Result<TokenSpan, SyntaxError> {
-        if self.input().len() < 2 || !self.input().starts_with('$') {
+        if self.input().len() < 2 || !self.input().starts_with(b"$") {
             return Err(SyntaxError::None);
         }
         let bak_pos = self.input_pos();
         self.advance_bytes(1);
-        match self._label().map(|(x, span)| (self.interner.intern(x), span)) {
+        match self._label().map(|(x, span)| (self.interner.intern_bytes(x), span)) {
             Some((name, mut span)) => {
                 span.start = bak_pos as u32;
                 Ok(TokenSpan(Token::Variable(name), span))
@@ -468,76 +602,117 @@ impl<'a> Tokenizer<'a> {
         }
     }
 
-    fn str_escape(&mut self, bytes: &mut Vec<u8>, sq: bool) -> Result<(), SyntaxError> {
-        let chr = match (self.input().chars().nth(1), sq) {
-            (Some('n'), false) => Some(b'\n'),
-            (Some('r'), false) => Some(b'\r'),
-            (Some('t'), false) => Some(b'\t'),
-            (Some('f'), false) => Some(b'\x0C'),
-            (Some('v'), false) => Some(b'\x0B'),
-            (Some('e'), false) => unimplemented!(),
-            (Some('"'), false) => Some(b'"'),
-            (Some('\''), true) => Some(b'\''),
+    fn str_escape(&mut self, bytes: &mut Vec<u8>, ty: EscapeTy) -> Result<(), SyntaxError> {
+        let chr = match (self.input_chars().nth(1), ty) {
             (Some('\\'), _) => Some(b'\\'),
-            (Some('$'), false) => Some(b'$'),
-            (Some(x @ 'x'), false) |
-            (Some(x @ 'X'), false) => {
-                // read up to 2 hex characters, on 0 add \x to bytes
-                let mut end_idx = 0;
-                for i in 2..4 {
-                    match self.input().chars().nth(i) {
-                        Some('a'...'z') | Some('A'...'Z') | Some('0'...'9') => end_idx = i,
-                        _ => break,
-                    }
-                }
-                if end_idx == 0 {
-                    bytes.push(b'\\');
-                    Some(x as u8)
-                } else {
-                    let start_pos = self.input().char_indices().nth(2).unwrap().0;
-                    let end_pos = self.input().char_indices().nth(end_idx + 1).unwrap().0;
-                    let byte = u8::from_str_radix(&self.input()[start_pos..end_pos], 16).unwrap();
-                    bytes.push(byte);
-                    self.advance_chars(1 + end_idx);
+            (Some('\''), EscapeTy::SingleQuote) => Some(b'\''),
+            (Some(_), EscapeTy::SingleQuote) => None,
+            (Some('n'), _) => Some(b'\n'),
+            (Some('r'), _) => Some(b'\r'),
+            (Some('t'), _) => Some(b'\t'),
+            (Some('f'), _) => Some(b'\x0C'),
+            (Some('v'), _) => Some(b'\x0B'),
+            (Some('e'), _) => Some(b'\x1B'),
+            (Some('$'), _) => Some(b'$'),
+            // the quote character is only escapable within its own kind of string
+            (Some('"'), EscapeTy::DoubleQuote) => Some(b'"'),
+            (Some('`'), EscapeTy::Backquote) => Some(b'`'),
+            (Some('x'), _) |
+            (Some('X'), _) => {
+                // read up to 2 hex characters, on 0 keep the \x as is
+                let len = self.input_chars().skip(2).take(2).take_while(|x| x.is_digit(16)).count();
+                if len > 0 {
+                    bytes.push(u8::from_str_radix(str::from_utf8(&self.input()[2..2 + len]).unwrap(), 16).unwrap());
+                    self.advance_bytes(2 + len);
                     return Ok(());
                 }
-            }
-            (Some('u'), false) => unimplemented!(),
-            (Some(x), _) => {
-                bytes.push(b'\\');
-                let mut tmp_str = String::new();
-                tmp_str.push(x);
-                bytes.extend(tmp_str.as_bytes());
                 None
             }
+            (Some('0'...'7'), _) => {
+                // read up to 3 octal characters, values above \377 overflow (as in PHP)
+                let len = self.input_chars().skip(1).take(3).take_while(|x| x.is_digit(8)).count();
+                bytes.push(u16::from_str_radix(str::from_utf8(&self.input()[1..1 + len]).unwrap(), 8).unwrap() as u8);
+                self.advance_bytes(1 + len);
+                return Ok(());
+            }
+            // a \u which is not followed by a { is kept as is
+            (Some('u'), _) if self.input()[2..].starts_with(b"{") => return self.str_escape_codepoint(bytes),
+            (Some(_), _) => None,
             _ => return Err(SyntaxError::Unterminated("string escape sequence", mk_span(self.input_pos(), self.input_pos() + 1))),
         };
-        self.advance_chars(2);
-        if let Some(chr) = chr {
-            bytes.push(chr);
+        match chr {
+            Some(chr) => {
+                self.advance_bytes(2);
+                bytes.push(chr);
+            }
+            // unknown escape sequences are kept as is, a newline is left for line counting
+            None if self.input()[1..].starts_with(b"\n") => bytes.extend(self.advance_bytes(1)),
+            None => bytes.extend(self.advance_bytes(2)),
+        }
+        Ok(())
+    }
+
+    /// matches an unicode codepoint escape sequence (\u{1F600}) and appends its UTF-8 representation
+    fn str_escape_codepoint(&mut self, bytes: &mut Vec<u8>) -> Result<(), SyntaxError> {
+        let start_pos = self.input_pos();
+        let len = self.input_chars().skip(3).take_while(|x| x.is_digit(16)).count();
+        if len == 0 || !self.input()[3 + len..].starts_with(b"}") {
+            let end_pos = start_pos + 3 + len + if self.input()[3 + len..].starts_with(b"}") { 1 } else { 0 };
+            return Err(SyntaxError::InvalidEscapeSequence("Invalid UTF-8 codepoint escape sequence", mk_span(start_pos, end_pos)));
+        }
+        let digits = str::from_utf8(&self.input()[3..3 + len]).unwrap().trim_start_matches('0');
+        let codepoint = match if digits.is_empty() { Ok(0) } else { u32::from_str_radix(digits, 16) } {
+            Ok(codepoint) if codepoint <= 0x10FFFF => codepoint,
+            _ => {
+                return Err(SyntaxError::InvalidEscapeSequence("Invalid UTF-8 codepoint escape sequence: Codepoint too large",
+                                                              mk_span(start_pos, start_pos + 4 + len)))
+            }
+        };
+        // encode manually, since PHP also allows surrogates here (which aren't valid chars)
+        if codepoint < 0x80 {
+            bytes.push(codepoint as u8);
+        } else if codepoint < 0x800 {
+            bytes.push(0xC0 | (codepoint >> 6) as u8);
+            bytes.push(0x80 | (codepoint & 0x3F) as u8);
+        } else if codepoint < 0x10000 {
+            bytes.push(0xE0 | (codepoint >> 12) as u8);
+            bytes.push(0x80 | ((codepoint >> 6) & 0x3F) as u8);
+            bytes.push(0x80 | (codepoint & 0x3F) as u8);
+        } else {
+            bytes.push(0xF0 | (codepoint >> 18) as u8);
+            bytes.push(0x80 | ((codepoint >> 12) & 0x3F) as u8);
+            bytes.push(0x80 | ((codepoint >> 6) & 0x3F) as u8);
+            bytes.push(0x80 | (codepoint & 0x3F) as u8);
         }
+        self.advance_bytes(4 + len);
         Ok(())
     }
 
+    /// turn the bytes collected so far into a string fragment, which spans from the end of the previous part
+    /// (or the start of the string contents) up to `end`
+    fn push_fragment(&mut self, bytes: &mut Vec<u8>, parts: &mut Vec<TokenSpan>, content_start: usize, end: usize) {
+        if bytes.is_empty() {
+            return;
+        }
+        let start = parts.last().map_or(content_start, |x| x.1.end as usize);
+        let fragment = match String::from_utf8(mem::replace(bytes, vec![])) {
+            Ok(str_) => Token::ConstantEncapsedString(self.interner.intern(&str_)),
+            Err(err) => Token::BinaryCharSequence(Rc::new(err.into_bytes())),
+        };
+        parts.push(TokenSpan(fragment, mk_span(start, end)));
+    }
+
     #[inline]
     fn return_tokens_from_parts(&mut self,
                                 start_tok: TokenSpan,
                                 end_tok: TokenSpan,
-                                bytes: Vec<u8>,
-                                parts: Vec<TokenSpan>)
+                                mut bytes: Vec<u8>,
+                                mut parts: Vec<TokenSpan>)
                                 -> TokenSpan {
-        let (start_pos, end_pos) = (start_tok.1.start, end_tok.1.end);
+        let (content_start, content_end) = (start_tok.1.end as usize, end_tok.1.start as usize);
+        self.push_fragment(&mut bytes, &mut parts, content_start, content_end);
         self.queue.push(end_tok);
-        if !bytes.is_empty() {
-            let ret_token = match String::from_utf8(bytes) {
-                Ok(str_) => Token::ConstantEncapsedString(self.interner.intern(&str_)),
-                Err(err) => Token::BinaryCharSequence(Rc::new(err.into_bytes())),
-            };
-            self.queue.push(TokenSpan(ret_token, mk_span(start_pos as usize, end_pos as usize)));
-        }
-        if !parts.is_empty() {
-            self.queue.extend(parts.into_iter().rev());
-        }
+        self.queue.extend(parts.into_iter().rev());
         state_helper!(push, self, EmitQueue);
         start_tok
     }
@@ -549,9 +724,9 @@ impl<'a> Tokenizer<'a> {
         }
         // backup the whole state, since line counting needs resetting too
         let bak_state = self.state.clone();
-        if self.input().starts_with("b'") {
+        if self.input().starts_with(b"b'") {
             self.advance_bytes(2);
-        } else if self.input().starts_with('\'') {
+        } else if self.input().starts_with(b"'") {
             self.advance_bytes(1);
         } else {
             return Err(SyntaxError::None);
@@ -562,18 +737,18 @@ impl<'a> Tokenizer<'a> {
         let mut bytes: Vec<u8> = vec![];
         loop {
             let end_pos =
-                match self.input().chars().position(|x| x == '\\' || x == '\'' || x == '\n') {
+                match self.input_chars().position(|x| x == '\\' || x == '\'' || x == '\n') {
                     Some(end_pos) => end_pos,
-                    None => self.input().chars().count(),
+                    None => self.input().len(),
                 };
-            bytes.extend(self.advance_chars(end_pos).as_bytes());
-            match self.input().chars().nth(0) {
+            bytes.extend(self.advance_bytes(end_pos));
+            match self.input_chars().nth(0) {
                 Some('\n') => {
                     self.advance_bytes(1);
                     self.state.next_line();
                     bytes.push(b'\n');
                 }
-                Some('\\') => try!(self.str_escape(&mut bytes, true)),
+                Some('\\') => try!(self.str_escape(&mut bytes, EscapeTy::SingleQuote)),
                 Some('\'') => {
                     self.advance_bytes(1);
                     break;
@@ -593,68 +768,107 @@ impl<'a> Tokenizer<'a> {
         Ok(TokenSpan(ret_token, span))
     }
 
-    fn str_variable(&mut self, bytes: &mut Vec<u8>, parts: &mut Vec<TokenSpan>) {
+    /// matches the offset of a simple variable within a string (the `[...]` in "$a[0]", "$a[-1]", "$a[key]", "$a[$i]")
+    fn str_var_offset(&mut self, parts: &mut Vec<TokenSpan>) -> Result<(), SyntaxError> {
+        let start_pos = self.input_pos();
         self.advance_bytes(1);
-        // T_DOLLAR_OPEN_CURLY_BRACES ${ ... } syntax (simple = DollarCurlyBraces, complex = str_block)
-        if self.input().starts_with('{') {
-            let pos = self.input_pos() - 1;
-            if !bytes.is_empty() {
-                let len = bytes.len();
-                let old_fragment = match String::from_utf8(mem::replace(bytes, vec![])) {
-                    Ok(str_) => Token::ConstantEncapsedString(self.interner.intern(&str_)),
-                    Err(err) => Token::BinaryCharSequence(Rc::new(err.into_bytes())),
+        parts.push(TokenSpan(Token::SquareBracketOpen, mk_span(start_pos, start_pos + 1)));
+        let is_negative = self.input().starts_with(b"-");
+        if is_negative {
+            let pos = self.input_pos();
+            self.advance_bytes(1);
+            parts.push(TokenSpan(Token::Minus, mk_span(pos, pos + 1)));
+        }
+        let pos = self.input_pos();
+        match self.input_chars().nth(0) {
+            // LNUM | HNUM | BNUM
+            Some('0'...'9') => {
+                let (prefix_len, radix) = if self.input().starts_with(b"0x") {
+                    (2, 16)
+                } else if self.input().starts_with(b"0b") {
+                    (2, 2)
+                } else {
+                    (0, 10)
                 };
-                parts.push(TokenSpan(old_fragment, mk_span(pos - len, pos)));
+                let end_pos = match self.input_chars().skip(prefix_len).position(|x| !x.is_digit(radix)) {
+                    Some(end_pos) => end_pos,
+                    None => self.input().len() - prefix_len,
+                };
+                let num = self.advance_bytes(prefix_len + end_pos);
+                parts.push(TokenSpan(Token::NumString(self.interner.intern_bytes(num)), mk_span(pos, self.input_pos())));
+            }
+            Some('$') if !is_negative => {
+                if let Ok(var) = self.match_variable() {
+                    parts.push(var);
+                }
             }
+            _ if !is_negative => {
+                if let Some((label, span)) = self._label().map(|(x, span)| (self.interner.intern_bytes(x), span)) {
+                    parts.push(TokenSpan(Token::String(label), span));
+                }
+            }
+            _ => (),
+        }
+        if pos == self.input_pos() || !self.input().starts_with(b"]") {
+            return Err(SyntaxError::Unterminated("variable offset in string", mk_span(start_pos, self.input_pos())));
+        }
+        let pos = self.input_pos();
+        self.advance_bytes(1);
+        parts.push(TokenSpan(Token::SquareBracketClose, mk_span(pos, pos + 1)));
+        Ok(())
+    }
+
+    fn str_variable(&mut self, bytes: &mut Vec<u8>, parts: &mut Vec<TokenSpan>, content_start: usize) -> Result<(), SyntaxError> {
+        self.advance_bytes(1);
+        // T_DOLLAR_OPEN_CURLY_BRACES ${ ... } syntax (simple = DollarCurlyBraces, complex = str_block)
+        if self.input().starts_with(b"{") {
+            let pos = self.input_pos() - 1;
+            self.push_fragment(bytes, parts, content_start, pos);
             let next_part = parts.len();
-            self.str_block(bytes, parts, false);
+            self.str_block(bytes, parts, content_start, false);
             // patch the CurlyBracesOpen token
             assert_eq!(parts[next_part].0, Token::CurlyBracesOpen);
             parts[next_part].1.start -= 1;
             parts[next_part].0 = Token::DollarCurlyBracesOpen;
-            return;
+            return Ok(());
         }
         // match variable
-        if let Some((label, span)) = self._label().map(|(x, span)| (self.interner.intern(x), span)) {
+        if let Some((label, span)) = self._label().map(|(x, span)| (self.interner.intern_bytes(x), span)) {
             let mut tmp_parts = vec![];
             // match var_offset
-            if self.input().starts_with('[') {
-                unimplemented!();
+            if self.input().starts_with(b"[") {
+                try!(self.str_var_offset(&mut tmp_parts));
             }
-            // match object access (only $var->label supported in PHP)
-            else if self.input().starts_with("->") {
+            // match object access (only $var->label and $var?->label supported in PHP)
+            else if self.input().starts_with(b"->") || self.input().starts_with(b"?->") {
                 let bak_pos = self.input_pos();
-                self.advance_bytes(2);
-                if let Some((property, span)) = self._label().map(|(x, span)| (self.interner.intern(x), span)) {
-                    tmp_parts.push(TokenSpan(Token::ObjectOp, mk_span(bak_pos, bak_pos+1)));
+                let op = if self.input().starts_with(b"?") { Token::NullsafeObjectOp } else { Token::ObjectOp };
+                let op_len = op.repr().len();
+                self.advance_bytes(op_len);
+                if let Some((property, span)) = self._label().map(|(x, span)| (self.interner.intern_bytes(x), span)) {
+                    tmp_parts.push(TokenSpan(op, mk_span(bak_pos, bak_pos + op_len)));
                     tmp_parts.push(TokenSpan(Token::String(property), mk_span(span.start, span.end)));
                 } else {
                     self.state.src_pos = bak_pos;
                 }
             }
             // and match the single variable, prepend it
-            if !bytes.is_empty() {
-                let len = bytes.len();
-                let start = span.start - 1;
-                let old_fragment = match String::from_utf8(mem::replace(bytes, vec![])) {
-                    Ok(str_) => Token::ConstantEncapsedString(self.interner.intern(&str_)),
-                    Err(err) => Token::BinaryCharSequence(Rc::new(err.into_bytes())),
-                };
-                parts.push(TokenSpan(old_fragment, mk_span(start as usize - len, start)));
-            }
+            self.push_fragment(bytes, parts, content_start, span.start as usize - 1);
             parts.push(TokenSpan(Token::Variable(label), mk_span(span.start - 1, span.end)));
             parts.extend(tmp_parts);
         } else {
             bytes.push(b'$');
         }
+        Ok(())
     }
 
     fn str_block(&mut self,
                  bytes: &mut Vec<u8>,
                  parts: &mut Vec<TokenSpan>,
+                 content_start: usize,
                  require_dollar: bool) {
         self.advance_bytes(1);
-        if self.input().starts_with('$') || !require_dollar {
+        if self.input().starts_with(b"$") || !require_dollar {
             let bak_state = self.state.clone();
             // temporary state transition to use the same instance to match
             self.state.state = State::InScripting;
@@ -664,15 +878,7 @@ impl<'a> Tokenizer<'a> {
                 tokens.push(tok);
             }
             if let Some(&TokenSpan(Token::CurlyBracesClose, _)) = tokens.last() {
-                if !bytes.is_empty() {
-                    let len = bytes.len();
-                    let start = bak_state.src_pos - 1;
-                    let old_fragment = match String::from_utf8(mem::replace(bytes, vec![])) {
-                        Ok(str_) => Token::ConstantEncapsedString(self.interner.intern(&str_)),
-                        Err(err) => Token::BinaryCharSequence(Rc::new(err.into_bytes())),
-                    };
-                    parts.push(TokenSpan(old_fragment, mk_span(start - len, start)));
-                }
+                self.push_fragment(bytes, parts, content_start, bak_state.src_pos - 1);
                 parts.extend(tokens);
                 // undo the temporary tokenizer state transition
                 self.state.state = bak_state.state;
@@ -691,41 +897,41 @@ impl<'a> Tokenizer<'a> {
             return Err(SyntaxError::None);
         }
         let bak_state_str = self.state.clone();
-        if self.input().starts_with("b\"") {
+        if self.input().starts_with(b"b\"") {
             self.advance_bytes(2);
-        } else if self.input().starts_with('"') {
+        } else if self.input().starts_with(b"\"") {
             self.advance_bytes(1);
         } else {
             return Err(SyntaxError::None);
         }
         // valid escapes: \n \r \t \f \v \e \" \\ \$ \x \X \u{unicode}
+        let content_start = self.input_pos();
 
         // repeatedly progress until we encounter an escape sequence (or end)
         let mut parts = vec![];
         let mut bytes: Vec<u8> = vec![];
         loop {
-            let end_pos = match self.input()
-                .chars()
+            let end_pos = match self.input_chars()
                 .position(|x| x == '\\' || x == '"' || x == '$' || x == '\n' || x == '{') {
                 Some(end_pos) => end_pos,
-                None => self.input().chars().count() - 1,
+                None => self.input().len() - 1,
             };
-            bytes.extend(self.advance_chars(end_pos).as_bytes());
+            bytes.extend(self.advance_bytes(end_pos));
 
-            match self.input().chars().nth(0) {
+            match self.input_chars().nth(0) {
                 Some('\n') => {
                     self.advance_bytes(1);
                     self.state.next_line();
                     bytes.push(b'\n');
                 }
-                Some('\\') => try!(self.str_escape(&mut bytes, false)),
+                Some('\\') => try!(self.str_escape(&mut bytes, EscapeTy::DoubleQuote)),
                 Some('"') => {
                     self.advance_bytes(1);
                     break;
                 }
-                Some('$') => self.str_variable(&mut bytes, &mut parts),
+                Some('$') => try!(self.str_variable(&mut bytes, &mut parts, content_start)),
                 // match {$<IN_SCRIPTING>} block
-                Some('{') => self.str_block(&mut bytes, &mut parts, true),
+                Some('{') => self.str_block(&mut bytes, &mut parts, content_start, true),
                 _ => {
                     let err_pos = self.input_pos();
                     self.state = bak_state_str;
@@ -735,7 +941,7 @@ impl<'a> Tokenizer<'a> {
         }
         let current_pos = self.input_pos();
         Ok(self.return_tokens_from_parts(
-            TokenSpan(Token::DoubleQuote, mk_span(bak_state_str.src_pos, bak_state_str.src_pos + 1)),
+            TokenSpan(Token::DoubleQuote, mk_span(bak_state_str.src_pos, content_start)),
             TokenSpan(Token::DoubleQuote, mk_span(current_pos - 1, current_pos)),
             bytes, parts
         ))
@@ -746,37 +952,38 @@ impl<'a> Tokenizer<'a> {
         if self.input().len() < 2 {
             return Err(SyntaxError::None);
         }
-        let bak_state_str = if self.input().starts_with('`') {
+        let bak_state_str = if self.input().starts_with(b"`") {
             let bak_state = self.state.clone();
             self.advance_bytes(1);
             bak_state
         } else {
             return Err(SyntaxError::None);
         };
+        let content_start = self.input_pos();
         let mut parts = vec![];
         let mut bytes: Vec<u8> = vec![];
         loop {
-            let end_pos = match self.input()
-                .chars()
-                .position(|x| x == '\\' || x == '"' || x == '$' || x == '\n' || x == '{') {
+            let end_pos = match self.input_chars()
+                .position(|x| x == '\\' || x == '`' || x == '$' || x == '\n' || x == '{') {
                 Some(end_pos) => end_pos,
-                None => self.input().chars().count() - 1,
+                None => self.input().len(),
             };
-            bytes.extend(self.advance_chars(end_pos).as_bytes());
+            bytes.extend(self.advance_bytes(end_pos));
 
-            match self.input().chars().nth(0) {
+            match self.input_chars().nth(0) {
                 Some('\n') => {
                     self.advance_bytes(1);
                     self.state.next_line();
                     bytes.push(b'\n');
                 }
+                Some('\\') => try!(self.str_escape(&mut bytes, EscapeTy::Backquote)),
                 Some('`') => {
                     self.advance_bytes(1);
                     break;
                 }
-                Some('$') => self.str_variable(&mut bytes, &mut parts),
+                Some('$') => try!(self.str_variable(&mut bytes, &mut parts, content_start)),
                 // match {$<IN_SCRIPTING>} block
-                Some('{') => self.str_block(&mut bytes, &mut parts, true),
+                Some('{') => self.str_block(&mut bytes, &mut parts, content_start, true),
                 _ => {
                     let old_pos = self.input_pos();
                     self.state = bak_state_str;
@@ -786,7 +993,7 @@ impl<'a> Tokenizer<'a> {
         }
         let current_pos = self.input_pos();
         Ok(self.return_tokens_from_parts(
-            TokenSpan(Token::Backquote, mk_span(bak_state_str.src_pos, bak_state_str.src_pos + 1)),
+            TokenSpan(Token::Backquote, mk_span(bak_state_str.src_pos, content_start)),
             TokenSpan(Token::Backquote, mk_span(current_pos - 1, current_pos)),
             bytes, parts
         ))
@@ -806,9 +1013,9 @@ impl<'a> Tokenizer<'a> {
             return Err(SyntaxError::None);
         }
         let bak_state_str = self.state.clone();
-        if self.input().starts_with("b<<<") {
+        if self.input().starts_with(b"b<<<") {
             self.advance_bytes(4);
-        } else if self.input().starts_with("<<<") {
+        } else if self.input().starts_with(b"<<<") {
             self.advance_bytes(3);
         } else {
             return Err(SyntaxError::None);
@@ -816,10 +1023,10 @@ impl<'a> Tokenizer<'a> {
         self.whitespace_only();
         // determine the label type
         let mut doc_ty = DocType::HereDoc;
-        if self.input().starts_with('\'') {
+        if self.input().starts_with(b"'") {
             doc_ty = DocType::NowDoc;
             self.advance_bytes(1);
-        } else if self.input().starts_with('"') {
+        } else if self.input().starts_with(b"\"") {
             doc_ty = DocType::HereDocEncapsed;
             self.advance_bytes(1);
         }
@@ -837,7 +1044,7 @@ impl<'a> Tokenizer<'a> {
             DocType::HereDoc => None,
         };
         if let Some(chr) = required_chr {
-            if self.input().starts_with(chr) {
+            if self.input().starts_with(&[chr as u8]) {
                 self.advance_bytes(1);
             } else {
                 self.state = bak_state_str;
@@ -851,6 +1058,7 @@ impl<'a> Tokenizer<'a> {
         }
 
         // NOWDOC behaves roughly like sq_string and HEREDOC like dq_string
+        let content_start = self.input_pos();
         let mut bytes: Vec<u8> = vec![];
         let mut parts = vec![];
 
@@ -859,44 +1067,54 @@ impl<'a> Tokenizer<'a> {
             _ => false,
         };
 
-        // match characters until we find the required end_tag
+        // the end-tag may directly follow the header (for an empty doc) or a newline,
+        // its indentation (PHP >= 7.3) is removed from every line of the body
         let end_tag = label;
+        let (end_tag_line, indentation) = match find_doc_end_tag(self.input(), &end_tag) {
+            Some((offset, indentation_len)) => (content_start + offset, &self.input()[offset..offset + indentation_len]),
+            None => {
+                let old_pos = self.input_pos() + self.input().len();
+                self.state = bak_state_str;
+                return Err(SyntaxError::Unterminated("Here/Nowdoc", mk_span(self.state.src_pos, old_pos)));
+            }
+        };
+        if indentation.contains(&b' ') && indentation.contains(&b'\t') {
+            return Err(SyntaxError::InvalidIndentation("Invalid indentation - tabs and spaces cannot be mixed".to_owned(),
+                mk_span(end_tag_line, end_tag_line + indentation.len())));
+        }
+        let mut at_end_tag = self.input_pos() == end_tag_line;
+        if !at_end_tag {
+            try!(self.doc_indentation(indentation));
+        }
+        // match characters until we reach the end_tag, code may follow it on the same line
         loop {
-            let end_pos = match self.input()
-                .chars()
+            if at_end_tag {
+                self.advance_bytes(indentation.len() + end_tag.len());
+                break
+            }
+            let end_pos = match self.input_chars()
                 .position(|x| x == '\\' || x == '$' || x == '\n' || x == '{') {
                 Some(end_pos) => end_pos,
                 None => self.input().len(),
             };
-            bytes.extend(self.advance_chars(end_pos).as_bytes());
+            bytes.extend(self.advance_bytes(end_pos));
 
-            match (self.input().chars().nth(0), is_now_doc) {
+            match (self.input_chars().nth(0), is_now_doc) {
                 (Some('\n'), _) => {
                     self.advance_bytes(1);
                     self.state.next_line();
                     // we are done if we are followed by our end-tag
-                    if self.input().starts_with(&end_tag) {
-                        self.advance_bytes(end_tag.len());
-                        // only skip the semicolon for the validation of the here/nowdoc
-                        // but make sure it end's up in the token stream
-                        let old_pos = self.input_pos();
-                        if self.input().starts_with(';') {
-                            self.advance_bytes(1);
-                        }
-                        if !self.newline() {
-                            let old_pos = self.input_pos();
-                            self.state = bak_state_str;
-                            return Err(SyntaxError::Unterminated("Here/Nowdoc: end-tag requires to be followed by a newline", mk_span(self.state.src_pos, old_pos)));
-                        }
-                        self.state.src_pos = old_pos;
-                        break
-                    } else {
+                    at_end_tag = self.input_pos() == end_tag_line;
+                    if !at_end_tag {
                         bytes.push(b'\n');
+                        try!(self.doc_indentation(indentation));
                     }
                 }
-                (Some('\\'), _) => try!(self.str_escape(&mut bytes, is_now_doc)),
-                (Some('$'), false) => self.str_variable(&mut bytes, &mut parts),
-                (Some('{'), false) => self.str_block(&mut bytes, &mut parts, true),
+                (Some('\\'), false) => try!(self.str_escape(&mut bytes, EscapeTy::HereDoc)),
+                (Some('$'), false) => try!(self.str_variable(&mut bytes, &mut parts, content_start)),
+                (Some('{'), false) => self.str_block(&mut bytes, &mut parts, content_start, true),
+                // a nowdoc contains neither escapes nor variables
+                (Some(x), true) if x == '\\' || x == '$' || x == '{' => bytes.extend(self.advance_bytes(1)),
                 _ => {
                     let old_pos = self.input_pos();
                     self.state = bak_state_str;
@@ -905,53 +1123,80 @@ impl<'a> Tokenizer<'a> {
             }
         }
         let current_pos = self.input_pos();
-        if is_now_doc {
-            assert!(parts.is_empty());
-            let ret_token = match String::from_utf8(bytes) {
-                Ok(str_) => Token::ConstantEncapsedString(self.interner.intern(&str_)),
-                Err(err) => Token::BinaryCharSequence(Rc::new(err.into_bytes())),
-            };
-            return Ok(TokenSpan(ret_token, mk_span(bak_state_str.src_pos, current_pos)));
-        }
+        // before PHP 7.3 the end-tag may only be followed by a ';' and a newline
+        let code_follows = {
+            let rest = self.input();
+            let rest = if rest.starts_with(b";") { &rest[1..] } else { rest };
+            !(rest.is_empty() || rest.starts_with(b"\n") || rest.starts_with(b"\r"))
+        };
+        let end_tag = self.interner.intern_bytes(&end_tag);
+        let indentation = self.interner.intern_bytes(indentation);
+        let start_token = if is_now_doc { Token::NowDocStart(end_tag) } else { Token::HereDocStart(end_tag) };
         Ok(self.return_tokens_from_parts(
-            TokenSpan(Token::HereDocStart, mk_span(bak_state_str.src_pos, bak_state_str.src_pos + 1)),
-            TokenSpan(Token::HereDocEnd, mk_span(current_pos - 1, current_pos)),
+            TokenSpan(start_token, mk_span(bak_state_str.src_pos, content_start)),
+            TokenSpan(Token::HereDocEnd(indentation, code_follows), mk_span(end_tag_line, current_pos)),
             bytes, parts
         ))
     }
 
+    /// skip the indentation of the end-tag at the start of a here/nowdoc body line
+    fn doc_indentation(&mut self, indentation: &[u8]) -> Result<(), SyntaxError> {
+        let start = self.input_pos();
+        for &expected in indentation {
+            match self.input().first() {
+                Some(&x) if x == expected => {
+                    self.advance_bytes(1);
+                }
+                Some(&b' ') | Some(&b'\t') => {
+                    return Err(SyntaxError::InvalidIndentation("Invalid indentation - tabs and spaces cannot be mixed".to_owned(),
+                        mk_span(start, self.input_pos() + 1)));
+                }
+                // lines containing only whitespace may be indented less
+                Some(&b'\n') | Some(&b'\r') | None => break,
+                Some(_) => {
+                    let message = format!("Invalid body indentation level (expecting an indentation level of at least {})", indentation.len());
+                    return Err(SyntaxError::InvalidIndentation(message, mk_span(start, self.input_pos())));
+                }
+            }
+        }
+        Ok(())
+    }
+
     /// match a comment
     pub fn match_comments(&mut self) -> Result<TokenSpan, SyntaxError> {
         let old_pos = self.input_pos();
         let mut doc_comment = false;
+        if self.attributes && self.input().starts_with(b"#[") {
+            return Err(SyntaxError::None);
+        }
         // single line comment
-        let start_tokens_count = if self.input().starts_with('#') {
+        let start_tokens_count = if self.input().starts_with(b"#") {
             1
-        } else if self.input().starts_with("//") {
+        } else if self.input().starts_with(b"//") {
             2
         } else {
             0
         };
         let comment = if start_tokens_count > 0 {
             self.advance_bytes(start_tokens_count);
-            let end_pos = match self.input().chars().position(|x| x == '\n') {
+            let end_pos = match self.input_chars().position(|x| x == '\n') {
                 Some(end_pos) => end_pos,
                 None => self.input().len(),
             };
-            self.advance_chars(end_pos)
+            self.advance_bytes(end_pos)
         } else {
             // block comment
-            let start_tokens_count = if self.input().starts_with("/**") {
+            let start_tokens_count = if self.input().starts_with(b"/**") {
                 doc_comment = true;
                 3
-            } else if self.input().starts_with("/*") {
+            } else if self.input().starts_with(b"/*") {
                 2
             } else {
                 0
             };
             if start_tokens_count > 0 {
                 self.advance_bytes(start_tokens_count);
-                let end_pos = match self.input().find("*/") {
+                let end_pos = match find_bytes(self.input(), b"*/") {
                     Some(end_pos) => end_pos,
                     None => {
                         let old_pos = self.state.src_pos;
@@ -965,10 +1210,9 @@ impl<'a> Tokenizer<'a> {
             } else {
                 return Err(SyntaxError::None);
             }
-        }.to_owned();
-        for _ in 0..comment.lines().count().checked_sub(1).unwrap_or(0) {
-            self.state.next_line()
-        }
+        };
+        self.register_newlines(old_pos);
+        let comment = String::from_utf8_lossy(comment).into_owned();
         let mut span = mk_span(old_pos, self.input_pos());
         if doc_comment {
             // For a doc comment the parser'll use the content of Token::Comment as doc_comment
@@ -1014,44 +1258,59 @@ impl<'a> Tokenizer<'a> {
             self.state.state = State::Done;
             return Ok(TokenSpan(Token::End, mk_span(self.code.len(), self.code.len())));
         }
+        // report a BOM, but skip it so that tokenizing can continue afterwards
+        if self.input_pos() == 0 && self.input().starts_with(b"\xEF\xBB\xBF") {
+            self.advance_bytes(3);
+            return Err(SyntaxError::ByteOrderMark(mk_span(0usize, 3usize)));
+        }
         ret_token!(match_token!(self, OpenTagWithEcho, state = InScripting));
         ret_token!(match_token!(self, OpenTag, state = InScripting));
         if self.short_tags {
             ret_token!(match_token_alias!(self, "<?", OpenTag, state = InScripting));
         }
+        if self.asp_tags {
+            ret_token!(match_token_alias!(self, "<%=", OpenTagWithEcho, state = InScripting));
+            ret_token!(match_token_alias!(self, "<%", OpenTag, state = InScripting));
+        }
         // read inline HTML until PHP starttag (keep last)
         let mut end_pos = 0;
         {
-            let mut input = self.input();
-            loop {
-                let (is_php_tag, next_pos) = match input.find("<?") {
-                    None => (false, input.len()),
-                    Some(x) => (true, x),
-                };
-                let is_php_tag = if is_php_tag && self.short_tags {
-                    true
-                } else {
-                    input.starts_with("<?=") || input.starts_with_ci("<?php")
-                };
-                if is_php_tag || next_pos == input.len() {
-                    end_pos += next_pos;
+            let input = self.input();
+            while end_pos < input.len() {
+                match input[end_pos..].iter().position(|&x| x == b'<') {
+                    None => {
+                        end_pos = input.len();
+                        break;
+                    }
+                    Some(x) => end_pos += x,
+                }
+                let input = &input[end_pos..];
+                if input.starts_with(b"<?=") || input.starts_with_ci("<?php") || (self.short_tags && input.starts_with(b"<?")) ||
+                   (self.asp_tags && input.starts_with(b"<%")) {
                     break;
                 }
-                // move on (skip) on <? with short-tags disabled and no PHP "open-suffix" (= or php)
-                input = &input[next_pos + 2..];
+                // skip any other `<` (e.g. <? with short-tags disabled)
+                end_pos += 1;
             }
         }
         if end_pos != 0 {
             let span = mk_span(self.input_pos(), self.input_pos() + end_pos);
             let str_ = self.advance_bytes(end_pos);
-            return Ok(TokenSpan(Token::InlineHtml(self.interner.intern(str_)), span));
+            self.register_newlines(span.start as usize);
+            let ret_token = match str::from_utf8(str_) {
+                Ok(str_) => Token::InlineHtml(self.interner.intern(str_)),
+                Err(_) => Token::BinaryInlineHtml(Rc::new(str_.to_vec())),
+            };
+            return Ok(TokenSpan(ret_token, span));
         }
         Ok(TokenSpan(Token::End, mk_span(self.code.len(), self.code.len())))
     }
 
     /// token scanner for script-seciton
     fn in_scripting_token(&mut self) -> Result<TokenSpan, SyntaxError> {
-        self.whitespace();
+        if let Some(whitespace) = self.whitespace_token() {
+            return Ok(whitespace);
+        }
         // check if we are at the end of the input
         if self.input().is_empty() {
             return Ok(TokenSpan(Token::End, mk_span(self.code.len(), self.code.len())));
@@ -1070,7 +1329,7 @@ impl<'a> Tokenizer<'a> {
         self.state.src_pos = old_pos;
 
         if let Some((label, span)) = self._label() {
-            ret.push(Ok(TokenSpan(Token::String(self.interner.intern(label)), span)));
+            ret.push(Ok(TokenSpan(Token::String(self.interner.intern_bytes(label)), span)));
         }
         self.state.src_pos = old_pos;
         ret.push(self.in_scripting_other_token());
@@ -1103,9 +1362,15 @@ impl<'a> Tokenizer<'a> {
 
     fn in_scripting_other_token(&mut self) -> Result<TokenSpan, SyntaxError> {
         ret_token!(match_token!(self, CloseTag, state = Initial));
+        if self.asp_tags {
+            ret_token!(match_token_alias!(self, "%>", CloseTag, state = Initial));
+        }
         ret_token!(match_token!(self, Exit));
         ret_token!(match_token_alias!(self, "die", Exit));
         ret_token!(match_token!(self, Function));
+        if self.arrow_functions {
+            ret_token!(match_token!(self, Fn));
+        }
         ret_token!(match_token!(self, Const));
         ret_token!(match_token!(self, Return));
         ret_token!({
@@ -1113,10 +1378,11 @@ impl<'a> Tokenizer<'a> {
             if self.input().starts_with_ci(keyword) {
                 let old_pos = self.input_pos();
                 self.advance_bytes(keyword.len());
-                // yield_from submatch
-                self.whitespace();
+                // yield_from submatch, the whitespace only belongs to the token if it's followed by from
+                let whitespace_len = self.input().iter().take_while(|&&x| x == b' ' || x == b'\t' || x == b'\r' || x == b'\n').count();
                 let keyword = "from";
-                if self.input().starts_with_ci(keyword) {
+                if (&self.input()[whitespace_len..]).starts_with_ci(keyword) {
+                    self.whitespace();
                     self.advance_bytes(keyword.len());
                     let span = mk_span(old_pos, self.input_pos());
                     Ok(TokenSpan(Token::YieldFrom, span))
@@ -1149,6 +1415,9 @@ impl<'a> Tokenizer<'a> {
         ret_token!(match_token!(self, As));
         ret_token!(match_token!(self, Switch));
         ret_token!(match_token!(self, EndSwitch));
+        if self.match_expressions {
+            ret_token!(match_token!(self, Match));
+        }
         ret_token!(match_token!(self, Case));
         ret_token!(match_token!(self, Default));
         ret_token!(match_token!(self, Break));
@@ -1162,6 +1431,8 @@ impl<'a> Tokenizer<'a> {
         ret_token!(match_token!(self, Extends));
         ret_token!(match_token!(self, Implements));
         ret_token!(match_token!(self, ObjectOp, state <- LookingForProperty));
+        ret_token!(match_token!(self, NullsafeObjectOp, state <- LookingForProperty));
+        ret_token!(match_token!(self, AttributeStart));
         ret_token!(match_token!(self, ScopeOp));
         ret_token!(match_token!(self, NsSeparator));
         ret_token!(match_token!(self, Ellipsis));
@@ -1171,7 +1442,7 @@ impl<'a> Tokenizer<'a> {
         ret_token!(match_token!(self, Var));
 
         // match cast tokens, all in one-try
-        if self.input().starts_with('(') {
+        if self.input().starts_with(b"(") {
             #[inline]
             fn try_determine_cast_type(self_: &mut Tokenizer) -> Result<TokenSpan, SyntaxError> {
                 ret_token!(match_token!(self_, CastInt));
@@ -1193,7 +1464,7 @@ impl<'a> Tokenizer<'a> {
             self.whitespace_only();
             if let Ok(ret) = try_determine_cast_type(self) {
                 self.whitespace_only();
-                if self.input().starts_with(')') {
+                if self.input().starts_with(b")") {
                     self.advance_bytes(1);
                     return Ok(TokenSpan(ret.0, mk_span(old_pos, self.input_pos())));
                 }
@@ -1277,9 +1548,12 @@ impl<'a> Tokenizer<'a> {
 
     /// token-scanner for looking-for-property state
     fn looking_for_property_token(&mut self) -> Result<TokenSpan, SyntaxError> {
-        self.whitespace();
+        if let Some(whitespace) = self.whitespace_token() {
+            return Ok(whitespace);
+        }
         ret_token!(match_token!(self, ObjectOp));
-        match self._label().map(|(x, span)| (self.interner.intern(x), span)) {
+        ret_token!(match_token!(self, NullsafeObjectOp));
+        match self._label().map(|(x, span)| (self.interner.intern_bytes(x), span)) {
             None => (),
             Some((x, span)) => {
                 state_helper!(pop, self);
@@ -1323,6 +1597,39 @@ mod tests {
         assert_eq!(get_n_tokens(&mut tokenizer, 5), vec![Ok(Token::OpenTag), Ok(Token::ObjectOp), Ok(Token::String("test".into())),
             Ok(Token::ObjectOp), Ok(Token::String("gest2".into()))
         ]);
+        let mut tokenizer = Tokenizer::new("<?php $a?->class?->b ?>");
+        assert_eq!(get_n_tokens(&mut tokenizer, 6), vec![Ok(Token::OpenTag), Ok(Token::Variable("a".into())), Ok(Token::NullsafeObjectOp),
+            Ok(Token::String("class".into())), Ok(Token::NullsafeObjectOp), Ok(Token::String("b".into()))
+        ]);
+    }
+
+    #[test]
+    fn simple_attribute() {
+        let mut tokenizer = Tokenizer::new("<?php #[A] # [B]\n$c");
+        assert_eq!(get_n_tokens(&mut tokenizer, 6), vec![Ok(Token::OpenTag), Ok(Token::AttributeStart), Ok(Token::String("A".into())),
+            Ok(Token::SquareBracketClose), Ok(Token::Comment(" [B]".into())), Ok(Token::Variable("c".into()))
+        ]);
+        let mut tokenizer = Tokenizer::new("<?php #[A]\n$c");
+        tokenizer.set_attributes(false);
+        assert_eq!(get_n_tokens(&mut tokenizer, 3), vec![Ok(Token::OpenTag), Ok(Token::Comment("[A]".into())), Ok(Token::Variable("c".into()))]);
+    }
+
+    #[test]
+    fn simple_fn() {
+        let mut tokenizer = Tokenizer::new("<?php fn fnord");
+        assert_eq!(get_n_tokens(&mut tokenizer, 3), vec![Ok(Token::OpenTag), Ok(Token::Fn), Ok(Token::String("fnord".into()))]);
+        let mut tokenizer = Tokenizer::new("<?php fn");
+        tokenizer.set_arrow_functions(false);
+        assert_eq!(get_n_tokens(&mut tokenizer, 2), vec![Ok(Token::OpenTag), Ok(Token::String("fn".into()))]);
+    }
+
+    #[test]
+    fn simple_match() {
+        let mut tokenizer = Tokenizer::new("<?php match");
+        assert_eq!(get_n_tokens(&mut tokenizer, 2), vec![Ok(Token::OpenTag), Ok(Token::Match)]);
+        let mut tokenizer = Tokenizer::new("<?php match");
+        tokenizer.set_match_expressions(false);
+        assert_eq!(get_n_tokens(&mut tokenizer, 2), vec![Ok(Token::OpenTag), Ok(Token::String("match".into()))]);
     }
 
     #[test]
@@ -1352,6 +1659,49 @@ mod tests {
         assert_eq!(get_n_tokens(&mut tokenizer, 3), vec![Ok(Token::InlineHtml("a?>b".into())), Ok(Token::OpenTag), Ok(Token::CloseTag)]);
     }
 
+    #[test]
+    fn case_insensitive_keywords() {
+        let mut tokenizer = Tokenizer::new("<?PHP ECHO __CLASS__ Or (INT)");
+        assert_eq!(get_n_tokens(&mut tokenizer, 5),
+                   vec![Ok(Token::OpenTag), Ok(Token::Echo), Ok(Token::MagicClass), Ok(Token::LogicalOr), Ok(Token::CastInt)]);
+    }
+
+    #[test]
+    fn non_utf8_source() {
+        // ISO-8859-1 encoded source, labels may contain any byte within 0x80-0xff
+        let mut tokenizer = Tokenizer::from_bytes(b"caf\xe9<?php $caf\xe9 = 'caf\xe9' . \"\xe9$a\";");
+        assert_eq!(get_n_tokens(&mut tokenizer, 10),
+                   vec![Ok(Token::BinaryInlineHtml(Rc::new(b"caf\xe9".to_vec()))),
+                        Ok(Token::OpenTag),
+                        Ok(Token::Variable("caf\u{10FFE9}".into())),
+                        Ok(Token::Equal),
+                        Ok(Token::BinaryCharSequence(Rc::new(b"caf\xe9".to_vec()))),
+                        Ok(Token::Dot),
+                        Ok(Token::DoubleQuote),
+                        Ok(Token::BinaryCharSequence(Rc::new(b"\xe9".to_vec()))),
+                        Ok(Token::Variable("a".into())),
+                        Ok(Token::DoubleQuote)]);
+        // distinct bytes give distinct names
+        let mut tokenizer = Tokenizer::from_bytes(b"<?php $caf\xe9 $caf\xe8");
+        let tokens = get_n_tokens(&mut tokenizer, 3);
+        assert_ne!(tokens[1], tokens[2]);
+        match tokens[2] {
+            Ok(Token::Variable(ref name)) => assert_eq!(name.to_bytes(), b"caf\xe8".to_vec()),
+            ref x => panic!("expected variable, got {:?}", x),
+        }
+        let mut tokenizer = Tokenizer::new("<?php $转注 = 1;");
+        let tokens: Vec<_> = (0..3).map(|_| tokenizer.next_token().unwrap()).collect();
+        assert_eq!(tokens[1].0, Token::Variable("转注".into()));
+        assert_eq!(tokens[1].1, mk_span(6usize, 13usize));
+    }
+
+    #[test]
+    fn byte_order_mark() {
+        let mut tokenizer = Tokenizer::new("\u{FEFF}<?php 1");
+        assert_eq!(get_n_tokens(&mut tokenizer, 3),
+                   vec![Err(SyntaxError::ByteOrderMark(mk_span(0usize, 3usize))), Ok(Token::OpenTag), Ok(Token::Int(1))]);
+    }
+
     #[test]
     fn simple_lnum() {
         let mut tokenizer = Tokenizer::new("<?php  42");
@@ -1393,6 +1743,19 @@ mod tests {
                    vec![Ok(Token::OpenTag), Ok(Token::Double(1.1))]);
     }
 
+    #[test]
+    fn numeric_separators() {
+        let mut tokenizer = Tokenizer::new("<?php 1_000_000 0x7f_ff 0b1_0 01_0 1_0.2_5");
+        assert_eq!(get_n_tokens(&mut tokenizer, 6),
+                   vec![Ok(Token::OpenTag), Ok(Token::Int(1000000)), Ok(Token::Int(0x7fff)), Ok(Token::Int(2)), Ok(Token::Int(8)),
+                        Ok(Token::Double(10.25))]);
+        // a separator has to be followed by a digit
+        let mut tokenizer = Tokenizer::new("<?php 1__0 1_ 0x_1");
+        assert_eq!(get_n_tokens(&mut tokenizer, 7),
+                   vec![Ok(Token::OpenTag), Ok(Token::Int(1)), Ok(Token::String("__0".into())), Ok(Token::Int(1)), Ok(Token::String("_".into())),
+                        Ok(Token::Int(0)), Ok(Token::String("x_1".into()))]);
+    }
+
     #[test]
     fn dq_string() {
         let mut tokenizer = Tokenizer::new("<?php \"\"");
@@ -1427,6 +1790,50 @@ mod tests {
                         Ok(Token::ConstantEncapsedString("testhallo\\nwelt 'g'".into()))]);
     }
 
+    #[test]
+    fn string_escapes() {
+        fn escaped(src: &str) -> Result<Token, SyntaxError> {
+            let mut tokenizer = Tokenizer::new(src);
+            get_n_tokens(&mut tokenizer, 3).pop().unwrap()
+        }
+        let dq_table: &[(&str, &[u8])] = &[
+            (r#"\n\r\t\v\f\e"#, b"\n\r\t\x0B\x0C\x1B"),
+            (r#"\\\$\""#, b"\\$\""),
+            (r#"\'\`\q\{"#, b"\\'\\`\\q\\{"),
+            (r#"\x41\x4a\x4Ag\xg\X7"#, b"AJJg\\xg\x07"),
+            (r#"\101\0\08\477\7777"#, b"A\x00\x008\x3F\xFF7"),
+            (r#"\u{41}\u{00e7}\u{1F600}\u{0000000041}\u41"#, "Aç😀A\\u41".as_bytes()),
+            (r#"\u{D800}"#, b"\xED\xA0\x80"),
+        ];
+        for &(input, expected) in dq_table {
+            let token = match String::from_utf8(expected.to_vec()) {
+                Ok(str_) => Token::ConstantEncapsedString(str_.into()),
+                Err(err) => Token::BinaryCharSequence(Rc::new(err.into_bytes())),
+            };
+            assert_eq!(escaped(&format!("<?php \"{}\"", input)), Ok(token));
+        }
+        // the quote character is only escapable within its own kind of string
+        assert_eq!(escaped("<?php `\\`\\\"`"), Ok(Token::ConstantEncapsedString("`\\\"".into())));
+        assert_eq!(escaped("<?php <<<EOT\n\\\"\\`\\101\\u{41}\nEOT;\n"), Ok(Token::ConstantEncapsedString("\\\"\\`AA".into())));
+        // single-quoted strings and nowdocs only know \\ and \' or nothing at all
+        let mut tokenizer = Tokenizer::new("<?php '\\\\\\'\\n\\101' <<<'EOT'\n\\\\ \\101 $a {$a}\nEOT;\n");
+        assert_eq!(get_n_tokens(&mut tokenizer, 4),
+                   vec![Ok(Token::OpenTag),
+                        Ok(Token::ConstantEncapsedString("\\'\\n\\101".into())),
+                        Ok(Token::NowDocStart("EOT".into())),
+                        Ok(Token::ConstantEncapsedString("\\\\ \\101 $a {$a}".into()))]);
+
+        let mut tokenizer = Tokenizer::new("<?php \"a\\u{}\"");
+        assert_eq!(get_n_tokens(&mut tokenizer, 2)[1],
+                   Err(SyntaxError::InvalidEscapeSequence("Invalid UTF-8 codepoint escape sequence", mk_span(8usize, 12usize))));
+        let mut tokenizer = Tokenizer::new("<?php \"\\u{4g}\"");
+        assert_eq!(get_n_tokens(&mut tokenizer, 2)[1],
+                   Err(SyntaxError::InvalidEscapeSequence("Invalid UTF-8 codepoint escape sequence", mk_span(7usize, 11usize))));
+        let mut tokenizer = Tokenizer::new("<?php \"\\u{110000}\"");
+        assert_eq!(get_n_tokens(&mut tokenizer, 2)[1],
+                   Err(SyntaxError::InvalidEscapeSequence("Invalid UTF-8 codepoint escape sequence: Codepoint too large", mk_span(7usize, 17usize))));
+    }
+
     #[test]
     fn dq_string_var() {
         let mut tokenizer = Tokenizer::new("<?php \"ab $world cd\"");
@@ -1441,6 +1848,10 @@ mod tests {
         assert_eq!(get_n_tokens(&mut tokenizer, 6), vec![Ok(Token::OpenTag), Ok(Token::DoubleQuote), Ok(Token::Variable("world".into())), Ok(Token::ObjectOp),
             Ok(Token::String("ab".into())), Ok(Token::DoubleQuote),
         ]);
+        let mut tokenizer = Tokenizer::new("<?php \"$world?->ab\"");
+        assert_eq!(get_n_tokens(&mut tokenizer, 6), vec![Ok(Token::OpenTag), Ok(Token::DoubleQuote), Ok(Token::Variable("world".into())),
+            Ok(Token::NullsafeObjectOp), Ok(Token::String("ab".into())), Ok(Token::DoubleQuote),
+        ]);
         let mut tokenizer = Tokenizer::new("<?php \"$world->ab->cd\"");
         assert_eq!(get_n_tokens(&mut tokenizer, 7),
                    vec![Ok(Token::OpenTag),
@@ -1463,6 +1874,43 @@ mod tests {
                         Ok(Token::CurlyBracesClose)]);
     }
 
+    #[test]
+    fn dq_string_var_offset() {
+        let mut tokenizer = Tokenizer::new("<?php \"$a[0]$a[-1]\"");
+        assert_eq!(get_n_tokens(&mut tokenizer, 12),
+                   vec![Ok(Token::OpenTag),
+                        Ok(Token::DoubleQuote),
+                        Ok(Token::Variable("a".into())),
+                        Ok(Token::SquareBracketOpen),
+                        Ok(Token::NumString("0".into())),
+                        Ok(Token::SquareBracketClose),
+                        Ok(Token::Variable("a".into())),
+                        Ok(Token::SquareBracketOpen),
+                        Ok(Token::Minus),
+                        Ok(Token::NumString("1".into())),
+                        Ok(Token::SquareBracketClose),
+                        Ok(Token::DoubleQuote)]);
+        let mut tokenizer = Tokenizer::new("<?php \"$a[key] $a[$i]]\"");
+        assert_eq!(get_n_tokens(&mut tokenizer, 13),
+                   vec![Ok(Token::OpenTag),
+                        Ok(Token::DoubleQuote),
+                        Ok(Token::Variable("a".into())),
+                        Ok(Token::SquareBracketOpen),
+                        Ok(Token::String("key".into())),
+                        Ok(Token::SquareBracketClose),
+                        Ok(Token::ConstantEncapsedString(" ".into())),
+                        Ok(Token::Variable("a".into())),
+                        Ok(Token::SquareBracketOpen),
+                        Ok(Token::Variable("i".into())),
+                        Ok(Token::SquareBracketClose),
+                        Ok(Token::ConstantEncapsedString("]".into())),
+                        Ok(Token::DoubleQuote)]);
+        let mut tokenizer = Tokenizer::new("<?php \"$a[0x1f]\"");
+        assert_eq!(get_n_tokens(&mut tokenizer, 6)[4], Ok(Token::NumString("0x1f".into())));
+        let mut tokenizer = Tokenizer::new("<?php \"$a[1 ]\"");
+        assert_eq!(get_n_tokens(&mut tokenizer, 2)[1], Err(SyntaxError::Unterminated("variable offset in string", mk_span(9usize, 11usize))));
+    }
+
     #[test]
     fn backquote() {
         let mut tokenizer = Tokenizer::new("<?php `ab $world cd`");
@@ -1473,6 +1921,14 @@ mod tests {
                         Ok(Token::Variable("world".into())),
                         Ok(Token::ConstantEncapsedString(" cd".into())),
                         Ok(Token::Backquote)]);
+        let mut tokenizer = Tokenizer::new("<?php `echo \"$a\\\\\"`");
+        assert_eq!(get_n_tokens(&mut tokenizer, 6),
+                   vec![Ok(Token::OpenTag),
+                        Ok(Token::Backquote),
+                        Ok(Token::ConstantEncapsedString("echo \"".into())),
+                        Ok(Token::Variable("a".into())),
+                        Ok(Token::ConstantEncapsedString("\\\"".into())),
+                        Ok(Token::Backquote)]);
     }
 
     #[test]
@@ -1480,22 +1936,55 @@ mod tests {
         let mut tokenizer = Tokenizer::new("<?php <<<EOT\ntest\nEOT;\n");
         assert_eq!(get_n_tokens(&mut tokenizer, 4),
                    vec![Ok(Token::OpenTag),
-                        Ok(Token::HereDocStart),
+                        Ok(Token::HereDocStart("EOT".into())),
                         Ok(Token::ConstantEncapsedString("test".into())),
-                        Ok(Token::HereDocEnd)]);
+                        Ok(Token::HereDocEnd("".into(), false))]);
         let mut tokenizer = Tokenizer::new("<?php <<<\"EOT\"\nte\\tst\nEOT;\n");
         assert_eq!(get_n_tokens(&mut tokenizer, 4),
                    vec![Ok(Token::OpenTag),
-                        Ok(Token::HereDocStart),
+                        Ok(Token::HereDocStart("EOT".into())),
                         Ok(Token::ConstantEncapsedString("te\tst".into())),
-                        Ok(Token::HereDocEnd)]);
+                        Ok(Token::HereDocEnd("".into(), false))]);
     }
 
     #[test]
     fn nowdoc() {
         let mut tokenizer = Tokenizer::new("<?php <<<'EOT'\nte\\tst\nEOT;\n");
-        assert_eq!(get_n_tokens(&mut tokenizer, 2),
-                   vec![Ok(Token::OpenTag), Ok(Token::ConstantEncapsedString("te\\tst".into()))]);
+        assert_eq!(get_n_tokens(&mut tokenizer, 5),
+                   vec![Ok(Token::OpenTag),
+                        Ok(Token::NowDocStart("EOT".into())),
+                        Ok(Token::ConstantEncapsedString("te\\tst".into())),
+                        Ok(Token::HereDocEnd("".into(), false)),
+                        Ok(Token::SemiColon)]);
+    }
+
+    #[test]
+    fn flexible_heredoc() {
+        let mut tokenizer = Tokenizer::new("<?php <<<EOT\n    a\n\n      b\n    EOT, 1");
+        assert_eq!(get_n_tokens(&mut tokenizer, 6),
+                   vec![Ok(Token::OpenTag),
+                        Ok(Token::HereDocStart("EOT".into())),
+                        Ok(Token::ConstantEncapsedString("a\n\n  b".into())),
+                        Ok(Token::HereDocEnd("    ".into(), true)),
+                        Ok(Token::Comma),
+                        Ok(Token::Int(1))]);
+        let mut tokenizer = Tokenizer::new("<?php <<<'EOT'\n\ta\n\tEOT;");
+        assert_eq!(get_n_tokens(&mut tokenizer, 5),
+                   vec![Ok(Token::OpenTag),
+                        Ok(Token::NowDocStart("EOT".into())),
+                        Ok(Token::ConstantEncapsedString("a".into())),
+                        Ok(Token::HereDocEnd("\t".into(), false)),
+                        Ok(Token::SemiColon)]);
+        // the label followed by other label characters isn't the end-tag
+        let mut tokenizer = Tokenizer::new("<?php <<<'EOT'\nEOTX\nEOT;");
+        assert_eq!(get_n_tokens(&mut tokenizer, 3)[2], Ok(Token::ConstantEncapsedString("EOTX".into())));
+        let mut tokenizer = Tokenizer::new("<?php <<<EOT\n a\n  EOT;");
+        assert_eq!(get_n_tokens(&mut tokenizer, 2)[1],
+                   Err(SyntaxError::InvalidIndentation("Invalid body indentation level (expecting an indentation level of at least 2)".to_owned(),
+                       mk_span(13usize, 14usize))));
+        let mut tokenizer = Tokenizer::new("<?php <<<EOT\n\ta\n  EOT;");
+        assert_eq!(get_n_tokens(&mut tokenizer, 2)[1],
+                   Err(SyntaxError::InvalidIndentation("Invalid indentation - tabs and spaces cannot be mixed".to_owned(), mk_span(13usize, 14usize))));
     }
 
     #[test]
@@ -1530,6 +2019,54 @@ mod tests {
         }
     }
 
+    #[test]
+    fn string_fragment_spans() {
+        fn spans(src: &str) -> Vec<(Token, u32, u32)> {
+            let mut tokenizer = Tokenizer::new(src);
+            let mut tokens = vec![];
+            loop {
+                match tokenizer.next_token().unwrap() {
+                    TokenSpan(Token::End, _) => return tokens,
+                    TokenSpan(tok, span) => tokens.push((tok, span.start, span.end)),
+                }
+            }
+        }
+        // the spans of fragments cover their escape sequences in the source
+        assert_eq!(spans(r#"<?php b"\x41\t$a{$b}\u{1F600}c""#), vec![
+            (Token::OpenTag, 0, 5),
+            (Token::DoubleQuote, 6, 8),
+            (Token::ConstantEncapsedString("A\t".into()), 8, 14),
+            (Token::Variable("a".into()), 14, 16),
+            (Token::CurlyBracesOpen, 16, 17),
+            (Token::Variable("b".into()), 17, 19),
+            (Token::CurlyBracesClose, 19, 20),
+            (Token::ConstantEncapsedString("\u{1F600}c".into()), 20, 30),
+            (Token::DoubleQuote, 30, 31),
+        ]);
+        assert_eq!(spans("<?php <<<EOT\n\\$ ${a}\nEOT;\n"), vec![
+            (Token::OpenTag, 0, 5),
+            (Token::HereDocStart("EOT".into()), 6, 13),
+            (Token::ConstantEncapsedString("$ ".into()), 13, 16),
+            (Token::DollarCurlyBracesOpen, 16, 18),
+            (Token::String("a".into()), 18, 19),
+            (Token::CurlyBracesClose, 19, 20),
+            // the last newline isn't part of the heredoc's value
+            (Token::HereDocEnd("".into(), false), 21, 24),
+            (Token::SemiColon, 24, 25),
+        ]);
+    }
+
+    #[test]
+    fn line_map() {
+        let src = "<?php <<<EOT\na\nEOT;\n/*\n*/\n$b;";
+        let mut tokenizer = Tokenizer::new(src);
+        while tokenizer.next_token().unwrap().0 != Token::End {}
+        let line_map = tokenizer.state.external.line_map;
+        let lines: Vec<_> = (0..6).map(|x| line_map.line(x).0).collect();
+        assert_eq!(lines, vec![0, 13, 15, 20, 23, 26]);
+        assert_eq!(line_map.line_from_position(src.len() - 1), 5);
+    }
+
     // TODO: use own error type?
     // TODO: error handling tests
 }
@@ -1,3 +1,4 @@
+use std::borrow::{Borrow, Cow};
 use std::rc::Rc;
 use interner::RcStr;
 
@@ -31,6 +32,12 @@ pub enum SyntaxError {
     None,
     Unterminated(&'static str, Span),
     UnknownCharacter(Span),
+    /// an invalid escape sequence within a string, such as "\u{}"
+    InvalidEscapeSequence(&'static str, Span),
+    /// a UTF-8 byte order mark at the start of the file, which PHP would send as output
+    ByteOrderMark(Span),
+    /// a line of a heredoc or nowdoc which isn't indented like its end-tag
+    InvalidIndentation(String, Span),
 }
 
 impl SyntaxError {
@@ -38,7 +45,22 @@ impl SyntaxError {
         match *self {
             SyntaxError::None => unimplemented!(),
             SyntaxError::Unterminated(_, ref span) |
-            SyntaxError::UnknownCharacter(ref span) => span.clone(),
+            SyntaxError::UnknownCharacter(ref span) |
+            SyntaxError::InvalidEscapeSequence(_, ref span) |
+            SyntaxError::ByteOrderMark(ref span) |
+            SyntaxError::InvalidIndentation(_, ref span) => span.clone(),
+        }
+    }
+
+    /// Get a human readable description of the error
+    pub fn message(&self) -> Cow<'static, str> {
+        match *self {
+            SyntaxError::None => "syntax error".into(),
+            SyntaxError::Unterminated(what, _) => format!("unterminated {}", what).into(),
+            SyntaxError::UnknownCharacter(_) => "unknown character".into(),
+            SyntaxError::InvalidEscapeSequence(message, _) => message.into(),
+            SyntaxError::ByteOrderMark(_) => "UTF-8 byte order mark (BOM) found, it would be sent as output".into(),
+            SyntaxError::InvalidIndentation(ref message, _) => message.clone().into(),
         }
     }
 }
@@ -79,8 +101,12 @@ pub enum Token {
     /// `
     Backquote,
     DoubleQuote,
-    HereDocStart,
-    HereDocEnd,
+    /// the start of a heredoc, containing its label
+    HereDocStart(RcStr),
+    /// the start of a nowdoc, containing its label. its contents are a single literal
+    NowDocStart(RcStr),
+    /// the end-tag of a heredoc or nowdoc, containing its indentation and whether code follows it on its line (both PHP >= 7.3)
+    HereDocEnd(RcStr, bool),
     // php tokens
     OpenTagWithEcho,
     OpenTag,
@@ -88,6 +114,7 @@ pub enum Token {
     CloseTag,
     Exit,
     Function,
+    Fn,
     Const,
     Return,
     Yield,
@@ -113,6 +140,7 @@ pub enum Token {
     As,
     Switch,
     EndSwitch,
+    Match,
     Case,
     Default,
     Break,
@@ -127,6 +155,10 @@ pub enum Token {
     Implements,
     /// T_OBJECT_OPERATOR
     ObjectOp,
+    /// T_NULLSAFE_OBJECT_OPERATOR
+    NullsafeObjectOp,
+    /// `#[`, which starts an attribute group (T_ATTRIBUTE)
+    AttributeStart,
     /// T_PAAMAYIM_NEKUDOTAYIM
     ScopeOp,
     NsSeparator,
@@ -198,12 +230,18 @@ pub enum Token {
     Int(i64),
     Double(f64),
     Comment(RcStr),
+    /// spaces, tabs and newlines, only emitted when the tokenizer keeps trivia (see `Lexer`)
+    Whitespace(RcStr),
     /// likely an arbitrary identifier
     String(RcStr),
     /// like 'test', constant encapsed string
     ConstantEncapsedString(RcStr),
+    /// a numeric offset of a variable within a string, e.g. the `0` in "$a[0]"
+    NumString(RcStr),
     BinaryCharSequence(Rc<Vec<u8>>),
     InlineHtml(RcStr),
+    /// inline HTML which isn't valid UTF-8
+    BinaryInlineHtml(Rc<Vec<u8>>),
     // magic-tokens
     MagicClass,
     MagicTrait,
@@ -224,7 +262,7 @@ impl Token {
             | Token::Do | Token::While | Token::EndWhile
             | Token::For | /*Token::EndFor |*/ Token::Foreach | Token::EndForeach | Token::Declare | Token::EndDeclare | Token::As | Token::Try | Token::Catch | Token::Finally
             | Token::Throw | Token::Use | Token::Insteadof | Token::Global | Token::Var | Token::Unset | Token::Isset | Token::Empty | Token::Continue | Token::Goto
-            | Token::Function | Token::Const | Token::Return | Token::Print | Token::Yield | Token::List | Token::Switch | Token::EndSwitch | Token::Case | Token::Default
+            | Token::Function | Token::Fn | Token::Const | Token::Return | Token::Print | Token::Yield | Token::List | Token::Switch | Token::EndSwitch | Token::Match | Token::Case | Token::Default
             | Token::Break | Token::Array | Token::Callable | Token::Extends | Token::Implements | Token::Namespace | Token::Trait | Token::Interface | Token::Class
             | Token::MagicClass | Token::MagicTrait | Token::MagicFunction | Token::MagicMethod | Token::MagicLine | Token::MagicFile | Token::MagicDir
             | Token::MagicNamespace => true,
@@ -232,17 +270,57 @@ impl Token {
         }
     }
 
+    /// keywords which may be used as the name of a class member, e.g. `$a->list()` or `const DEFAULT = 1`
+    #[inline]
+    pub fn is_semi_reserved(&self) -> bool {
+        match *self {
+            Token::Static | Token::Abstract | Token::Final | Token::Private | Token::Protected | Token::Public => true,
+            _ => self.is_reserved_non_modifier(),
+        }
+    }
+
     /// Get the string representation of a token
+    /// this panics for tokens which carry a value (e.g. `Token::Variable`), use `description` for these
     #[inline]
     pub fn repr(&self) -> &'static str {
         match *self {
+            Token::SemiColon => ";",
+            Token::Colon => ":",
+            Token::Comma => ",",
+            Token::Dot => ".",
+            Token::SquareBracketOpen => "[",
+            Token::SquareBracketClose => "]",
+            Token::ParenthesesOpen => "(",
+            Token::ParenthesesClose => ")",
+            Token::BwOr => "|",
+            Token::BwXor => "^",
+            Token::Ampersand => "&",
+            Token::Plus => "+",
+            Token::Minus => "-",
+            Token::Div => "/",
+            Token::Mul => "*",
+            Token::Equal => "=",
+            Token::Mod => "%",
+            Token::BoolNot => "!",
+            Token::BwNot => "~",
+            Token::Dollar => "$",
+            Token::Lt => "<",
+            Token::Gt => ">",
+            Token::QuestionMark => "?",
+            Token::Silence => "@",
+            Token::DollarCurlyBracesOpen => "${",
+            Token::Backquote => "`",
+            Token::DoubleQuote => "\"",
             Token::OpenTagWithEcho => "<?=",
             Token::OpenTag => "<?php",
             Token::CloseTag => "?>",
             Token::Exit => "exit",
             Token::Function => "function",
+            Token::Fn => "fn",
             Token::Const => "const",
             Token::Return => "return",
+            Token::Yield => "yield",
+            Token::YieldFrom => "yield from",
             Token::Try => "try",
             Token::Catch => "catch",
             Token::Finally => "finally",
@@ -264,6 +342,7 @@ impl Token {
             Token::As => "as",
             Token::Switch => "switch",
             Token::EndSwitch => "endswitch",
+            Token::Match => "match",
             Token::Case => "case",
             Token::Default => "default",
             Token::Break => "break",
@@ -277,6 +356,8 @@ impl Token {
             Token::Extends => "extends",
             Token::Implements => "implements",
             Token::ObjectOp => "->",
+            Token::NullsafeObjectOp => "?->",
+            Token::AttributeStart => "#[",
             Token::ScopeOp => "::",
             Token::NsSeparator => "\\",
             Token::Ellipsis => "...",
@@ -353,7 +434,37 @@ impl Token {
             Token::MagicFile => "__FILE__",
             Token::MagicDir => "__DIR__",
             Token::MagicNamespace => "__NAMESPACE__",
-            _ => unimplemented!(),
+            Token::End | Token::HereDocStart(_) | Token::NowDocStart(_) | Token::HereDocEnd(_, _) | Token::Variable(_) | Token::Int(_) | Token::Double(_)
+            | Token::Comment(_) | Token::Whitespace(_) | Token::String(_) | Token::ConstantEncapsedString(_) | Token::NumString(_)
+            | Token::BinaryCharSequence(_) | Token::InlineHtml(_) | Token::BinaryInlineHtml(_) => unimplemented!(),
+        }
+    }
+}
+
+impl Token {
+    /// describes a token in messages, e.g. `$a` for a variable or "end of file".
+    /// this isn't the source text of the token: quotes, escape sequences and the kind of comment aren't kept
+    /// and the end of a heredoc doesn't contain its label, use the span of the token to get the source text
+    pub fn description(&self) -> Cow<'static, str> {
+        match *self {
+            Token::End => Cow::Borrowed("end of file"),
+            Token::HereDocStart(ref label) => Cow::Owned(format!("<<<{}", label.borrow() as &str)),
+            Token::NowDocStart(ref label) => Cow::Owned(format!("<<<'{}'", label.borrow() as &str)),
+            Token::HereDocEnd(_, _) => Cow::Borrowed("end of heredoc"),
+            Token::Variable(ref name) => Cow::Owned(format!("${}", name.borrow() as &str)),
+            Token::Int(i) => Cow::Owned(i.to_string()),
+            Token::Double(d) => Cow::Owned(format!("{:?}", d)),
+            Token::Comment(ref comment) => Cow::Owned(format!("/*{}*/", comment.borrow() as &str)),
+            Token::CastInt | Token::CastDouble | Token::CastString | Token::CastArray | Token::CastObject
+            | Token::CastBool | Token::CastUnset => Cow::Owned(format!("({})", self.repr())),
+            Token::Whitespace(ref str_) |
+            Token::String(ref str_) |
+            Token::ConstantEncapsedString(ref str_) |
+            Token::NumString(ref str_) |
+            Token::InlineHtml(ref str_) => Cow::Owned((str_.borrow() as &str).to_owned()),
+            Token::BinaryCharSequence(ref bytes) |
+            Token::BinaryInlineHtml(ref bytes) => Cow::Owned(String::from_utf8_lossy(bytes).into_owned()),
+            _ => Cow::Borrowed(self.repr()),
         }
     }
 }
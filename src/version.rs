@@ -0,0 +1,223 @@
+//! PHP language versions and the syntax which was introduced or removed by them
+use std::fmt;
+use std::str::FromStr;
+use ast::Ty;
+
+/// A PHP language version (e.g. `7.1`), patch releases don't change the syntax
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PhpVersion {
+    pub major: u32,
+    pub minor: u32,
+}
+
+impl PhpVersion {
+    /// the oldest language version syntax is tracked for,
+    /// syntax without a `Feature` is assumed to be available there
+    pub const OLDEST: PhpVersion = PhpVersion { major: 5, minor: 6 };
+    /// the newest language version the parser understands
+    pub const LATEST: PhpVersion = PhpVersion { major: 8, minor: 2 };
+
+    pub fn new(major: u32, minor: u32) -> PhpVersion {
+        PhpVersion {
+            major: major,
+            minor: minor,
+        }
+    }
+}
+
+impl Default for PhpVersion {
+    fn default() -> PhpVersion {
+        PhpVersion::LATEST
+    }
+}
+
+impl fmt::Display for PhpVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+impl FromStr for PhpVersion {
+    type Err = &'static str;
+
+    /// parses `major.minor`, an additional patch version (`7.1.3`) is ignored
+    fn from_str(s: &str) -> Result<PhpVersion, &'static str> {
+        let mut parts = s.trim().split('.');
+        let mut next = || match parts.next() {
+            Some(x) => x.parse::<u32>().map_err(|_| "invalid PHP version, expected e.g. 7.1"),
+            None => Err("invalid PHP version, expected e.g. 7.1"),
+        };
+        let major = try!(next());
+        let minor = try!(next());
+        let _ = next();
+        Ok(PhpVersion::new(major, minor))
+    }
+}
+
+/// Syntax which is only available in a range of PHP versions
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Feature {
+    /// `function (int $a)`
+    ScalarTypes,
+    /// `function (): Foo`
+    ReturnTypes,
+    /// `$a ?? $b`
+    NullCoalesce,
+    /// `$a <=> $b`
+    Spaceship,
+    /// `yield from $a`
+    YieldFrom,
+    /// `?Foo`
+    NullableTypes,
+    /// `function (): void`
+    VoidType,
+    /// `function (iterable $a)`
+    IterableType,
+    /// `catch (A | B $e)`
+    MultiCatch,
+    /// `function (object $a)`
+    ObjectType,
+    /// an indented heredoc/nowdoc end-tag, which may be followed by code on its line
+    FlexibleHeredoc,
+    /// `fn($a) => $a * 2`
+    ArrowFunctions,
+    /// `public ?Foo $a;`
+    TypedProperties,
+    /// `[...$a, ...$b]`
+    ArraySpread,
+    /// `1_000_000`
+    NumericSeparators,
+    /// `match ($a) { 1, 2 => 'a', default => 'b' }`
+    Match,
+    /// `$a?->b()`
+    NullsafeOperator,
+    /// `foo(limit: 10)`
+    NamedArguments,
+    /// `$a::class`
+    ClassNameOnObjects,
+    /// `#[Route('/x')]`
+    Attributes,
+    /// `int|string`
+    UnionTypes,
+    /// `function __construct(private $a)`
+    ConstructorPromotion,
+    /// `A&B`
+    IntersectionTypes,
+    /// `(A&B)|null`
+    DnfTypes,
+    /// `enum Suit: string { case Hearts = 'H'; }`
+    Enums,
+    /// `public readonly int $a`
+    ReadonlyProperties,
+    /// `function a(): never`
+    NeverType,
+    /// `strlen(...)`
+    FirstClassCallables,
+    /// `function a($b = new B)`
+    NewInInitializers,
+    /// `readonly class A {}`
+    ReadonlyClasses,
+    /// `<%`, `<%=` and `%>`
+    AspTags,
+    /// `(unset) $a`
+    UnsetCast,
+    /// `foo(&$a)`
+    CallTimePassByRef,
+}
+
+impl Feature {
+    /// the first version supporting this syntax
+    pub fn introduced(&self) -> Option<PhpVersion> {
+        match *self {
+            Feature::ScalarTypes | Feature::ReturnTypes | Feature::NullCoalesce | Feature::Spaceship | Feature::YieldFrom => {
+                Some(PhpVersion::new(7, 0))
+            }
+            Feature::NullableTypes | Feature::VoidType | Feature::IterableType | Feature::MultiCatch => Some(PhpVersion::new(7, 1)),
+            Feature::ObjectType => Some(PhpVersion::new(7, 2)),
+            Feature::FlexibleHeredoc => Some(PhpVersion::new(7, 3)),
+            Feature::ArrowFunctions | Feature::TypedProperties | Feature::ArraySpread | Feature::NumericSeparators => Some(PhpVersion::new(7, 4)),
+            Feature::Match | Feature::NullsafeOperator | Feature::NamedArguments | Feature::ClassNameOnObjects | Feature::Attributes |
+            Feature::UnionTypes | Feature::ConstructorPromotion => {
+                Some(PhpVersion::new(8, 0))
+            }
+            Feature::IntersectionTypes | Feature::Enums | Feature::ReadonlyProperties | Feature::NeverType |
+            Feature::FirstClassCallables | Feature::NewInInitializers => Some(PhpVersion::new(8, 1)),
+            Feature::DnfTypes | Feature::ReadonlyClasses => Some(PhpVersion::new(8, 2)),
+            Feature::CallTimePassByRef | Feature::AspTags | Feature::UnsetCast => None,
+        }
+    }
+
+    /// the first version which no longer supports this syntax
+    pub fn removed(&self) -> Option<PhpVersion> {
+        match *self {
+            Feature::CallTimePassByRef => Some(PhpVersion::new(5, 4)),
+            Feature::AspTags => Some(PhpVersion::new(7, 0)),
+            Feature::UnsetCast => Some(PhpVersion::new(8, 0)),
+            _ => None,
+        }
+    }
+
+    /// the feature required to use a type in a declaration
+    pub fn of_ty(ty: &Ty) -> Option<Feature> {
+        match *ty {
+            Ty::Bool | Ty::String | Ty::Double | Ty::Float | Ty::Int => Some(Feature::ScalarTypes),
+            Ty::Object(None) => Some(Feature::ObjectType),
+            Ty::Void => Some(Feature::VoidType),
+            Ty::Iterable => Some(Feature::IterableType),
+            Ty::Never => Some(Feature::NeverType),
+            _ => None,
+        }
+    }
+
+    pub fn is_supported_by(&self, version: PhpVersion) -> bool {
+        self.introduced().map_or(true, |v| version >= v) && self.removed().map_or(true, |v| version < v)
+    }
+
+    /// describe why this feature can't be used when targeting the given version
+    pub fn unsupported_message(&self, target: PhpVersion) -> String {
+        match self.removed() {
+            Some(removed) if target >= removed => format!("{}: removed in PHP {} (targeting PHP {})", self, removed, target),
+            _ => format!("{}: requires PHP {} (targeting PHP {})", self, self.introduced().unwrap_or(target), target),
+        }
+    }
+}
+
+impl fmt::Display for Feature {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", match *self {
+            Feature::ScalarTypes => "scalar type declarations",
+            Feature::ReturnTypes => "return type declarations",
+            Feature::NullCoalesce => "the null coalescing operator",
+            Feature::Spaceship => "the spaceship operator",
+            Feature::YieldFrom => "generator delegation (yield from)",
+            Feature::NullableTypes => "nullable types",
+            Feature::VoidType => "the void return type",
+            Feature::IterableType => "the iterable type",
+            Feature::MultiCatch => "catching multiple exception types",
+            Feature::ObjectType => "the object type",
+            Feature::FlexibleHeredoc => "the flexible heredoc and nowdoc syntax",
+            Feature::ArrowFunctions => "arrow functions",
+            Feature::TypedProperties => "typed properties",
+            Feature::ArraySpread => "unpacking inside arrays",
+            Feature::NumericSeparators => "numeric literal separators",
+            Feature::Match => "match expressions",
+            Feature::NullsafeOperator => "the nullsafe operator",
+            Feature::NamedArguments => "named arguments",
+            Feature::ClassNameOnObjects => "::class on objects",
+            Feature::Attributes => "attributes",
+            Feature::UnionTypes => "union types",
+            Feature::ConstructorPromotion => "constructor property promotion",
+            Feature::IntersectionTypes => "intersection types",
+            Feature::DnfTypes => "disjunctive normal form types",
+            Feature::Enums => "enums",
+            Feature::ReadonlyProperties => "readonly properties",
+            Feature::NeverType => "the never return type",
+            Feature::FirstClassCallables => "the first-class callable syntax",
+            Feature::NewInInitializers => "new in initializers",
+            Feature::ReadonlyClasses => "readonly classes",
+            Feature::AspTags => "ASP-style tags",
+            Feature::UnsetCast => "the (unset) cast",
+            Feature::CallTimePassByRef => "call-time pass-by-reference",
+        })
+    }
+}
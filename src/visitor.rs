@@ -0,0 +1,280 @@
+//! Traversal of the AST
+//!
+//! Implement `Visitor` and override the methods for the nodes of interest,
+//! the respective `walk_*` function continues the traversal into the children of a node.
+use ast::{Argument, Attribute, Block, Decl, Expr, Expr_, FunctionDecl, InterpolatedPart, Member, Stmt, Stmt_, TypeExpr, Variable};
+
+pub trait Visitor {
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        walk_stmt(self, stmt)
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        walk_expr(self, expr)
+    }
+
+    /// functions, methods and closures
+    fn visit_function_decl(&mut self, decl: &FunctionDecl) {
+        walk_function_decl(self, decl)
+    }
+
+    fn visit_member(&mut self, member: &Member) {
+        walk_member(self, member)
+    }
+
+    fn visit_attribute(&mut self, attribute: &Attribute) {
+        walk_attribute(self, attribute)
+    }
+
+    /// parameter, return and property types
+    fn visit_ty(&mut self, _ty: &TypeExpr) {}
+}
+
+pub fn walk_stmts<V: Visitor + ?Sized>(visitor: &mut V, stmts: &[Stmt]) {
+    for stmt in stmts {
+        visitor.visit_stmt(stmt);
+    }
+}
+
+pub fn walk_block<V: Visitor + ?Sized>(visitor: &mut V, block: &Block) {
+    walk_stmts(visitor, &block.0)
+}
+
+fn walk_opt_expr<V: Visitor + ?Sized>(visitor: &mut V, expr: &Option<Box<Expr>>) {
+    if let Some(ref expr) = *expr {
+        visitor.visit_expr(expr);
+    }
+}
+
+fn walk_exprs<V: Visitor + ?Sized>(visitor: &mut V, exprs: &[Expr]) {
+    for expr in exprs {
+        visitor.visit_expr(expr);
+    }
+}
+
+fn walk_args<V: Visitor + ?Sized>(visitor: &mut V, args: &[Argument]) {
+    for arg in args {
+        visitor.visit_expr(&arg.value);
+    }
+}
+
+fn walk_attributes<V: Visitor + ?Sized>(visitor: &mut V, attributes: &[Attribute]) {
+    for attribute in attributes {
+        visitor.visit_attribute(attribute);
+    }
+}
+
+pub fn walk_attribute<V: Visitor + ?Sized>(visitor: &mut V, attribute: &Attribute) {
+    walk_args(visitor, &attribute.args)
+}
+
+fn walk_variable<V: Visitor + ?Sized>(visitor: &mut V, var: &Variable) {
+    if let Variable::Fetch(ref expr) = *var {
+        visitor.visit_expr(expr);
+    }
+}
+
+pub fn walk_stmt<V: Visitor + ?Sized>(visitor: &mut V, stmt: &Stmt) {
+    match stmt.0 {
+        Stmt_::None | Stmt_::Use(_) | Stmt_::Goto(_) => (),
+        Stmt_::Block(ref block) => walk_block(visitor, block),
+        Stmt_::Decl(ref decl) => walk_decl(visitor, decl),
+        Stmt_::Expr(ref expr) => visitor.visit_expr(expr),
+        Stmt_::Echo(ref exprs) | Stmt_::Unset(ref exprs) => walk_exprs(visitor, exprs),
+        Stmt_::Return(ref expr) | Stmt_::Break(ref expr) | Stmt_::Continue(ref expr) => walk_opt_expr(visitor, expr),
+        Stmt_::If(ref cond, ref block, ref else_block) => {
+            visitor.visit_expr(cond);
+            walk_block(visitor, block);
+            walk_block(visitor, else_block);
+        }
+        Stmt_::While(ref cond, ref block) => {
+            visitor.visit_expr(cond);
+            walk_block(visitor, block);
+        }
+        Stmt_::DoWhile(ref block, ref cond) => {
+            walk_block(visitor, block);
+            visitor.visit_expr(cond);
+        }
+        Stmt_::For(ref init, ref cond, ref end_of_loop, ref block) => {
+            walk_exprs(visitor, init);
+            walk_exprs(visitor, cond);
+            walk_exprs(visitor, end_of_loop);
+            walk_block(visitor, block);
+        }
+        Stmt_::ForEach(ref base, ref key, ref value, ref block) => {
+            visitor.visit_expr(base);
+            walk_opt_expr(visitor, key);
+            visitor.visit_expr(value);
+            walk_block(visitor, block);
+        }
+        Stmt_::Try(ref block, ref catch_clauses, ref finally_block) => {
+            walk_block(visitor, block);
+            for clause in catch_clauses {
+                walk_block(visitor, &clause.block);
+            }
+            if let Some(ref finally_block) = *finally_block {
+                walk_block(visitor, finally_block);
+            }
+        }
+        Stmt_::Throw(ref expr) => visitor.visit_expr(expr),
+        Stmt_::Switch(ref base, ref cases) => {
+            visitor.visit_expr(base);
+            for case in cases {
+                walk_exprs(visitor, &case.conds);
+                walk_block(visitor, &case.block);
+            }
+        }
+    }
+}
+
+pub fn walk_decl<V: Visitor + ?Sized>(visitor: &mut V, decl: &Decl) {
+    match *decl {
+        Decl::Namespace(_) | Decl::Label(_) => (),
+        Decl::GlobalFunction(_, ref decl) => visitor.visit_function_decl(decl),
+        Decl::Class(ref decl) => {
+            walk_attributes(visitor, &decl.attributes);
+            for member in &decl.members {
+                visitor.visit_member(member);
+            }
+        }
+        Decl::Enum(ref decl) => {
+            walk_attributes(visitor, &decl.attributes);
+            for member in &decl.members {
+                visitor.visit_member(member);
+            }
+        }
+        Decl::Interface(ref attributes, _, _, ref members) | Decl::Trait(ref attributes, _, ref members) => {
+            walk_attributes(visitor, attributes);
+            for member in members {
+                visitor.visit_member(member);
+            }
+        }
+        Decl::StaticVars(ref vars) => {
+            for &(_, ref default) in vars {
+                if let Some(ref default) = *default {
+                    visitor.visit_expr(default);
+                }
+            }
+        }
+        Decl::GlobalVars(ref vars) => {
+            for var in vars {
+                walk_variable(visitor, var);
+            }
+        }
+    }
+}
+
+pub fn walk_member<V: Visitor + ?Sized>(visitor: &mut V, member: &Member) {
+    match *member {
+        Member::Constant(ref attributes, _, _, ref expr) => {
+            walk_attributes(visitor, attributes);
+            visitor.visit_expr(expr);
+        }
+        Member::Property(ref attributes, _, ref ty, _, ref default) => {
+            walk_attributes(visitor, attributes);
+            if let Some(ref ty) = *ty {
+                visitor.visit_ty(ty);
+            }
+            if let Some(ref default) = *default {
+                visitor.visit_expr(default);
+            }
+        }
+        Member::Method(_, _, ref decl) => visitor.visit_function_decl(decl),
+        Member::TraitUse(_, _) => (),
+        Member::EnumCase(ref attributes, _, ref value) => {
+            walk_attributes(visitor, attributes);
+            if let Some(ref value) = *value {
+                visitor.visit_expr(value);
+            }
+        }
+    }
+}
+
+pub fn walk_function_decl<V: Visitor + ?Sized>(visitor: &mut V, decl: &FunctionDecl) {
+    walk_attributes(visitor, &decl.attributes);
+    for param in &decl.params {
+        walk_attributes(visitor, &param.attributes);
+        if let Some(ref ty) = param.ty {
+            visitor.visit_ty(ty);
+        }
+        if let Some(ref default) = param.default {
+            visitor.visit_expr(default);
+        }
+    }
+    if let Some(ref ty) = decl.ret_ty {
+        visitor.visit_ty(ty);
+    }
+    if let Some(ref body) = decl.body {
+        walk_block(visitor, body);
+    }
+}
+
+pub fn walk_expr<V: Visitor + ?Sized>(visitor: &mut V, expr: &Expr) {
+    match expr.0 {
+        Expr_::Path(_) | Expr_::String(_) | Expr_::BinaryString(_) | Expr_::Int(_, _) | Expr_::Double(_, _) => (),
+        Expr_::Array(ref items) | Expr_::List(ref items) => {
+            for &(ref key, ref value) in items {
+                if let Some(ref key) = *key {
+                    visitor.visit_expr(key);
+                }
+                visitor.visit_expr(value);
+            }
+        }
+        Expr_::Variable(ref var) => walk_variable(visitor, var),
+        Expr_::Reference(ref expr) | Expr_::Clone(ref expr) | Expr_::Empty(ref expr) |
+        Expr_::Include(_, ref expr) | Expr_::Unpack(ref expr) | Expr_::UnaryOp(_, ref expr) |
+        Expr_::Cast(_, ref expr) | Expr_::FirstClassCallable(ref expr) | Expr_::YieldFrom(ref expr) => visitor.visit_expr(expr),
+        Expr_::Isset(ref exprs) => walk_exprs(visitor, exprs),
+        Expr_::Exit(ref expr) => walk_opt_expr(visitor, expr),
+        Expr_::Yield(ref key, ref value) => {
+            walk_opt_expr(visitor, key);
+            walk_opt_expr(visitor, value);
+        }
+        Expr_::ArrayIdx(ref base, ref idxs) => {
+            visitor.visit_expr(base);
+            for idx in idxs {
+                if let Some(ref idx) = *idx {
+                    visitor.visit_expr(idx);
+                }
+            }
+        }
+        Expr_::ObjMember(ref base, ref args, _) | Expr_::StaticMember(ref base, ref args) => {
+            visitor.visit_expr(base);
+            walk_exprs(visitor, args);
+        }
+        Expr_::Call(ref base, ref args) | Expr_::New(ref base, ref args) => {
+            visitor.visit_expr(base);
+            walk_args(visitor, args);
+        }
+        Expr_::BinaryOp(_, ref left, ref right) | Expr_::InstanceOf(ref left, ref right) |
+        Expr_::Assign(ref left, ref right) | Expr_::CompoundAssign(ref left, _, ref right) |
+        Expr_::AssignRef(ref left, ref right) => {
+            visitor.visit_expr(left);
+            visitor.visit_expr(right);
+        }
+        Expr_::Interpolated(_, ref parts) => {
+            for part in parts {
+                if let InterpolatedPart::Expr(ref expr) = *part {
+                    visitor.visit_expr(expr);
+                }
+            }
+        }
+        Expr_::Function(ref decl, _) => visitor.visit_function_decl(decl),
+        Expr_::ArrowFunction(ref decl, ref body, _) => {
+            visitor.visit_function_decl(decl);
+            visitor.visit_expr(body);
+        }
+        Expr_::Match(ref subject, ref arms) => {
+            visitor.visit_expr(subject);
+            for arm in arms {
+                walk_exprs(visitor, &arm.conds);
+                visitor.visit_expr(&arm.expr);
+            }
+        }
+        Expr_::TernaryIf(ref cond, ref case_true, ref case_else) => {
+            visitor.visit_expr(cond);
+            walk_opt_expr(visitor, case_true);
+            visitor.visit_expr(case_else);
+        }
+    }
+}